@@ -1,7 +1,9 @@
+use std::path::{Path, PathBuf};
 use std::process::Command;
 use walkdir::WalkDir;
-use crate::classpath::{ClasspathBuilder, JavaEnvironment};
+use crate::classpath::ClasspathBuilder;
 use crate::coordinates::Coordinate;
+use crate::effective::EffectivePom;
 use crate::manifest::Manifest;
 use crate::repository::Repository;
 
@@ -9,23 +11,99 @@ pub struct BuildOptions {
     pub debug: bool,
 }
 
+/// Where a project keeps its sources, resources and compiled classes
+#[derive(Clone, Debug)]
+pub struct Layout {
+    pub sources: PathBuf,
+    pub resources: Vec<PathBuf>,
+    pub classes: PathBuf,
+}
+
+impl Layout {
+    pub fn standard(root: &Path) -> Self {
+        Self {
+            sources: root.join("src").join("main").join("java"),
+            resources: vec![root.join("src").join("main").join("resources")],
+            classes: root.join("target").join("classes"),
+        }
+    }
+
+    /// The directories a pom.xml overrides, relative paths being relative to `root`
+    pub fn from_pom(root: &Path, pom: &EffectivePom) -> Self {
+        let resolve = |dir: &str| {
+            let dir = ["${project.basedir}/", "${basedir}/"].iter()
+                .find_map(|prefix| dir.strip_prefix(prefix))
+                .unwrap_or(dir);
+            root.join(dir)
+        };
+
+        let mut layout = Self::standard(root);
+        if let Some(dir) = &pom.source_directory {
+            layout.sources = resolve(dir);
+        }
+        if !pom.resource_directories.is_empty() {
+            layout.resources = pom.resource_directories.iter().map(|dir| resolve(dir)).collect();
+        }
+        layout
+    }
+}
+
 pub struct Builder {
     manifest: Manifest,
     repository: Repository,
+    layout: Layout,
+    resolved: Option<Vec<(Coordinate, String)>>,
+    release: Option<String>,
 }
 
 impl Builder {
-    pub fn new(manifest: Manifest, repository: Repository) -> Self {
+    pub fn new(manifest: Manifest, repository: Repository, layout: Layout) -> Self {
         Self {
             manifest,
             repository,
+            layout,
+            resolved: None,
+            release: None,
         }
     }
 
-    fn find_java_files(&self) -> anyhow::Result<Vec<String>> {
-        let src_dir = std::env::current_dir()?.join("src").join("main").join("java");
+    /// Compiles against these instead of just the manifest's direct dependencies
+    pub fn with_resolved(mut self, resolved: Vec<(Coordinate, String)>) -> Self {
+        self.resolved = Some(resolved);
+        self
+    }
+
+    /// Passes `--release` to javac, "1.8" style versions are shortened to "8"
+    pub fn with_release(mut self, release: Option<String>) -> Self {
+        self.release = release;
+        self
+    }
+
+    pub fn manifest(&self) -> &Manifest {
+        &self.manifest
+    }
+
+    pub fn repository(&self) -> &Repository {
+        &self.repository
+    }
+
+    pub fn layout(&self) -> &Layout {
+        &self.layout
+    }
 
-        let java_files: Vec<String> = WalkDir::new(src_dir)
+    /// What goes on the classpath, the resolved graph when there is one
+    pub fn dependencies(&self) -> anyhow::Result<Vec<(Coordinate, String)>> {
+        if let Some(resolved) = &self.resolved {
+            return Ok(resolved.clone());
+        }
+
+        self.manifest.deps.iter()
+            .map(|(coord_str, dep_spec)| Ok((dep_spec.coordinate(coord_str)?, dep_spec.version().to_string())))
+            .collect()
+    }
+
+    fn find_java_files(&self) -> anyhow::Result<Vec<String>> {
+        let java_files: Vec<String> = WalkDir::new(&self.layout.sources)
             .into_iter()
             .filter_map(|e| e.ok())
             .filter(|e| e.path().extension().and_then(|s| s.to_str()) == Some("java"))
@@ -35,33 +113,33 @@ impl Builder {
         Ok(java_files)
     }
 
-    pub fn build(&self, options: BuildOptions) -> anyhow::Result<()> {
+    fn classpath(&self) -> anyhow::Result<String> {
         let mut cp_builder = ClasspathBuilder::new(self.repository.clone());
-
-        for (coord_str, dep_spec) in &self.manifest.deps {
-            let coord = Coordinate::parse(coord_str)?;
-            let version = match dep_spec {
-                crate::manifest::DepSpec::Simple(v) => v,
-                crate::manifest::DepSpec::Detailed { version, .. } => version,
-            };
-            cp_builder.with_dep(&coord, version);
+        for (coord, version) in self.dependencies()? {
+            cp_builder.with_dep(&coord, &version);
         }
 
-        let classpath = cp_builder.build();
+        Ok(cp_builder.build())
+    }
+
+    pub fn build(&self, options: BuildOptions) -> anyhow::Result<()> {
+        let classpath = self.classpath()?;
 
         let java_files = self.find_java_files()?;
         if java_files.is_empty() {
-            anyhow::bail!("no java source files found in src/main/java");
+            anyhow::bail!("no java source files found in {}", self.layout.sources.display());
         }
 
-        let target_dir = std::env::current_dir()?.join("target").join("classes");
-        std::fs::create_dir_all(&target_dir)?;
+        let target_dir = &self.layout.classes;
+        std::fs::create_dir_all(target_dir)?;
 
         let mut javac = Command::new("javac");
-        javac.args([
-            "-d", target_dir.to_str().unwrap(),
-            "-cp", &classpath
-        ]);
+        javac.arg("-d").arg(target_dir).args(["-cp", &classpath]);
+
+        // javac only takes the short form, maven.compiler.target is often "1.8"
+        if let Some(release) = &self.release {
+            javac.args(["--release", release.strip_prefix("1.").unwrap_or(release)]);
+        }
 
         // Add debug info if requested
         if options.debug {
@@ -77,4 +155,79 @@ impl Builder {
 
         Ok(())
     }
-}
\ No newline at end of file
+
+    /// Generates API docs for src/main/java into `out`
+    pub fn javadoc(&self, out: &Path) -> anyhow::Result<()> {
+        let classpath = self.classpath()?;
+        let java_files = self.find_java_files()?;
+        if java_files.is_empty() {
+            anyhow::bail!("no java source files found in {}", self.layout.sources.display());
+        }
+
+        std::fs::create_dir_all(out)?;
+        let status = Command::new("javadoc")
+            .args(["-quiet", "-d"])
+            .arg(out)
+            .args(["-cp", &classpath])
+            .args(&java_files)
+            .status()?;
+        if !status.success() {
+            anyhow::bail!("javadoc failed with status: {}", status);
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::effective::ModelBuilder;
+
+    #[test]
+    fn test_layout_from_pom() -> anyhow::Result<()> {
+        let pom = ModelBuilder::new().build(r#"
+            <project>
+                <groupId>com.ourcorp</groupId>
+                <artifactId>ledger</artifactId>
+                <version>1.0.0</version>
+                <build>
+                    <sourceDirectory>${project.basedir}/src</sourceDirectory>
+                    <resources><resource><directory>conf</directory></resource></resources>
+                </build>
+            </project>
+        "#)?;
+
+        let root = Path::new("/work/ledger");
+        let layout = Layout::from_pom(root, &pom);
+        assert_eq!(layout.sources, root.join("src"));
+        assert_eq!(layout.resources, vec![root.join("conf")]);
+        assert_eq!(layout.classes, root.join("target").join("classes"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_dependencies() -> anyhow::Result<()> {
+        let manifest: Manifest = toml::from_str(r#"
+            [project]
+            name = "ledger"
+            version = "1.0.0"
+
+            [deps]
+            "com.google.guava:guava" = "32.1.2-jre"
+        "#)?;
+        let builder = Builder::new(manifest, Repository::new(PathBuf::from("/tmp/m2")), Layout::standard(Path::new("/work/ledger")));
+
+        // without a resolved graph only the direct dependencies go on the classpath
+        let guava = Coordinate::parse("com.google.guava:guava")?;
+        assert_eq!(builder.dependencies()?, vec![(guava.clone(), "32.1.2-jre".to_string())]);
+
+        let failureaccess = Coordinate::parse("com.google.guava:failureaccess")?;
+        let resolved = vec![(guava, "32.1.2-jre".to_string()), (failureaccess, "1.0.1".to_string())];
+        let builder = builder.with_resolved(resolved.clone());
+        assert_eq!(builder.dependencies()?, resolved);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,360 @@
+use std::collections::HashSet;
+use std::fs::{self, File};
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use walkdir::WalkDir;
+
+use crate::coordinates::Coordinate;
+use crate::download::ArtifactSource;
+use crate::repository::coordinate_from_layout;
+
+/// What the index knows about one file of one artifact version
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct CacheEntry {
+    pub sha256: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub source: Option<ArtifactSource>,
+}
+
+/// User-level artifact store shared by every project on the machine.
+///
+/// File contents live once under `objects/<2 hex>/<sha256>` and `index/`
+/// mirrors the Maven layout, mapping each artifact file to the object that
+/// holds it. Project repositories get hardlinks to the objects, falling back
+/// to a plain copy across filesystems.
+#[derive(Clone, Debug)]
+pub struct ArtifactCache {
+    root: PathBuf,
+}
+
+pub fn sha256_file(path: &Path) -> anyhow::Result<String> {
+    let mut file = File::open(path)?;
+    let mut hasher = Sha256::new();
+    let mut buf = [0u8; 64 * 1024];
+
+    loop {
+        let n = file.read(&mut buf)?;
+        if n == 0 {
+            break;
+        }
+        hasher.update(&buf[..n]);
+    }
+
+    Ok(hex::encode(hasher.finalize()))
+}
+
+/// Parses ages like `30d`, `12h`, `2w`, `45m` or `90s`
+pub fn parse_age(input: &str) -> anyhow::Result<Duration> {
+    let input = input.trim();
+    let split = input.find(|c: char| !c.is_ascii_digit()).unwrap_or(input.len());
+    let (amount, unit) = input.split_at(split);
+
+    let amount: u64 = amount.parse()
+        .map_err(|_| anyhow::anyhow!("invalid age '{}', expected something like 30d", input))?;
+
+    let seconds = match unit {
+        "s" => 1,
+        "m" => 60,
+        "h" => 60 * 60,
+        "d" | "" => 24 * 60 * 60,
+        "w" => 7 * 24 * 60 * 60,
+        _ => anyhow::bail!("invalid age unit '{}', expected one of s, m, h, d, w", unit),
+    };
+
+    Ok(Duration::from_secs(amount * seconds))
+}
+
+pub fn format_size(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["KiB", "MiB", "GiB", "TiB"];
+
+    if bytes < 1024 {
+        return format!("{} B", bytes);
+    }
+
+    let mut size = bytes as f64 / 1024.0;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    format!("{:.1} {}", size, UNITS[unit])
+}
+
+/// What `ArtifactCache::clean` removed, or would remove on a dry run
+#[derive(Debug, Default)]
+pub struct CleanReport {
+    pub entries: usize,
+    pub objects: usize,
+    pub bytes: u64,
+}
+
+// Hardlink when we can, otherwise copy. On Linux fs::copy goes through
+// copy_file_range, which reflinks on filesystems that support it.
+fn link_or_copy(from: &Path, to: &Path) -> io::Result<()> {
+    if fs::hard_link(from, to).is_ok() {
+        return Ok(());
+    }
+    fs::copy(from, to).map(|_| ())
+}
+
+impl ArtifactCache {
+    pub fn new(root: PathBuf) -> Self {
+        Self { root }
+    }
+
+    /// `$GALLADE_CACHE_DIR`, else `$XDG_CACHE_HOME/gallade`, else `~/.cache/gallade`
+    pub fn default_root() -> Option<PathBuf> {
+        let non_empty = |name: &str| std::env::var_os(name).filter(|v| !v.is_empty());
+
+        if let Some(dir) = non_empty("GALLADE_CACHE_DIR") {
+            return Some(PathBuf::from(dir));
+        }
+        if let Some(dir) = non_empty("XDG_CACHE_HOME") {
+            return Some(PathBuf::from(dir).join("gallade"));
+        }
+        if let Some(dir) = non_empty("LOCALAPPDATA") {
+            return Some(PathBuf::from(dir).join("gallade"));
+        }
+        non_empty("HOME").map(|home| PathBuf::from(home).join(".cache").join("gallade"))
+    }
+
+    pub fn open_default() -> Option<Self> {
+        Self::default_root().map(Self::new)
+    }
+
+    pub fn objects_dir(&self) -> PathBuf {
+        self.root.join("objects")
+    }
+
+    pub fn index_dir(&self) -> PathBuf {
+        self.root.join("index")
+    }
+
+    fn object_path(&self, sha256: &str) -> PathBuf {
+        self.objects_dir().join(&sha256[..2]).join(sha256)
+    }
+
+    fn index_path(&self, relative: &Path) -> PathBuf {
+        let mut name = relative.file_name().unwrap_or_default().to_os_string();
+        name.push(".json");
+        self.index_dir().join(relative).with_file_name(name)
+    }
+
+    /// The entry for a Maven-layout path, as long as its object is still present
+    pub fn lookup(&self, relative: &Path) -> Option<CacheEntry> {
+        let content = fs::read_to_string(self.index_path(relative)).ok()?;
+        let entry: CacheEntry = serde_json::from_str(&content).ok()?;
+        self.object_path(&entry.sha256).exists().then_some(entry)
+    }
+
+    /// Adds `file` to the store under `relative` and returns its entry
+    pub fn insert(&self, relative: &Path, file: &Path, source: Option<&ArtifactSource>) -> anyhow::Result<CacheEntry> {
+        let sha256 = sha256_file(file)?;
+
+        let object = self.object_path(&sha256);
+        if !object.exists() {
+            fs::create_dir_all(object.parent().unwrap())?;
+            match link_or_copy(file, &object) {
+                Ok(()) => {}
+                // another gallade process stored the same content first
+                Err(e) if e.kind() == io::ErrorKind::AlreadyExists => {}
+                Err(e) => return Err(e.into()),
+            }
+        }
+
+        let entry = CacheEntry {
+            sha256,
+            source: source.cloned(),
+        };
+
+        let index = self.index_path(relative);
+        fs::create_dir_all(index.parent().unwrap())?;
+        fs::write(index, serde_json::to_string(&entry)?)?;
+
+        Ok(entry)
+    }
+
+    /// Materializes the object behind `relative` at `dest`, replacing whatever was there
+    pub fn link(&self, relative: &Path, entry: &CacheEntry, dest: &Path) -> anyhow::Result<()> {
+        if let Some(parent) = dest.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        if dest.exists() {
+            fs::remove_file(dest)?;
+        }
+
+        link_or_copy(&self.object_path(&entry.sha256), dest)?;
+
+        // the index entry's mtime doubles as last-used time for `cache clean`
+        File::options()
+            .write(true)
+            .open(self.index_path(relative))?
+            .set_modified(SystemTime::now())?;
+
+        Ok(())
+    }
+
+    /// Every indexed artifact file with the size of the object behind it
+    pub fn entries(&self) -> Vec<(Coordinate, String, CacheEntry, u64)> {
+        let mut entries = Vec::new();
+
+        for file in WalkDir::new(self.index_dir()).into_iter().filter_map(|e| e.ok()) {
+            let Some(relative) = file.path().strip_prefix(self.index_dir()).ok() else {
+                continue;
+            };
+            let relative = relative.with_extension("");
+            let Some((coord, version)) = coordinate_from_layout(&relative) else {
+                continue;
+            };
+
+            if let Some(entry) = self.lookup(&relative) {
+                let size = fs::metadata(self.object_path(&entry.sha256)).map(|m| m.len()).unwrap_or(0);
+                entries.push((coord, version, entry, size));
+            }
+        }
+
+        entries.sort_by_key(|(coord, version, _, _)| format!("{}:{}", coord, version));
+        entries
+    }
+
+    /// Drops index entries that haven't been used for `older_than`, then every
+    /// object no remaining entry points at. Projects keep their own hardlinks,
+    /// so nothing already installed breaks.
+    pub fn clean(&self, older_than: Duration, dry_run: bool) -> anyhow::Result<CleanReport> {
+        let cutoff = SystemTime::now() - older_than;
+        let mut report = CleanReport::default();
+        let mut referenced = HashSet::new();
+
+        for file in WalkDir::new(self.index_dir()).into_iter().filter_map(|e| e.ok()) {
+            if !file.file_type().is_file() {
+                continue;
+            }
+
+            let stale = file.metadata()?.modified()? < cutoff;
+            if stale {
+                report.entries += 1;
+                if !dry_run {
+                    fs::remove_file(file.path())?;
+                }
+                continue;
+            }
+
+            let entry: Option<CacheEntry> = fs::read_to_string(file.path()).ok()
+                .and_then(|content| serde_json::from_str(&content).ok());
+            if let Some(entry) = entry {
+                referenced.insert(entry.sha256);
+            }
+        }
+
+        for file in WalkDir::new(self.objects_dir()).into_iter().filter_map(|e| e.ok()) {
+            if !file.file_type().is_file() {
+                continue;
+            }
+
+            let sha256 = file.file_name().to_string_lossy();
+            if !referenced.contains(sha256.as_ref()) {
+                report.objects += 1;
+                report.bytes += file.metadata()?.len();
+                if !dry_run {
+                    fs::remove_file(file.path())?;
+                }
+            }
+        }
+
+        Ok(report)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_insert_and_link() -> anyhow::Result<()> {
+        let temp = TempDir::new()?;
+        let cache = ArtifactCache::new(temp.path().join("cache"));
+        let relative = Path::new("com/google/guava/guava/31.1-jre/guava-31.1-jre.jar");
+
+        let downloaded = temp.path().join("project-a").join("guava-31.1-jre.jar");
+        fs::create_dir_all(downloaded.parent().unwrap())?;
+        fs::write(&downloaded, b"guava bytes")?;
+
+        assert!(cache.lookup(relative).is_none());
+        let entry = cache.insert(relative, &downloaded, None)?;
+        assert_eq!(entry.sha256, hex::encode(Sha256::digest(b"guava bytes")));
+
+        let found = cache.lookup(relative).unwrap();
+        assert_eq!(found.sha256, entry.sha256);
+
+        let dest = temp.path().join("project-b").join("guava-31.1-jre.jar");
+        cache.link(relative, &found, &dest)?;
+        assert_eq!(fs::read(&dest)?, b"guava bytes");
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::MetadataExt;
+            assert_eq!(fs::metadata(&dest)?.ino(), fs::metadata(&downloaded)?.ino());
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_lookup_ignores_missing_object() -> anyhow::Result<()> {
+        let temp = TempDir::new()?;
+        let cache = ArtifactCache::new(temp.path().to_path_buf());
+        let relative = Path::new("org/slf4j/slf4j-api/1.7.36/slf4j-api-1.7.36.jar");
+
+        let file = temp.path().join("slf4j-api-1.7.36.jar");
+        fs::write(&file, b"slf4j")?;
+        cache.insert(relative, &file, None)?;
+
+        fs::remove_dir_all(cache.objects_dir())?;
+        assert!(cache.lookup(relative).is_none());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_clean_older_than() -> anyhow::Result<()> {
+        let temp = TempDir::new()?;
+        let cache = ArtifactCache::new(temp.path().join("cache"));
+
+        let old = Path::new("org/slf4j/slf4j-api/1.7.36/slf4j-api-1.7.36.jar");
+        let fresh = Path::new("com/google/guava/guava/31.1-jre/guava-31.1-jre.jar");
+        for (relative, content) in [(old, "old"), (fresh, "fresh")] {
+            let file = temp.path().join(relative.file_name().unwrap());
+            fs::write(&file, content)?;
+            cache.insert(relative, &file, None)?;
+        }
+
+        File::options()
+            .write(true)
+            .open(cache.index_path(old))?
+            .set_modified(SystemTime::now() - parse_age("45d")?)?;
+
+        let report = cache.clean(parse_age("30d")?, true)?;
+        assert_eq!((report.entries, report.objects, report.bytes), (1, 1, 3));
+        assert!(cache.lookup(old).is_some());
+
+        cache.clean(parse_age("30d")?, false)?;
+        assert!(cache.lookup(old).is_none());
+        assert!(cache.lookup(fresh).is_some());
+        assert_eq!(cache.entries().len(), 1);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_age() {
+        assert_eq!(parse_age("30d").unwrap(), Duration::from_secs(30 * 86400));
+        assert_eq!(parse_age("2w").unwrap(), Duration::from_secs(14 * 86400));
+        assert_eq!(parse_age("12h").unwrap(), Duration::from_secs(12 * 3600));
+        assert!(parse_age("soon").is_err());
+        assert!(parse_age("3y").is_err());
+    }
+}
@@ -4,6 +4,7 @@ use crate::coordinates::Coordinate;
 use crate::repository::{ArtifactKind, Repository};
 
 
+#[allow(dead_code)]
 pub type Classpath = String;
 
 #[derive(Debug, Clone)]
@@ -40,6 +41,11 @@ impl ClasspathBuilder {
     }
 
     pub fn with_dep(&mut self, coord: &Coordinate, version: &str) -> &Self {
+        // zips, executables and the like are fetched but never go on the classpath
+        if !coord.is_jar() {
+            return self;
+        }
+
         let jar_path = self.repository.get_artifact_path(coord, version, ArtifactKind::Binary);
         if !jar_path.exists() {
             println!("{} is not a valid path, skipping", jar_path.to_string_lossy());
@@ -53,6 +59,7 @@ impl ClasspathBuilder {
         self
     }
 
+    #[allow(dead_code)]
     pub fn with_repo(&mut self, repo: Repository) -> &Self {
         self.repository = repo;
         self
@@ -63,38 +70,38 @@ impl ClasspathBuilder {
         self.paths
             .iter()
             .map(|e| e.to_string_lossy().into_owned())
-            .collect::<Vec<(String)>>()
+            .collect::<Vec<String>>()
             .join(sep)
     }
 }
 
+#[allow(dead_code)]
 pub struct JavaEnvironment {
     java_home: PathBuf,
     pub classpath: String
 }
 
+#[allow(dead_code)]
 impl JavaEnvironment {
     pub fn new(classpath: String) -> anyhow::Result<Self> {
-        let mut java_home = PathBuf::new();
-        if let Ok(path) = env::var("JAVA_HOME") {
-            java_home = PathBuf::from(path);
+        let java_home = if let Ok(path) = env::var("JAVA_HOME") {
+            PathBuf::from(path)
         } else {
-            if let Ok(output) = std::process::Command::new("java")
+            let output = std::process::Command::new("java")
                 .arg("-XshowSettings:properties")
                 .arg("-version")
-                .output() {
-                let output = String::from_utf8_lossy(&output.stderr);
-                for line in output.lines() {
-                    if line.contains("java.home") {
-                        if let Some(home) = line.split('=').nth(1) {
-                            java_home = PathBuf::from(home.trim());
-                        } else {
-                            anyhow::bail!("couldn't parse java.home from java -version output");
-                        }
-                    }
-                }
+                .output()
+                .map_err(|_| anyhow::anyhow!("JAVA_HOME not set"))?;
+
+            let output = String::from_utf8_lossy(&output.stderr);
+            let home = output.lines()
+                .find(|line| line.contains("java.home"))
+                .ok_or_else(|| anyhow::anyhow!("JAVA_HOME not set"))?;
+
+            match home.split('=').nth(1) {
+                Some(home) => PathBuf::from(home.trim()),
+                None => anyhow::bail!("couldn't parse java.home from java -version output"),
             }
-            anyhow::bail!("JAVA_HOME not set")
         };
 
         if !java_home.exists() {
@@ -1,31 +1,91 @@
 use std::fmt;
 
 
-#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+#[derive(Debug, Clone, Default, Eq, PartialEq, Hash)]
 pub struct Coordinate {
     pub namespace: String,
     pub name: String,
-    pub version: Option<String>
+    pub version: Option<String>,
+    /// File extension when it isn't `jar`
+    pub extension: Option<String>,
+    pub classifier: Option<String>,
 }
 
 impl Coordinate {
+    /// `group:artifact[:extension[:classifier]][:version]`, the last part being the version
+    /// whenever there are three or more
     pub fn parse(coord: &str) -> anyhow::Result<Self> {
         let parts: Vec<&str> = coord.split(':').collect();
-        match parts.len() {
-            2 => Ok(Self {
-                namespace: parts[0].to_string(),
-                name: parts[1].to_string(),
-                version: None,
-            }),
-            3 => Ok(Self {
-                namespace: parts[0].to_string(),
-                name: parts[1].to_string(),
-                version: Some(parts[2].to_string()),
-            }),
-            _ => anyhow::bail!("invalid coordinate format - expected namespace:name[:version]")
+        let (extension, classifier) = match parts.len() {
+            2 | 3 => (None, None),
+            4 => (Some(parts[2]), None),
+            5 => (Some(parts[2]), Some(parts[3])),
+            _ => anyhow::bail!("invalid coordinate format - expected namespace:name[:extension[:classifier]][:version]")
+        };
+
+        let mut parsed = Self::artifact(parts[0], parts[1], extension, classifier);
+        if parts.len() > 2 {
+            parsed.version = Some(parts[parts.len() - 1].to_string());
+        }
+        Ok(parsed)
+    }
+
+    /// `group:artifact[:extension[:classifier]]` with no version, the form used
+    /// as gallade.toml and gallade.lock keys
+    pub fn parse_unversioned(coord: &str) -> anyhow::Result<Self> {
+        let parts: Vec<&str> = coord.split(':').collect();
+        if !(2..=4).contains(&parts.len()) {
+            anyhow::bail!("invalid coordinate format - expected namespace:name[:extension[:classifier]]");
+        }
+        Ok(Self::artifact(parts[0], parts[1], parts.get(2).copied(), parts.get(3).copied()))
+    }
+
+    fn artifact(namespace: &str, name: &str, extension: Option<&str>, classifier: Option<&str>) -> Self {
+        Self {
+            namespace: namespace.to_string(),
+            name: name.to_string(),
+            version: None,
+            extension: extension.filter(|e| !e.is_empty() && *e != "jar").map(String::from),
+            classifier: classifier.filter(|c| !c.is_empty()).map(String::from),
         }
     }
 
+    /// Applies a Maven dependency `<type>` and `<classifier>`, mapping the types
+    /// whose extension isn't the type name the way Maven's artifact handlers do
+    pub fn with_type(mut self, kind: Option<&str>, classifier: Option<&str>) -> Self {
+        let (extension, implied) = match kind.unwrap_or("jar") {
+            "test-jar" => ("jar", Some("tests")),
+            "java-source" => ("jar", Some("sources")),
+            "javadoc" => ("jar", Some("javadoc")),
+            "ejb" | "maven-plugin" | "bundle" => ("jar", None),
+            other => (other, None),
+        };
+        self.extension = Some(extension).filter(|e| *e != "jar").map(String::from);
+        self.classifier = classifier.or(implied).filter(|c| !c.is_empty()).map(String::from);
+        self
+    }
+
+    pub fn extension(&self) -> &str {
+        self.extension.as_deref().unwrap_or("jar")
+    }
+
+    /// Whether the artifact belongs on a Java classpath
+    pub fn is_jar(&self) -> bool {
+        self.extension() == "jar"
+    }
+
+    /// `name-version[-classifier].extension`
+    pub fn file_name(&self, version: &str) -> String {
+        match &self.classifier {
+            Some(classifier) => format!("{}-{}-{}.{}", self.name, version, classifier, self.extension()),
+            None => format!("{}-{}.{}", self.name, version, self.extension()),
+        }
+    }
+
+    pub fn pom_file_name(&self, version: &str) -> String {
+        format!("{}-{}.pom", self.name, version)
+    }
+
     pub fn to_path(&self) -> String {
         format!(
             "{}/{}",
@@ -37,10 +97,18 @@ impl Coordinate {
 
 impl fmt::Display for Coordinate {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        match &self.version {
-            Some(v) => write!(f, "{}:{}:{}", self.namespace, self.name, v),
-            None => write!(f, "{}:{}", self.namespace, self.name)
+        write!(f, "{}:{}", self.namespace, self.name)?;
+        // the extension has to be spelled out whenever a classifier follows it
+        if self.extension.is_some() || self.classifier.is_some() {
+            write!(f, ":{}", self.extension())?;
         }
+        if let Some(classifier) = &self.classifier {
+            write!(f, ":{}", classifier)?;
+        }
+        if let Some(v) = &self.version {
+            write!(f, ":{}", v)?;
+        }
+        Ok(())
     }
 }
 
@@ -69,4 +137,33 @@ mod tests {
         let coord = Coordinate::parse("com.google.guava:guava").unwrap();
         assert_eq!(coord.to_path(), "com/google/guava/guava");
     }
+
+    #[test]
+    fn test_extension_and_classifier() -> anyhow::Result<()> {
+        let epoll = Coordinate::parse("io.netty:netty-transport-native-epoll:jar:linux-x86_64:4.1.100.Final")?;
+        assert_eq!(epoll.extension, None);
+        assert_eq!(epoll.classifier.as_deref(), Some("linux-x86_64"));
+        assert_eq!(epoll.version.as_deref(), Some("4.1.100.Final"));
+        assert_eq!(epoll.file_name("4.1.100.Final"), "netty-transport-native-epoll-4.1.100.Final-linux-x86_64.jar");
+        assert_eq!(epoll.to_string(), "io.netty:netty-transport-native-epoll:jar:linux-x86_64:4.1.100.Final");
+
+        let protoc = Coordinate::parse("com.google.protobuf:protoc:exe:linux-x86_64:3.25.1")?;
+        assert!(!protoc.is_jar());
+        assert_eq!(protoc.file_name("3.25.1"), "protoc-3.25.1-linux-x86_64.exe");
+        assert_eq!(protoc.pom_file_name("3.25.1"), "protoc-3.25.1.pom");
+
+        let war = Coordinate::parse("com.ourcorp:portal:war:2.0.0")?;
+        assert_eq!((war.extension(), war.version.as_deref()), ("war", Some("2.0.0")));
+
+        // lock and manifest keys have no version, so every part is part of the artifact
+        let key = Coordinate::parse_unversioned("com.ourcorp:ledger:jar:tests")?;
+        assert_eq!(key.classifier.as_deref(), Some("tests"));
+        assert_eq!(Coordinate::parse_unversioned(&key.to_string())?, key);
+        assert_eq!(Coordinate::parse_unversioned("org.slf4j:slf4j-api")?.to_string(), "org.slf4j:slf4j-api");
+
+        let tests = Coordinate::parse("com.ourcorp:ledger")?.with_type(Some("test-jar"), None);
+        assert_eq!(tests.to_string(), "com.ourcorp:ledger:jar:tests");
+
+        Ok(())
+    }
 }
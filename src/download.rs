@@ -1,10 +1,19 @@
 use std::collections::HashSet;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
 use serde::{Deserialize, Serialize};
-use std::time::Duration;
-use reqwest::Client;
 
 use crate::coordinates::Coordinate;
+use crate::effective::PomRepository;
+use crate::http::{HttpClient, HttpConfig};
+use crate::m2::LocalMavenRepository;
+use crate::manifest::Repositories;
+use crate::routing::RoutingRules;
+
+/// Name Maven Central is known by in `[repositories]` and `[routing]`
+pub const CENTRAL: &str = "central";
 
 #[derive(Debug, Deserialize, Serialize)]
 struct MavenResponse {
@@ -25,27 +34,98 @@ pub struct ArtifactDoc {
     pub timestamp: i64,
 }
 
+#[derive(Debug, Deserialize)]
+struct MavenMetadata {
+    #[serde(default)]
+    versioning: Versioning,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct Versioning {
+    #[serde(default)]
+    versions: Versions,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct Versions {
+    #[serde(default)]
+    version: Vec<String>,
+}
+
+/// Versions listed in a maven-metadata.xml, newest first
+pub fn parse_metadata_versions(content: &str) -> anyhow::Result<Vec<String>> {
+    let metadata: MavenMetadata = quick_xml::de::from_str(content)?;
+    Ok(metadata.versioning.versions.version.into_iter().rev().collect())
+}
+
+/// Where an artifact was actually downloaded from
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+pub struct ArtifactSource {
+    pub repository: String,
+    pub url: String,
+}
+
 #[async_trait::async_trait]
 pub trait ArtifactRepository: Send + Sync {
     fn name(&self) -> &str;
+    fn artifact_url(&self, coord: &Coordinate, version: &str, file: &str) -> String;
     async fn search(&self, coord: &Coordinate) -> anyhow::Result<Vec<String>>;
     async fn fetch_jar(&self, coord: &Coordinate, version: &str) -> anyhow::Result<Vec<u8>>;
     async fn fetch_metadata(&self, coord: &Coordinate, version: &str) -> anyhow::Result<String>;
+
+    /// Repository that actually published `file`. Remote repositories are their
+    /// own origin, a local mirror answers with wherever it got the file from or
+    /// None when it can't tell
+    fn origin(&self, _coord: &Coordinate, _version: &str, _file: &str) -> Option<String> {
+        Some(self.name().to_string())
+    }
+
+    // Local repositories are always consulted, routing applies to the origin of each file instead
+    fn is_local(&self) -> bool {
+        false
+    }
+
+    // Repositories that can stream should override this, the default buffers the whole jar
+    async fn fetch_jar_to(&self, coord: &Coordinate, version: &str, dest: &Path) -> anyhow::Result<()> {
+        let jar = self.fetch_jar(coord, version).await?;
+        if let Some(parent) = dest.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        // dest may be hardlinked into the shared cache, never write through it
+        if dest.exists() {
+            fs::remove_file(dest)?;
+        }
+        fs::write(dest, jar)?;
+        Ok(())
+    }
 }
 
+/// Every repository that was tried for an artifact, with the reason each one failed
+#[derive(Debug)]
+pub struct FetchError {
+    artifact: String,
+    failures: Vec<(String, anyhow::Error)>,
+}
+
+impl fmt::Display for FetchError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "could not download {} from any repository", self.artifact)?;
+        for (repo, err) in &self.failures {
+            write!(f, "\n  {}: {:#}", repo, err)?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for FetchError {}
 
 pub struct MavenCentral {
-    client: Client,
+    client: HttpClient,
 }
 
 impl MavenCentral {
-    pub fn new() -> anyhow::Result<Self> {
-        let client = Client::builder()
-            .user_agent("gallade/0.1.0")
-            .timeout(Duration::from_secs(30))
-            .build()?;
-
-        Ok(Self { client })
+    pub fn new(client: HttpClient) -> Self {
+        Self { client }
     }
 
     async fn search_artifacts(&self, coord: &Coordinate) -> anyhow::Result<Vec<ArtifactDoc>> {
@@ -57,7 +137,6 @@ impl MavenCentral {
 
         let response = self.client
             .get(&url)
-            .send()
             .await?
             .json::<MavenResponse>()
             .await?;
@@ -69,7 +148,17 @@ impl MavenCentral {
 #[async_trait::async_trait]
 impl ArtifactRepository for MavenCentral {
     fn name(&self) -> &str {
-        "MavenCentral"
+        CENTRAL
+    }
+
+    fn artifact_url(&self, coord: &Coordinate, version: &str, file: &str) -> String {
+        format!(
+            "https://search.maven.org/remotecontent?filepath={}/{}/{}/{}",
+            coord.namespace.replace('.', "/"),
+            coord.name,
+            version,
+            file
+        )
     }
 
     async fn search(&self, coord: &Coordinate) -> anyhow::Result<Vec<String>> {
@@ -78,16 +167,9 @@ impl ArtifactRepository for MavenCentral {
     }
 
     async fn fetch_jar(&self, coord: &Coordinate, version: &str) -> anyhow::Result<Vec<u8>> {
-        let jar_path = format!("{}-{}.jar", coord.name, version);
-        let url = format!(
-            "https://search.maven.org/remotecontent?filepath={}/{}/{}/{}",
-            coord.namespace.replace('.', "/"),
-            coord.name,
-            version,
-            jar_path
-        );
+        let url = self.artifact_url(coord, version, &coord.file_name(version));
 
-        let response = self.client.get(&url).send().await?;
+        let response = self.client.get(&url).await?;
         if !response.status().is_success() {
             anyhow::bail!("failed to download jar: HTTP {}", response.status());
         }
@@ -95,17 +177,17 @@ impl ArtifactRepository for MavenCentral {
         Ok(response.bytes().await?.to_vec())
     }
 
+    async fn fetch_jar_to(&self, coord: &Coordinate, version: &str, dest: &Path) -> anyhow::Result<()> {
+        let url = self.artifact_url(coord, version, &coord.file_name(version));
+
+        self.client.download_to(&url, dest).await
+            .map_err(|e| e.context("failed to download jar"))
+    }
+
     async fn fetch_metadata(&self, coord: &Coordinate, version: &str) -> anyhow::Result<String> {
-        let pom_path = format!("{}-{}.pom", coord.name, version);
-        let url = format!(
-            "https://search.maven.org/remotecontent?filepath={}/{}/{}/{}",
-            coord.namespace.replace('.', "/"),
-            coord.name,
-            version,
-            pom_path
-        );
+        let url = self.artifact_url(coord, version, &coord.pom_file_name(version));
 
-        let response = self.client.get(&url).send().await?;
+        let response = self.client.get(&url).await?;
         if !response.status().is_success() {
             anyhow::bail!("failed to download pom: HTTP {}", response.status());
         }
@@ -114,72 +196,273 @@ impl ArtifactRepository for MavenCentral {
     }
 }
 
+/// Any repository using the standard Maven layout, e.g. a Nexus or Artifactory instance
+pub struct RemoteRepository {
+    name: String,
+    url: String,
+    client: HttpClient,
+}
+
+impl RemoteRepository {
+    pub fn new(name: &str, url: &str, client: HttpClient) -> Self {
+        Self {
+            name: name.to_string(),
+            url: url.trim_end_matches('/').to_string(),
+            client,
+        }
+    }
+
+    async fn get_text(&self, url: &str) -> anyhow::Result<String> {
+        let response = self.client.get(url).await?;
+        if !response.status().is_success() {
+            anyhow::bail!("HTTP {}", response.status());
+        }
+        Ok(response.text().await?)
+    }
+}
+
+#[async_trait::async_trait]
+impl ArtifactRepository for RemoteRepository {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn artifact_url(&self, coord: &Coordinate, version: &str, file: &str) -> String {
+        format!("{}/{}/{}/{}", self.url, coord.to_path(), version, file)
+    }
+
+    async fn search(&self, coord: &Coordinate) -> anyhow::Result<Vec<String>> {
+        let url = format!("{}/{}/maven-metadata.xml", self.url, coord.to_path());
+        let metadata = self.get_text(&url).await
+            .map_err(|e| e.context("failed to fetch maven-metadata.xml"))?;
+        parse_metadata_versions(&metadata)
+    }
+
+    async fn fetch_jar(&self, coord: &Coordinate, version: &str) -> anyhow::Result<Vec<u8>> {
+        let url = self.artifact_url(coord, version, &coord.file_name(version));
+
+        let response = self.client.get(&url).await?;
+        if !response.status().is_success() {
+            anyhow::bail!("failed to download jar: HTTP {}", response.status());
+        }
+
+        Ok(response.bytes().await?.to_vec())
+    }
+
+    async fn fetch_jar_to(&self, coord: &Coordinate, version: &str, dest: &Path) -> anyhow::Result<()> {
+        let url = self.artifact_url(coord, version, &coord.file_name(version));
+
+        self.client.download_to(&url, dest).await
+            .map_err(|e| e.context("failed to download jar"))
+    }
+
+    async fn fetch_metadata(&self, coord: &Coordinate, version: &str) -> anyhow::Result<String> {
+        let url = self.artifact_url(coord, version, &coord.pom_file_name(version));
+
+        self.get_text(&url).await
+            .map_err(|e| e.context("failed to download pom"))
+    }
+}
+
+/// Where repositories found in a dependency's `<repositories>` are named, e.g. `pom:jboss`
+pub const POM_REPOSITORY_PREFIX: &str = "pom:";
+
+// What Maven's own POMs call Central, declared all over the place and never worth adding
+const CENTRAL_URLS: [&str; 2] = ["https://repo.maven.apache.org/maven2", "https://repo1.maven.org/maven2"];
+
 #[derive(Clone)]
 pub struct RepositoryManager {
     repositories: Vec<Arc<dyn ArtifactRepository + Send + Sync>>,
+    routing: RoutingRules,
+    client: HttpClient,
+    /// URLs of every repository already in `repositories`
+    urls: Vec<String>,
+    /// URL prefixes of the POM-declared repositories that may be used
+    pom_policy: Vec<String>,
 }
 
 impl RepositoryManager {
-    pub fn new() -> anyhow::Result<Self> {
-        let repositories: Vec<Arc<dyn ArtifactRepository + Send + Sync>> = vec![
-            Arc::new(MavenCentral::new()?)
-        ];
+    pub fn new(config: &HttpConfig, configured: &Repositories) -> anyhow::Result<Self> {
+        // one client for every repository so they share proxy, TLS and connection pool
+        let client = config.build_client()?;
+
+        let mut repositories: Vec<Arc<dyn ArtifactRepository + Send + Sync>> = Vec::new();
+        let mut urls: Vec<String> = CENTRAL_URLS.iter().map(|url| url.to_string()).collect();
+        for (name, repo) in configured {
+            let client = client.with_credentials(repo.credentials());
+            repositories.push(Arc::new(RemoteRepository::new(name, &repo.url, client)));
+            urls.push(repo.url.trim_end_matches('/').to_string());
+        }
 
-        Ok(Self { repositories })
+        // declaring `central` yourself replaces the built-in one, e.g. with a mirror
+        if !configured.contains_key(CENTRAL) {
+            repositories.push(Arc::new(MavenCentral::new(client.clone())));
+        }
+
+        Ok(Self { repositories, routing: RoutingRules::default(), client, urls, pom_policy: Vec::new() })
+    }
+
+    pub fn with_routing(mut self, routing: RoutingRules) -> anyhow::Result<Self> {
+        for name in routing.repositories() {
+            // POM-declared repositories only turn up while resolving
+            if name.starts_with(POM_REPOSITORY_PREFIX) {
+                continue;
+            }
+            if !self.repositories.iter().any(|repo| repo.name() == name) {
+                anyhow::bail!("routing refers to unknown repository '{}'", name);
+            }
+        }
+
+        self.routing = routing;
+        Ok(self)
+    }
+
+    /// Consults a Maven local repository before any remote one
+    pub fn with_local_maven(mut self, root: PathBuf) -> Self {
+        let known = self.repositories.iter().map(|repo| repo.name().to_string()).collect();
+        self.repositories.insert(0, Arc::new(LocalMavenRepository::new(root, known)));
+        self
+    }
+
+    pub fn routing(&self) -> &RoutingRules {
+        &self.routing
+    }
+
+    /// Allows repositories declared in dependency POMs whose URL starts with one
+    /// of `allowed`, `*` allowing any. None are used otherwise, since any POM
+    /// could send resolution anywhere.
+    pub fn with_pom_repository_policy(mut self, allowed: Vec<String>) -> Self {
+        self.pom_policy = allowed;
+        self
+    }
+
+    // `*` only lets in https, a plain http repository has to be listed itself
+    fn pom_repository_allowed(&self, url: &str) -> bool {
+        self.pom_policy.iter().any(|prefix| {
+            let prefix = prefix.trim_end_matches('/');
+            (prefix == "*" && url.starts_with("https://")) || url == prefix || url.starts_with(&format!("{}/", prefix))
+        })
+    }
+
+    /// The repositories to use below a dependency whose POM declares `declared`:
+    /// these plus whichever of them the policy allows, tried after the rest
+    pub fn with_pom_repositories(&self, declared: &[PomRepository]) -> Self {
+        let mut manager = self.clone();
+        for repo in declared {
+            let url = repo.url.trim_end_matches('/');
+            let name = format!("{}{}", POM_REPOSITORY_PREFIX, repo.id);
+            if repo.id == CENTRAL || manager.urls.iter().any(|known| known == url) {
+                continue;
+            }
+            if !self.pom_repository_allowed(url) {
+                tracing::debug!("not using repository {} at {}, pom_repositories doesn't allow it", repo.id, url);
+                continue;
+            }
+            // two POMs giving the same id to different places, the first one keeps it
+            if manager.repositories.iter().any(|known| known.name() == name) {
+                tracing::debug!("not using repository {} at {}, another POM already declared {}", repo.id, url, name);
+                continue;
+            }
+
+            manager.repositories.push(Arc::new(RemoteRepository::new(&name, url, self.client.clone())));
+            manager.urls.push(url.to_string());
+        }
+        manager
     }
 
-    // Searches all repositories and returns consolidated results
+    // Only the repositories the routing rules allow to serve this group
+    fn candidates<'a>(&'a self, coord: &'a Coordinate) -> impl Iterator<Item = &'a Arc<dyn ArtifactRepository + Send + Sync>> + 'a {
+        self.repositories
+            .iter()
+            .filter(|repo| repo.is_local() || self.routing.is_allowed(&coord.namespace, repo.name()))
+    }
+
+    // Where `file` served by `repo` should be recorded as coming from, None if it
+    // can't be attributed or its origin isn't allowed to serve the group
+    fn attribute(&self, repo: &dyn ArtifactRepository, coord: &Coordinate, version: &str, file: &str) -> Option<ArtifactSource> {
+        let origin = repo.origin(coord, version, file)?;
+        if !self.routing.is_allowed(&coord.namespace, &origin) {
+            return None;
+        }
+
+        let publisher = self.repositories.iter().find(|r| r.name() == origin)?;
+        Some(ArtifactSource {
+            url: publisher.artifact_url(coord, version, file),
+            repository: origin,
+        })
+    }
+
+    fn fetch_error(&self, coord: &Coordinate, artifact: String, failures: Vec<(String, anyhow::Error)>) -> anyhow::Error {
+        let artifact = match self.routing.allowed_for(&coord.namespace) {
+            Some((pattern, _)) => format!("{} (restricted by routing rule {})", artifact, pattern),
+            None => artifact,
+        };
+        FetchError { artifact, failures }.into()
+    }
+
+    // Searches all repositories and returns consolidated results. Finding nothing
+    // while some repository couldn't be asked is an error, not an empty list.
     pub async fn search_versions(&self, coord: &Coordinate) -> anyhow::Result<Vec<String>> {
         let mut versions = HashSet::new();
-
-        for repo in &self.repositories {
-            if let Ok(repo_versions) = repo.search(coord).await {
-                versions.extend(repo_versions);
+        let mut failures = Vec::new();
+
+        for repo in self.candidates(coord) {
+            match repo.search(coord).await {
+                Ok(repo_versions) => versions.extend(repo_versions.into_iter().filter(|version| {
+                    !repo.is_local() || self.attribute(repo.as_ref(), coord, version, &coord.pom_file_name(version)).is_some()
+                })),
+                Err(e) => failures.push((repo.name().to_string(), e)),
             }
         }
 
+        if versions.is_empty() && !failures.is_empty() {
+            return Err(self.fetch_error(coord, format!("the versions of {}", coord), failures));
+        }
         Ok(versions.into_iter().collect())
     }
 
-    pub async fn download_jar(&self, coord: &Coordinate, version: &str) -> anyhow::Result<Vec<u8>> {
-        for repo in &self.repositories {
-            match repo.fetch_jar(coord, version).await {
-                Ok(bytes) => return Ok(bytes),
-                Err(_) => continue,
+    pub async fn download_jar_to(&self, coord: &Coordinate, version: &str, dest: &Path) -> anyhow::Result<ArtifactSource> {
+        let file = coord.file_name(version);
+        let mut failures = Vec::new();
+        for repo in self.candidates(coord) {
+            let Some(source) = self.attribute(repo.as_ref(), coord, version, &file) else {
+                continue;
+            };
+
+            match repo.fetch_jar_to(coord, version, dest).await {
+                Ok(()) => return Ok(source),
+                Err(e) => failures.push((repo.name().to_string(), e)),
             }
         }
-        anyhow::bail!("could not download jar from any repository")
+        Err(self.fetch_error(coord, format!("{}:{} jar", coord, version), failures))
     }
 
     // Same for metadata
-    pub async fn download_metadata(&self, coord: &Coordinate, version: &str) -> anyhow::Result<String> {
-        for repo in &self.repositories {
+    pub async fn download_metadata(&self, coord: &Coordinate, version: &str) -> anyhow::Result<(String, ArtifactSource)> {
+        let file = coord.pom_file_name(version);
+        let mut failures = Vec::new();
+        for repo in self.candidates(coord) {
+            let Some(source) = self.attribute(repo.as_ref(), coord, version, &file) else {
+                continue;
+            };
+
             match repo.fetch_metadata(coord, version).await {
-                Ok(text) => return Ok(text),
-                Err(_) => continue,
+                Ok(text) => return Ok((text, source)),
+                Err(e) => failures.push((repo.name().to_string(), e)),
             }
         }
-        anyhow::bail!("could not download metadata from any repository")
-    }
-
-    pub async fn fetch_source_repo(&self, coord: &Coordinate) -> anyhow::Result<String> {
-        for repo in &self.repositories {
-           if repo.search(coord).await.is_ok() {
-               return Ok(repo.name().to_string())
-           }
-        }
-
-        anyhow::bail!("no repository found for coordinate: {}", coord)
+        Err(self.fetch_error(coord, format!("{}:{} pom", coord, version), failures))
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use std::collections::HashMap;
 
     #[tokio::test]
     async fn test_maven_central_search() -> anyhow::Result<()> {
-        let maven = MavenCentral::new()?;
+        let maven = MavenCentral::new(HttpConfig::default().build_client()?);
         let coord = Coordinate::parse("org.slf4j:slf4j-api")?;
 
         let versions = maven.search(&coord).await?;
@@ -189,7 +472,7 @@ mod tests {
 
     #[tokio::test]
     async fn test_maven_central_fetch() -> anyhow::Result<()> {
-        let maven = MavenCentral::new()?;
+        let maven = MavenCentral::new(HttpConfig::default().build_client()?);
         let coord = Coordinate::parse("org.slf4j:slf4j-api")?;
 
         let jar = maven.fetch_jar(&coord, "1.7.36").await?;
@@ -200,4 +483,214 @@ mod tests {
 
         Ok(())
     }
-}
\ No newline at end of file
+
+    struct Unreachable(&'static str);
+
+    #[async_trait::async_trait]
+    impl ArtifactRepository for Unreachable {
+        fn name(&self) -> &str {
+            self.0
+        }
+
+        fn artifact_url(&self, _coord: &Coordinate, _version: &str, file: &str) -> String {
+            format!("https://{}.invalid/{}", self.0, file)
+        }
+
+        async fn search(&self, _coord: &Coordinate) -> anyhow::Result<Vec<String>> {
+            anyhow::bail!("connection refused")
+        }
+
+        async fn fetch_jar(&self, _coord: &Coordinate, _version: &str) -> anyhow::Result<Vec<u8>> {
+            Err(anyhow::anyhow!("connection reset").context("failed to download jar"))
+        }
+
+        async fn fetch_metadata(&self, _coord: &Coordinate, _version: &str) -> anyhow::Result<String> {
+            anyhow::bail!("failed to download pom: HTTP 404 Not Found")
+        }
+    }
+
+    // A manager that only knows `repositories`
+    fn with_repositories(repositories: Vec<Arc<dyn ArtifactRepository + Send + Sync>>) -> RepositoryManager {
+        RepositoryManager {
+            repositories,
+            ..RepositoryManager::new(&HttpConfig::default(), &Default::default()).unwrap()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_failures_keep_every_repository() {
+        let manager = with_repositories(vec![Arc::new(Unreachable("internal")), Arc::new(Unreachable("mirror"))]);
+        let coord = Coordinate::parse("org.slf4j:slf4j-api").unwrap();
+
+        let temp = tempfile::TempDir::new().unwrap();
+        let err = manager.download_jar_to(&coord, "1.7.36", &temp.path().join("slf4j-api.jar")).await
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("org.slf4j:slf4j-api:1.7.36"));
+        assert!(err.contains("internal: failed to download jar: connection reset"));
+        assert!(err.contains("mirror: failed to download jar: connection reset"));
+
+        let err = manager.download_metadata(&coord, "1.7.36").await.unwrap_err().to_string();
+        assert!(err.contains("mirror: failed to download pom: HTTP 404"));
+    }
+
+    // Serves every artifact it is asked for, like a squatted name on a public repository would
+    struct Squatter(&'static str);
+
+    #[async_trait::async_trait]
+    impl ArtifactRepository for Squatter {
+        fn name(&self) -> &str {
+            self.0
+        }
+
+        fn artifact_url(&self, coord: &Coordinate, version: &str, file: &str) -> String {
+            format!("https://{}.invalid/{}/{}/{}", self.0, coord.to_path(), version, file)
+        }
+
+        async fn search(&self, _coord: &Coordinate) -> anyhow::Result<Vec<String>> {
+            Ok(vec!["99.0.0".to_string()])
+        }
+
+        async fn fetch_jar(&self, _coord: &Coordinate, _version: &str) -> anyhow::Result<Vec<u8>> {
+            Ok(b"malicious".to_vec())
+        }
+
+        async fn fetch_metadata(&self, _coord: &Coordinate, _version: &str) -> anyhow::Result<String> {
+            Ok("<project/>".to_string())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_routing_pins_group_to_repository() -> anyhow::Result<()> {
+        let manager = with_repositories(vec![Arc::new(Unreachable("internal")), Arc::new(Squatter(CENTRAL))]).with_routing(RoutingRules::new(&HashMap::from([
+            ("com.ourcorp.*".to_string(), vec!["internal".to_string()]),
+        ])))?;
+
+        let temp = tempfile::TempDir::new()?;
+        let internal = Coordinate::parse("com.ourcorp.billing:ledger")?;
+
+        let err = manager.search_versions(&internal).await.unwrap_err().to_string();
+        assert!(err.starts_with("could not download the versions of com.ourcorp.billing:ledger"), "{}", err);
+        assert!(!err.contains(CENTRAL));
+        let err = manager.download_jar_to(&internal, "1.0.0", &temp.path().join("ledger.jar")).await
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("restricted by routing rule com.ourcorp.*"));
+        assert!(!err.contains(CENTRAL));
+
+        let public = Coordinate::parse("org.slf4j:slf4j-api")?;
+        let source = manager.download_jar_to(&public, "1.7.36", &temp.path().join("slf4j.jar")).await?;
+        assert_eq!(source.repository, CENTRAL);
+        assert!(source.url.ends_with("org/slf4j/slf4j-api/1.7.36/slf4j-api-1.7.36.jar"));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_local_maven_attributes_to_origin() -> anyhow::Result<()> {
+        let m2 = tempfile::TempDir::new()?;
+        let install = |coord: &str, version: &str, origin: &str| -> anyhow::Result<()> {
+            let coord = Coordinate::parse(coord)?;
+            let dir = m2.path().join(coord.to_path()).join(version);
+            fs::create_dir_all(&dir)?;
+            let jar = format!("{}-{}.jar", coord.name, version);
+            fs::write(dir.join(&jar), "from m2")?;
+            fs::write(dir.join(format!("{}-{}.pom", coord.name, version)), "<project/>")?;
+            fs::write(dir.join("_remote.repositories"), format!("{}>{}=\n", jar, origin))?;
+            Ok(())
+        };
+        install("org.slf4j:slf4j-api", "1.7.36", CENTRAL)?;
+        install("com.ourcorp.billing:ledger", "1.0.0", CENTRAL)?;
+
+        let manager = with_repositories(vec![Arc::new(Unreachable("internal")), Arc::new(Squatter(CENTRAL))]).with_local_maven(m2.path().to_path_buf()).with_routing(RoutingRules::new(&HashMap::from([
+            ("com.ourcorp.*".to_string(), vec!["internal".to_string()]),
+        ])))?;
+
+        let temp = tempfile::TempDir::new()?;
+        let public = Coordinate::parse("org.slf4j:slf4j-api")?;
+        let dest = temp.path().join("slf4j.jar");
+        let source = manager.download_jar_to(&public, "1.7.36", &dest).await?;
+        assert_eq!(fs::read_to_string(&dest)?, "from m2");
+        assert_eq!(source.repository, CENTRAL);
+        assert!(source.url.starts_with("https://central.invalid/"));
+
+        // Maven fetched it from central, which isn't allowed to serve this group
+        let internal = Coordinate::parse("com.ourcorp.billing:ledger")?;
+        assert!(manager.download_jar_to(&internal, "1.0.0", &temp.path().join("ledger.jar")).await.is_err());
+        // and the one repository that may can't be reached, which isn't the same as having no versions
+        let err = manager.search_versions(&internal).await.unwrap_err().to_string();
+        assert!(err.contains("internal:"), "{}", err);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_unknown_routing_repository() {
+        let manager = with_repositories(vec![Arc::new(Squatter(CENTRAL))]);
+
+        let routing = RoutingRules::new(&HashMap::from([
+            ("com.ourcorp.*".to_string(), vec!["internal".to_string()]),
+        ]));
+        assert!(manager.with_routing(routing).is_err());
+    }
+
+    #[test]
+    fn test_parse_metadata_versions() -> anyhow::Result<()> {
+        let versions = parse_metadata_versions(r#"
+            <metadata>
+                <groupId>com.ourcorp</groupId>
+                <artifactId>ledger</artifactId>
+                <versioning>
+                    <latest>1.2.0</latest>
+                    <versions>
+                        <version>1.0.0</version>
+                        <version>1.1.0</version>
+                        <version>1.2.0</version>
+                    </versions>
+                </versioning>
+            </metadata>
+        "#)?;
+
+        assert_eq!(versions, vec!["1.2.0", "1.1.0", "1.0.0"]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_pom_repository_policy() -> anyhow::Result<()> {
+        let declared = vec![
+            PomRepository { id: "jboss".to_string(), url: "https://repository.jboss.org/nexus/content/groups/public/".to_string() },
+            PomRepository { id: "confluent".to_string(), url: "https://packages.confluent.io/maven".to_string() },
+            PomRepository { id: "apache".to_string(), url: "https://repo.maven.apache.org/maven2".to_string() },
+            PomRepository { id: "lookalike".to_string(), url: "https://repository.jboss.org/nexus-mirror".to_string() },
+            PomRepository { id: "legacy".to_string(), url: "http://maven.ourcorp.com/releases".to_string() },
+        ];
+        let names = |manager: &RepositoryManager| -> Vec<String> {
+            manager.repositories.iter().map(|repo| repo.name().to_string()).collect()
+        };
+
+        // nothing declared in a POM is used unless it's allowed
+        let manager = RepositoryManager::new(&HttpConfig::default(), &Default::default())?;
+        assert_eq!(names(&manager.with_pom_repositories(&declared)), vec!["central"]);
+
+        let manager = manager.with_pom_repository_policy(vec!["https://repository.jboss.org/nexus/".to_string()]);
+        let below = manager.with_pom_repositories(&declared);
+        assert_eq!(names(&below), vec!["central", "pom:jboss"]);
+        // declared again further down, it's only added once
+        assert_eq!(names(&below.with_pom_repositories(&declared)), vec!["central", "pom:jboss"]);
+
+        // routing applies to them like to any other repository
+        let routed = below.with_routing(RoutingRules::new(&HashMap::from([
+            ("org.jboss.*".to_string(), vec!["pom:jboss".to_string()]),
+        ])))?;
+        let coord = Coordinate::parse("org.jboss.logging:jboss-logging")?;
+        assert_eq!(routed.candidates(&coord).map(|repo| repo.name()).collect::<Vec<_>>(), vec!["pom:jboss"]);
+
+        // anything over https, plain http only when it's listed
+        let open = manager.clone().with_pom_repository_policy(vec!["*".to_string()]);
+        assert_eq!(names(&open.with_pom_repositories(&declared)), vec!["central", "pom:jboss", "pom:confluent", "pom:lookalike"]);
+        let listed = manager.with_pom_repository_policy(vec!["*".to_string(), "http://maven.ourcorp.com".to_string()]);
+        assert_eq!(names(&listed.with_pom_repositories(&declared)).last().map(String::as_str), Some("pom:legacy"));
+
+        Ok(())
+    }
+}
@@ -0,0 +1,1009 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::path::PathBuf;
+
+use crate::coordinates::Coordinate;
+use crate::platform::{Platform, CLASSIFIER_VARIABLE};
+use crate::version::{MavenVersion, VersionReq};
+use crate::xml::Element;
+
+// how far up a parent chain we go before assuming a cycle
+const MAX_PARENTS: usize = 32;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Exclusion {
+    pub group_id: String,
+    pub artifact_id: String,
+}
+
+impl Exclusion {
+    /// `group:artifact` as written in gallade.toml, a bare group leaves out all of it
+    pub fn parse(pattern: &str) -> Self {
+        let (group_id, artifact_id) = pattern.split_once(':').unwrap_or((pattern, "*"));
+        Self { group_id: group_id.to_string(), artifact_id: artifact_id.to_string() }
+    }
+
+    /// Maven allows `*` for either part
+    pub fn matches(&self, coord: &Coordinate) -> bool {
+        (self.group_id == "*" || self.group_id == coord.namespace)
+            && (self.artifact_id == "*" || self.artifact_id == coord.name)
+    }
+}
+
+impl fmt::Display for Exclusion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}", self.group_id, self.artifact_id)
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Dependency {
+    pub group_id: String,
+    pub artifact_id: String,
+    pub version: Option<String>,
+    pub scope: Option<String>,
+    pub optional: bool,
+    pub kind: Option<String>,
+    pub classifier: Option<String>,
+    pub exclusions: Vec<Exclusion>,
+    /// Set when the classifier is `${os.detected.classifier}`, i.e. this platform's
+    pub native: bool,
+}
+
+impl Dependency {
+    fn parse(element: &Element) -> Self {
+        Self {
+            group_id: element.text_at("groupId").unwrap_or_default(),
+            artifact_id: element.text_at("artifactId").unwrap_or_default(),
+            version: element.text_at("version"),
+            scope: element.text_at("scope"),
+            optional: element.text_at("optional").as_deref() == Some("true"),
+            kind: element.text_at("type"),
+            classifier: element.text_at("classifier"),
+            exclusions: element.find("exclusions")
+                .map(|e| e.children("exclusion")
+                    .map(|ex| Exclusion {
+                        group_id: ex.text_at("groupId").unwrap_or_else(|| "*".to_string()),
+                        artifact_id: ex.text_at("artifactId").unwrap_or_else(|| "*".to_string()),
+                    })
+                    .collect())
+                .unwrap_or_default(),
+            native: false,
+        }
+    }
+
+    // dependencyManagement entries match on everything but the version
+    fn management_key(&self) -> (String, String, String, Option<String>) {
+        (
+            self.group_id.clone(),
+            self.artifact_id.clone(),
+            self.kind.clone().unwrap_or_else(|| "jar".to_string()),
+            self.classifier.clone(),
+        )
+    }
+
+    pub fn coordinate(&self) -> Coordinate {
+        let coord = Coordinate {
+            namespace: self.group_id.clone(),
+            name: self.artifact_id.clone(),
+            version: self.version.clone(),
+            ..Default::default()
+        };
+        coord.with_type(self.kind.as_deref(), self.classifier.as_deref())
+    }
+
+    fn interpolate(&mut self, properties: &HashMap<String, String>) {
+        self.group_id = interpolate(&self.group_id, properties);
+        self.artifact_id = interpolate(&self.artifact_id, properties);
+        for value in [&mut self.version, &mut self.scope, &mut self.kind, &mut self.classifier].into_iter().flatten() {
+            *value = interpolate(value, properties);
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct PomRepository {
+    pub id: String,
+    pub url: String,
+}
+
+/// Where an artifact moved to, from `<distributionManagement><relocation>`
+#[derive(Clone, Debug, PartialEq)]
+pub struct Relocation {
+    pub coordinate: Coordinate,
+    pub message: Option<String>,
+}
+
+/// One POM as written, before inheritance and interpolation
+#[derive(Clone, Debug)]
+struct Model {
+    parent: Option<Coordinate>,
+    parent_path: Option<String>,
+    group_id: Option<String>,
+    artifact_id: Option<String>,
+    version: Option<String>,
+    packaging: Option<String>,
+    properties: Vec<(String, String)>,
+    dependency_management: Vec<Dependency>,
+    dependencies: Vec<Dependency>,
+    repositories: Vec<PomRepository>,
+    source_directory: Option<String>,
+    resource_directories: Vec<String>,
+    main_class: Option<String>,
+    compiler_release: Option<String>,
+    /// Parts left out stay what they were, so the coordinate may be incomplete
+    relocation: Option<Relocation>,
+    profiles: Vec<Profile>,
+}
+
+/// The parts of a `<profile>` that can change what a POM depends on
+#[derive(Clone, Debug)]
+struct Profile {
+    id: String,
+    activation: Option<Activation>,
+    properties: Vec<(String, String)>,
+    dependency_management: Vec<Dependency>,
+    dependencies: Vec<Dependency>,
+    repositories: Vec<PomRepository>,
+}
+
+#[derive(Clone, Debug, Default)]
+struct Activation {
+    active_by_default: bool,
+    jdk: Option<String>,
+    /// `name`, `family`, `arch` and `version`, each possibly negated with `!`
+    os: Vec<(String, String)>,
+    property: Option<(String, Option<String>)>,
+    file_exists: Option<String>,
+    file_missing: Option<String>,
+}
+
+/// What `<activation>` rules are checked against, besides the platform
+#[derive(Clone, Debug, Default)]
+pub struct ProfileActivation {
+    /// Profile ids asked for with `-P`, a leading `!` or `-` turning one off
+    pub profiles: Vec<String>,
+    /// Set with `-D`, these also override the POM's own properties
+    pub properties: HashMap<String, String>,
+    pub java_version: Option<String>,
+    /// Where `<file>` rules are relative to, only known for the project's own POMs
+    pub basedir: Option<PathBuf>,
+}
+
+fn parse_dependencies(element: Option<&Element>) -> Vec<Dependency> {
+    element
+        .map(|deps| deps.children("dependency").map(Dependency::parse).collect())
+        .unwrap_or_default()
+}
+
+fn parse_properties(element: Option<&Element>) -> Vec<(String, String)> {
+    element
+        .map(|props| props.children.iter().map(|p| (p.name.clone(), p.text.clone())).collect())
+        .unwrap_or_default()
+}
+
+fn parse_repositories(element: Option<&Element>) -> Vec<PomRepository> {
+    element
+        .map(|repos| repos.children("repository")
+            .filter_map(|r| Some(PomRepository { id: r.text_at("id")?, url: r.text_at("url")? }))
+            .collect())
+        .unwrap_or_default()
+}
+
+// `!value` means anything but value
+fn negated(value: &str) -> (bool, &str) {
+    match value.strip_prefix('!') {
+        Some(rest) => (true, rest),
+        None => (false, value),
+    }
+}
+
+impl Profile {
+    fn parse(element: &Element) -> Self {
+        Self {
+            id: element.text_at("id").unwrap_or_default(),
+            activation: element.child("activation").map(Activation::parse),
+            properties: parse_properties(element.child("properties")),
+            dependency_management: parse_dependencies(element.find("dependencyManagement/dependencies")),
+            dependencies: parse_dependencies(element.child("dependencies")),
+            repositories: parse_repositories(element.child("repositories")),
+        }
+    }
+}
+
+impl Activation {
+    fn parse(element: &Element) -> Self {
+        Self {
+            active_by_default: element.text_at("activeByDefault").as_deref() == Some("true"),
+            jdk: element.text_at("jdk"),
+            os: element.child("os")
+                .map(|os| os.children.iter().map(|c| (c.name.clone(), c.text.to_lowercase())).collect())
+                .unwrap_or_default(),
+            property: element.child("property")
+                .and_then(|p| Some((p.text_at("name")?, p.text_at("value")))),
+            file_exists: element.text_at("file/exists"),
+            file_missing: element.text_at("file/missing"),
+        }
+    }
+
+    // Every condition given has to hold, as in Maven 3.2.2 and later
+    fn is_active(&self, context: &ProfileActivation, platform: &Platform) -> bool {
+        let mut conditions = Vec::new();
+        if let Some(jdk) = &self.jdk {
+            conditions.push(context.java().is_some_and(|java| jdk_matches(jdk, &java)));
+        }
+        if !self.os.is_empty() {
+            conditions.push(self.os.iter().all(|(key, value)| os_matches(key, value, platform)));
+        }
+        if let Some((name, value)) = &self.property {
+            conditions.push(property_matches(name, value.as_deref(), &context.properties));
+        }
+        if let Some(path) = &self.file_exists {
+            conditions.push(context.file(path).is_some_and(|file| file.exists()));
+        }
+        if let Some(path) = &self.file_missing {
+            conditions.push(context.file(path).is_some_and(|file| !file.exists()));
+        }
+        !conditions.is_empty() && conditions.into_iter().all(|holds| holds)
+    }
+}
+
+impl ProfileActivation {
+    // The version the way the JDK reports it, which is what `<jdk>` prefixes match
+    fn java(&self) -> Option<String> {
+        let version = self.java_version.as_deref()?;
+        let major = version.strip_prefix("1.").unwrap_or(version);
+        match major.parse::<u32>() {
+            Ok(major) if major < 9 => Some(format!("1.{}", major)),
+            _ => Some(major.to_string()),
+        }
+    }
+
+    fn file(&self, path: &str) -> Option<PathBuf> {
+        let basedir = self.basedir.as_ref()?;
+        let path = path
+            .replace("${project.basedir}", &basedir.to_string_lossy())
+            .replace("${basedir}", &basedir.to_string_lossy());
+        Some(basedir.join(path))
+    }
+
+    // Some(true) for -P id, Some(false) for -P !id
+    fn explicit(&self, id: &str) -> Option<bool> {
+        self.profiles.iter().find_map(|profile| {
+            match profile.strip_prefix('!').or_else(|| profile.strip_prefix('-')) {
+                Some(off) => (off == id).then_some(false),
+                None => (profile == id).then_some(true),
+            }
+        })
+    }
+}
+
+fn jdk_matches(rule: &str, java: &str) -> bool {
+    let (negate, rule) = negated(rule);
+    let matches = if rule.starts_with('[') || rule.starts_with('(') {
+        match (VersionReq::parse(rule), java.parse::<MavenVersion>()) {
+            (Ok(req), Ok(version)) => req.matches(&version),
+            _ => false,
+        }
+    } else {
+        java.starts_with(rule)
+    };
+    matches != negate
+}
+
+// Compared with what the JVM would report as os.name, os.arch and the OS family
+fn os_matches(key: &str, value: &str, platform: &Platform) -> bool {
+    let (negate, value) = negated(value);
+    let matches = match key {
+        "name" => value == match platform.name.as_str() {
+            "osx" => "mac os x",
+            other => other,
+        },
+        "family" => match value {
+            "windows" => platform.name == "windows",
+            "mac" => platform.name == "osx",
+            "unix" => platform.name != "windows",
+            _ => false,
+        },
+        "arch" => value == match platform.arch.as_str() {
+            "x86_64" => "amd64",
+            "x86_32" => "x86",
+            "aarch_64" => "aarch64",
+            other => other,
+        },
+        // there's no knowing the exact OS version of wherever the build ends up
+        _ => false,
+    };
+    matches != negate
+}
+
+fn property_matches(name: &str, value: Option<&str>, properties: &HashMap<String, String>) -> bool {
+    let (absent, name) = negated(name);
+    let current = properties.get(name).map(String::as_str);
+    match value {
+        _ if absent => current.is_none(),
+        None => current.is_some(),
+        Some(value) => {
+            let (negate, value) = negated(value);
+            (current == Some(value)) != negate
+        }
+    }
+}
+
+impl Model {
+    fn parse(xml: &str) -> anyhow::Result<Self> {
+        let root = Element::parse(xml)?;
+        if root.name != "project" {
+            anyhow::bail!("not a POM - root element is <{}>", root.name);
+        }
+
+        let parent = root.child("parent").map(|p| Coordinate {
+            namespace: p.text_at("groupId").unwrap_or_default(),
+            name: p.text_at("artifactId").unwrap_or_default(),
+            version: p.text_at("version"),
+            ..Default::default()
+        });
+
+        // an empty <relativePath/> turns the lookup off
+        let parent_path = match root.find("parent/relativePath") {
+            Some(path) => Some(path.text.clone()).filter(|p| !p.is_empty()),
+            None => root.child("parent").map(|_| "../pom.xml".to_string()),
+        };
+
+        let plugins: Vec<&Element> = root.find("build/plugins")
+            .map(|plugins| plugins.children("plugin").collect())
+            .unwrap_or_default();
+
+        // exec-maven-plugin and the jar plugin's archive/manifest both call it mainClass
+        let main_class = plugins.iter()
+            .filter_map(|plugin| plugin.child("configuration"))
+            .flat_map(|config| config.descendants("mainClass"))
+            .map(|e| e.text.clone())
+            .find(|text| !text.is_empty());
+
+        let compiler_release = plugins.iter()
+            .filter(|plugin| plugin.text_at("artifactId").as_deref() == Some("maven-compiler-plugin"))
+            .filter_map(|plugin| plugin.child("configuration"))
+            .find_map(|config| config.text_at("release").or_else(|| config.text_at("target")));
+
+        Ok(Self {
+            parent,
+            parent_path,
+            group_id: root.text_at("groupId"),
+            artifact_id: root.text_at("artifactId"),
+            version: root.text_at("version"),
+            packaging: root.text_at("packaging"),
+            properties: parse_properties(root.child("properties")),
+            dependency_management: parse_dependencies(root.find("dependencyManagement/dependencies")),
+            dependencies: parse_dependencies(root.child("dependencies")),
+            repositories: parse_repositories(root.child("repositories")),
+            source_directory: root.text_at("build/sourceDirectory"),
+            resource_directories: root.find("build/resources")
+                .map(|res| res.children("resource").filter_map(|r| r.text_at("directory")).collect())
+                .unwrap_or_default(),
+            main_class,
+            compiler_release,
+            relocation: root.find("distributionManagement/relocation").map(|r| Relocation {
+                coordinate: Coordinate {
+                    namespace: r.text_at("groupId").unwrap_or_default(),
+                    name: r.text_at("artifactId").unwrap_or_default(),
+                    version: r.text_at("version"),
+                    ..Default::default()
+                },
+                message: r.text_at("message"),
+            }),
+            profiles: root.find("profiles")
+                .map(|profiles| profiles.children("profile").map(Profile::parse).collect())
+                .unwrap_or_default(),
+        })
+    }
+
+    /// Merges in the profiles `context` activates, or the ones active by default
+    /// when none are, the same way Maven does before inheritance
+    fn activate(mut self, context: &ProfileActivation, platform: &Platform) -> Self {
+        let profiles = std::mem::take(&mut self.profiles);
+        let mut active: Vec<&Profile> = profiles.iter()
+            .filter(|p| context.explicit(&p.id)
+                .unwrap_or_else(|| p.activation.as_ref().is_some_and(|a| a.is_active(context, platform))))
+            .collect();
+        if active.is_empty() {
+            active = profiles.iter()
+                .filter(|p| context.explicit(&p.id) != Some(false))
+                .filter(|p| p.activation.as_ref().is_some_and(|a| a.active_by_default))
+                .collect();
+        }
+
+        for profile in active {
+            self.properties.extend(profile.properties.iter().cloned());
+            for dep in &profile.dependency_management {
+                self.dependency_management.retain(|d| d.management_key() != dep.management_key());
+                self.dependency_management.push(dep.clone());
+            }
+            for dep in &profile.dependencies {
+                self.dependencies.retain(|d| d.management_key() != dep.management_key());
+                self.dependencies.push(dep.clone());
+            }
+            for repo in &profile.repositories {
+                self.repositories.retain(|r| r.id != repo.id);
+                self.repositories.push(repo.clone());
+            }
+        }
+        self
+    }
+}
+
+/// Parent a POM declares, with the relative path Maven would look for it at
+pub fn declared_parent(xml: &str) -> anyhow::Result<Option<(Coordinate, Option<String>)>> {
+    let model = Model::parse(xml)?;
+    Ok(model.parent.map(|parent| (parent, model.parent_path)))
+}
+
+/// Replaces every `${name}` that has a value, unknown expressions are left as they are
+pub fn interpolate(value: &str, properties: &HashMap<String, String>) -> String {
+    let mut result = value.to_string();
+
+    // properties can refer to other properties, give up after a few rounds in case of a cycle
+    for _ in 0..10 {
+        let mut changed = false;
+        let mut out = String::with_capacity(result.len());
+        let mut rest = result.as_str();
+
+        while let Some(start) = rest.find("${") {
+            out.push_str(&rest[..start]);
+            match rest[start..].find('}') {
+                Some(len) => {
+                    let name = &rest[start + 2..start + len];
+                    match properties.get(name) {
+                        Some(value) => {
+                            out.push_str(value);
+                            changed = true;
+                        }
+                        None => out.push_str(&rest[start..start + len + 1]),
+                    }
+                    rest = &rest[start + len + 1..];
+                }
+                None => {
+                    out.push_str(&rest[start..]);
+                    rest = "";
+                }
+            }
+        }
+        out.push_str(rest);
+
+        result = out;
+        if !changed {
+            break;
+        }
+    }
+
+    result
+}
+
+/// The model a POM describes once its parents, properties, managed versions
+/// and imported BOMs have all been applied
+#[derive(Clone, Debug)]
+pub struct EffectivePom {
+    pub coordinate: Coordinate,
+    pub packaging: String,
+    pub dependency_management: Vec<Dependency>,
+    pub dependencies: Vec<Dependency>,
+    pub repositories: Vec<PomRepository>,
+    pub source_directory: Option<String>,
+    pub resource_directories: Vec<String>,
+    pub main_class: Option<String>,
+    pub compiler_release: Option<String>,
+    pub relocation: Option<Relocation>,
+}
+
+#[derive(Debug)]
+pub enum ModelError {
+    /// A parent or imported BOM that has to be added to the builder first
+    Missing(Coordinate),
+    Invalid(anyhow::Error),
+}
+
+impl fmt::Display for ModelError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Missing(coord) => write!(f, "POM {} is needed to build the effective model", coord),
+            Self::Invalid(err) => write!(f, "{:#}", err),
+        }
+    }
+}
+
+impl std::error::Error for ModelError {}
+
+impl From<anyhow::Error> for ModelError {
+    fn from(err: anyhow::Error) -> Self {
+        Self::Invalid(err)
+    }
+}
+
+/// Builds effective POMs from raw ones. Parents and imported BOMs aren't
+/// fetched here: `build` reports the first one it lacks with
+/// `ModelError::Missing`, the caller adds it and tries again.
+#[derive(Default)]
+pub struct ModelBuilder {
+    poms: HashMap<String, String>,
+    platform: Platform,
+    activation: ProfileActivation,
+}
+
+impl ModelBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Builds for another platform than this one, which only changes `${os.detected.*}`
+    pub fn with_platform(mut self, platform: Platform) -> Self {
+        self.platform = platform;
+        self
+    }
+
+    /// Decides which `<profiles>` get merged in
+    pub fn with_activation(mut self, activation: ProfileActivation) -> Self {
+        self.activation = activation;
+        self
+    }
+
+    pub fn add(&mut self, coord: &Coordinate, xml: String) {
+        self.poms.insert(coord.to_string(), xml);
+    }
+
+    pub fn build(&self, xml: &str) -> Result<EffectivePom, ModelError> {
+        self.build_inner(xml, 0)
+    }
+
+    fn build_inner(&self, xml: &str, depth: usize) -> Result<EffectivePom, ModelError> {
+        if depth > MAX_PARENTS {
+            return Err(anyhow::anyhow!("BOM imports nest too deeply").into());
+        }
+
+        // child first, then each ancestor
+        let parse = |xml: &str| Model::parse(xml).map(|model| model.activate(&self.activation, &self.platform));
+        let mut chain = vec![parse(xml)?];
+        while let Some(parent) = chain.last().and_then(|m| m.parent.clone()) {
+            if chain.len() > MAX_PARENTS {
+                return Err(anyhow::anyhow!("parent chain of {} is too long, is there a cycle?", parent).into());
+            }
+            let xml = self.poms.get(&parent.to_string()).ok_or(ModelError::Missing(parent))?;
+            chain.push(parse(xml)?);
+        }
+
+        let child = &chain[0];
+        let group_id = chain.iter().find_map(|m| m.group_id.clone().or_else(|| m.parent.as_ref().map(|p| p.namespace.clone())));
+        let version = chain.iter().find_map(|m| m.version.clone().or_else(|| m.parent.as_ref().and_then(|p| p.version.clone())));
+        let artifact_id = child.artifact_id.clone()
+            .ok_or_else(|| anyhow::anyhow!("POM has no artifactId"))?;
+
+        // os-maven-plugin's properties, which POMs are free to override
+        let mut properties: HashMap<String, String> = self.platform.properties().into_iter().collect();
+        let mut management: Vec<Dependency> = Vec::new();
+        let mut dependencies: Vec<Dependency> = Vec::new();
+        let mut repositories: Vec<PomRepository> = Vec::new();
+        let mut source_directory = None;
+        let mut resource_directories = Vec::new();
+        let mut main_class = None;
+        let mut compiler_release = None;
+
+        // oldest ancestor first so every child overrides what it inherits
+        for model in chain.iter().rev() {
+            properties.extend(model.properties.iter().cloned());
+
+            for dep in &model.dependency_management {
+                management.retain(|d| d.management_key() != dep.management_key());
+                management.push(dep.clone());
+            }
+            for dep in &model.dependencies {
+                dependencies.retain(|d| d.management_key() != dep.management_key());
+                dependencies.push(dep.clone());
+            }
+            for repo in &model.repositories {
+                repositories.retain(|r| r.id != repo.id);
+                repositories.push(repo.clone());
+            }
+
+            source_directory = model.source_directory.clone().or(source_directory);
+            if !model.resource_directories.is_empty() {
+                resource_directories = model.resource_directories.clone();
+            }
+            main_class = model.main_class.clone().or(main_class);
+            compiler_release = model.compiler_release.clone().or(compiler_release);
+        }
+        // -D wins over anything a POM sets, as on the mvn command line
+        properties.extend(self.activation.properties.iter().map(|(k, v)| (k.clone(), v.clone())));
+
+        let group_id = group_id.ok_or_else(|| anyhow::anyhow!("POM for {} has no groupId", artifact_id))?;
+        let version = version.ok_or_else(|| anyhow::anyhow!("POM for {} has no version", artifact_id))?;
+        let packaging = child.packaging.clone().unwrap_or_else(|| "jar".to_string());
+
+        for (prefix, value) in [("groupId", &group_id), ("artifactId", &artifact_id), ("version", &version), ("packaging", &packaging)] {
+            properties.insert(format!("project.{}", prefix), value.clone());
+            properties.insert(format!("pom.{}", prefix), value.clone());
+        }
+        if let Some(parent) = &child.parent {
+            properties.insert("project.parent.groupId".to_string(), parent.namespace.clone());
+            properties.insert("project.parent.artifactId".to_string(), parent.name.clone());
+            if let Some(version) = &parent.version {
+                properties.insert("project.parent.version".to_string(), version.clone());
+            }
+        }
+
+        // a classifier that only names this platform through a property is still native
+        let mut neutral = properties.clone();
+        neutral.remove("os.detected.classifier");
+        for dep in management.iter_mut().chain(dependencies.iter_mut()) {
+            dep.native = dep.classifier.as_ref().is_some_and(|c| interpolate(c, &neutral).contains(CLASSIFIER_VARIABLE));
+            dep.interpolate(&properties);
+        }
+        for repo in &mut repositories {
+            repo.url = interpolate(&repo.url, &properties);
+        }
+
+        // import-scoped BOMs contribute their managed versions, declared ones win
+        let mut imported = Vec::new();
+        for bom in management.iter().filter(|d| d.scope.as_deref() == Some("import")) {
+            // imports are always <type>pom</type>, what's wanted is the POM itself
+            let coord = Coordinate { extension: None, classifier: None, ..bom.coordinate() };
+            let xml = self.poms.get(&coord.to_string()).ok_or(ModelError::Missing(coord))?;
+            imported.extend(self.build_inner(xml, depth + 1)?.dependency_management);
+        }
+        management.retain(|d| d.scope.as_deref() != Some("import"));
+        for dep in imported {
+            if !management.iter().any(|d| d.management_key() == dep.management_key()) {
+                management.push(dep);
+            }
+        }
+
+        for dep in &mut dependencies {
+            if let Some(managed) = management.iter().find(|m| m.management_key() == dep.management_key()) {
+                if dep.version.is_none() {
+                    dep.version = managed.version.clone();
+                }
+                if dep.scope.is_none() {
+                    dep.scope = managed.scope.clone();
+                }
+                for exclusion in &managed.exclusions {
+                    if !dep.exclusions.contains(exclusion) {
+                        dep.exclusions.push(exclusion.clone());
+                    }
+                }
+            }
+        }
+
+        // only the artifact's own POM can say it moved, a parent saying so would move every child
+        let relocation = child.relocation.as_ref().map(|moved| {
+            let or = |part: &str, current: &str| match interpolate(part, &properties) {
+                part if part.is_empty() => current.to_string(),
+                part => part,
+            };
+            Relocation {
+                coordinate: Coordinate {
+                    namespace: or(&moved.coordinate.namespace, &group_id),
+                    name: or(&moved.coordinate.name, &artifact_id),
+                    version: Some(or(moved.coordinate.version.as_deref().unwrap_or_default(), &version)),
+                    ..Default::default()
+                },
+                message: moved.message.as_ref().map(|m| interpolate(m, &properties)),
+            }
+        });
+
+        Ok(EffectivePom {
+            coordinate: Coordinate { namespace: group_id, name: artifact_id, version: Some(version), ..Default::default() },
+            relocation,
+            packaging,
+            source_directory: source_directory.map(|dir| interpolate(&dir, &properties)),
+            resource_directories: resource_directories.iter().map(|dir| interpolate(dir, &properties)).collect(),
+            main_class: main_class.map(|class| interpolate(&class, &properties)),
+            // the compiler plugin falls back to these properties when it isn't configured
+            compiler_release: compiler_release
+                .or_else(|| properties.get("maven.compiler.release").cloned())
+                .or_else(|| properties.get("maven.compiler.target").cloned())
+                .or_else(|| properties.get("maven.compiler.source").cloned())
+                .map(|release| interpolate(&release, &properties)),
+            dependency_management: management,
+            dependencies,
+            repositories,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PARENT: &str = r#"
+        <project>
+            <groupId>com.ourcorp</groupId>
+            <artifactId>parent</artifactId>
+            <version>7</version>
+            <packaging>pom</packaging>
+            <properties>
+                <slf4j.version>2.0.9</slf4j.version>
+                <maven.compiler.release>17</maven.compiler.release>
+            </properties>
+            <dependencyManagement>
+                <dependencies>
+                    <dependency>
+                        <groupId>org.slf4j</groupId>
+                        <artifactId>slf4j-api</artifactId>
+                        <version>${slf4j.version}</version>
+                    </dependency>
+                    <dependency>
+                        <groupId>com.fasterxml.jackson</groupId>
+                        <artifactId>jackson-bom</artifactId>
+                        <version>2.15.2</version>
+                        <type>pom</type>
+                        <scope>import</scope>
+                    </dependency>
+                </dependencies>
+            </dependencyManagement>
+        </project>
+    "#;
+
+    const BOM: &str = r#"
+        <project>
+            <groupId>com.fasterxml.jackson</groupId>
+            <artifactId>jackson-bom</artifactId>
+            <version>2.15.2</version>
+            <dependencyManagement>
+                <dependencies>
+                    <dependency>
+                        <groupId>com.fasterxml.jackson.core</groupId>
+                        <artifactId>jackson-databind</artifactId>
+                        <version>${project.version}</version>
+                        <exclusions>
+                            <exclusion><groupId>org.example</groupId><artifactId>*</artifactId></exclusion>
+                        </exclusions>
+                    </dependency>
+                </dependencies>
+            </dependencyManagement>
+        </project>
+    "#;
+
+    const CHILD: &str = r#"
+        <project>
+            <parent>
+                <groupId>com.ourcorp</groupId>
+                <artifactId>parent</artifactId>
+                <version>7</version>
+            </parent>
+            <artifactId>ledger</artifactId>
+            <dependencies>
+                <dependency>
+                    <groupId>org.slf4j</groupId>
+                    <artifactId>slf4j-api</artifactId>
+                </dependency>
+                <dependency>
+                    <groupId>com.fasterxml.jackson.core</groupId>
+                    <artifactId>jackson-databind</artifactId>
+                    <scope>runtime</scope>
+                </dependency>
+                <dependency>
+                    <groupId>${project.groupId}</groupId>
+                    <artifactId>ledger-api</artifactId>
+                    <version>${project.version}</version>
+                </dependency>
+            </dependencies>
+            <build>
+                <plugins>
+                    <plugin>
+                        <artifactId>maven-jar-plugin</artifactId>
+                        <configuration>
+                            <archive><manifest><mainClass>com.ourcorp.ledger.Main</mainClass></manifest></archive>
+                        </configuration>
+                    </plugin>
+                </plugins>
+            </build>
+        </project>
+    "#;
+
+    #[test]
+    fn test_reports_missing_parent_then_builds() -> anyhow::Result<()> {
+        let mut builder = ModelBuilder::new();
+
+        match builder.build(CHILD) {
+            Err(ModelError::Missing(coord)) => assert_eq!(coord.to_string(), "com.ourcorp:parent:7"),
+            other => panic!("expected a missing parent, got {:?}", other.map(|p| p.coordinate)),
+        }
+        builder.add(&Coordinate::parse("com.ourcorp:parent:7")?, PARENT.to_string());
+
+        match builder.build(CHILD) {
+            Err(ModelError::Missing(coord)) => assert_eq!(coord.to_string(), "com.fasterxml.jackson:jackson-bom:2.15.2"),
+            other => panic!("expected a missing BOM, got {:?}", other.map(|p| p.coordinate)),
+        }
+        builder.add(&Coordinate::parse("com.fasterxml.jackson:jackson-bom:2.15.2")?, BOM.to_string());
+
+        let pom = builder.build(CHILD)?;
+        assert_eq!(pom.coordinate.to_string(), "com.ourcorp:ledger:7");
+        assert_eq!(pom.packaging, "jar");
+        assert_eq!(pom.compiler_release.as_deref(), Some("17"));
+        assert_eq!(pom.main_class.as_deref(), Some("com.ourcorp.ledger.Main"));
+
+        let versions: Vec<String> = pom.dependencies.iter().map(|d| d.coordinate().to_string()).collect();
+        assert_eq!(versions, vec![
+            "org.slf4j:slf4j-api:2.0.9",
+            "com.fasterxml.jackson.core:jackson-databind:2.15.2",
+            "com.ourcorp:ledger-api:7",
+        ]);
+        assert_eq!(pom.dependencies[1].scope.as_deref(), Some("runtime"));
+        assert_eq!(pom.dependencies[1].exclusions[0].to_string(), "org.example:*");
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_interpolate() {
+        let properties = HashMap::from([
+            ("a".to_string(), "${b}-x".to_string()),
+            ("b".to_string(), "1".to_string()),
+            ("loop".to_string(), "${loop}".to_string()),
+        ]);
+
+        assert_eq!(interpolate("${a}/${unknown}", &properties), "1-x/${unknown}");
+        assert_eq!(interpolate("${loop}", &properties), "${loop}");
+        assert_eq!(interpolate("open ${a", &properties), "open ${a");
+    }
+
+    #[test]
+    fn test_os_detected_classifier() -> anyhow::Result<()> {
+        let pom = ModelBuilder::new().with_platform(Platform::new("osx", "aarch_64")).build(r#"
+            <project>
+                <groupId>com.ourcorp</groupId>
+                <artifactId>ledger</artifactId>
+                <version>1.0.0</version>
+                <dependencies>
+                    <dependency>
+                        <groupId>io.netty</groupId>
+                        <artifactId>netty-tcnative-boringssl-static</artifactId>
+                        <version>2.0.61.Final</version>
+                        <classifier>${os.detected.classifier}</classifier>
+                    </dependency>
+                    <dependency>
+                        <groupId>io.netty</groupId>
+                        <artifactId>netty-transport-native-kqueue</artifactId>
+                        <version>4.1.100.Final</version>
+                        <classifier>${kqueue.classifier}</classifier>
+                    </dependency>
+                    <dependency>
+                        <groupId>com.google.protobuf</groupId>
+                        <artifactId>protoc</artifactId>
+                        <version>3.24.4</version>
+                        <type>exe</type>
+                        <classifier>osx-aarch_64</classifier>
+                    </dependency>
+                </dependencies>
+                <properties>
+                    <kqueue.classifier>${os.detected.classifier}</kqueue.classifier>
+                </properties>
+            </project>
+        "#)?;
+
+        assert_eq!(pom.dependencies[0].classifier.as_deref(), Some("osx-aarch_64"));
+        assert_eq!(pom.dependencies[1].classifier.as_deref(), Some("osx-aarch_64"));
+        // only the ones asking for whatever platform they're on are native
+        let native: Vec<bool> = pom.dependencies.iter().map(|dep| dep.native).collect();
+        assert_eq!(native, vec![true, true, false]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_relocation() -> anyhow::Result<()> {
+        let pom = ModelBuilder::new().build(r#"
+            <project>
+                <groupId>mysql</groupId>
+                <artifactId>mysql-connector-java</artifactId>
+                <version>8.0.33</version>
+                <packaging>pom</packaging>
+                <distributionManagement>
+                    <relocation>
+                        <groupId>com.mysql</groupId>
+                        <artifactId>mysql-connector-j</artifactId>
+                        <message>MySQL Connector/J artifacts moved to reverse-DNS compliant Maven 2+ coordinates.</message>
+                    </relocation>
+                </distributionManagement>
+            </project>
+        "#)?;
+
+        assert_eq!(pom.packaging, "pom");
+        let relocation = pom.relocation.expect("the POM says it moved");
+        // the version wasn't given, so it stays the same
+        assert_eq!(relocation.coordinate.to_string(), "com.mysql:mysql-connector-j:8.0.33");
+        assert!(relocation.message.is_some_and(|m| m.starts_with("MySQL Connector/J")));
+
+        let current = "<project><groupId>com.mysql</groupId><artifactId>mysql-connector-j</artifactId><version>8.0.33</version></project>";
+        assert!(ModelBuilder::new().build(current)?.relocation.is_none());
+        Ok(())
+    }
+
+    #[test]
+    fn test_profile_activation() -> anyhow::Result<()> {
+        let pom = r#"
+            <project>
+                <groupId>com.ourcorp</groupId>
+                <artifactId>ledger</artifactId>
+                <version>1.0.0</version>
+                <properties><jaxb.version>2.3.1</jaxb.version></properties>
+                <profiles>
+                    <profile>
+                        <id>java11</id>
+                        <activation><jdk>[11,)</jdk></activation>
+                        <properties><jaxb.version>4.0.4</jaxb.version></properties>
+                        <dependencies>
+                            <dependency>
+                                <groupId>jakarta.xml.bind</groupId>
+                                <artifactId>jakarta.xml.bind-api</artifactId>
+                                <version>${jaxb.version}</version>
+                            </dependency>
+                        </dependencies>
+                    </profile>
+                    <profile>
+                        <id>linux</id>
+                        <activation><os><family>unix</family><arch>amd64</arch></os></activation>
+                        <dependencies>
+                            <dependency>
+                                <groupId>io.netty</groupId>
+                                <artifactId>netty-transport-native-epoll</artifactId>
+                                <version>4.1.100</version>
+                            </dependency>
+                        </dependencies>
+                    </profile>
+                    <profile>
+                        <id>metrics</id>
+                        <activation><property><name>metrics</name></property></activation>
+                        <dependencies>
+                            <dependency>
+                                <groupId>io.micrometer</groupId>
+                                <artifactId>micrometer-core</artifactId>
+                                <version>1.12.0</version>
+                            </dependency>
+                        </dependencies>
+                    </profile>
+                    <profile>
+                        <id>fallback</id>
+                        <activation><activeByDefault>true</activeByDefault></activation>
+                        <dependencies>
+                            <dependency>
+                                <groupId>org.slf4j</groupId>
+                                <artifactId>slf4j-simple</artifactId>
+                                <version>2.0.9</version>
+                            </dependency>
+                        </dependencies>
+                    </profile>
+                </profiles>
+            </project>
+        "#;
+        let deps = |activation: ProfileActivation, platform: Platform| -> anyhow::Result<Vec<String>> {
+            let pom = ModelBuilder::new().with_platform(platform).with_activation(activation).build(pom)?;
+            Ok(pom.dependencies.iter().map(|d| d.coordinate().to_string()).collect())
+        };
+        let mac = Platform::new("osx", "aarch_64");
+
+        // nothing matched, so the default profile is used
+        assert_eq!(deps(ProfileActivation::default(), mac.clone())?, vec!["org.slf4j:slf4j-simple:2.0.9"]);
+
+        let java17 = ProfileActivation { java_version: Some("17".to_string()), ..Default::default() };
+        assert_eq!(deps(java17.clone(), Platform::new("linux", "x86_64"))?, vec![
+            "jakarta.xml.bind:jakarta.xml.bind-api:4.0.4",
+            "io.netty:netty-transport-native-epoll:4.1.100",
+        ]);
+        let java8 = ProfileActivation { java_version: Some("1.8".to_string()), ..Default::default() };
+        assert_eq!(deps(java8, mac.clone())?, vec!["org.slf4j:slf4j-simple:2.0.9"]);
+
+        // -D both activates by property and overrides the POM's own properties
+        let metrics = ProfileActivation {
+            properties: HashMap::from([("metrics".to_string(), "on".to_string()), ("jaxb.version".to_string(), "4.0.0".to_string())]),
+            ..java17.clone()
+        };
+        assert_eq!(deps(metrics, mac.clone())?, vec![
+            "jakarta.xml.bind:jakarta.xml.bind-api:4.0.0",
+            "io.micrometer:micrometer-core:1.12.0",
+        ]);
+
+        // -P turns profiles on and off whatever their rules say
+        let explicit = ProfileActivation { profiles: vec!["metrics".to_string(), "!java11".to_string()], ..java17 };
+        assert_eq!(deps(explicit, mac)?, vec!["io.micrometer:micrometer-core:1.12.0"]);
+
+        Ok(())
+    }
+}
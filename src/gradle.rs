@@ -0,0 +1,841 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::coordinates::Coordinate;
+use crate::import::Import;
+use crate::manifest::{DepSpec, Manifest, ProjectConfig, RepositoryConfig};
+
+/// A dependency declaration from a `dependencies {}` block
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct GradleDependency {
+    pub configuration: String,
+    pub group: String,
+    pub name: String,
+    pub version: Option<String>,
+    pub platform: bool,
+    pub exclusions: Vec<String>,
+    pub classifier: Option<String>,
+    pub extension: Option<String>,
+}
+
+impl GradleDependency {
+    pub fn key(&self) -> String {
+        format!("{}:{}", self.group, self.name)
+    }
+
+    /// The gallade.toml key, with the extension and classifier when there are any
+    pub fn manifest_key(&self) -> String {
+        let coord = Coordinate {
+            namespace: self.group.clone(),
+            name: self.name.clone(),
+            ..Default::default()
+        };
+        coord.with_type(self.extension.as_deref(), self.classifier.as_deref()).to_string()
+    }
+}
+
+/// What a best-effort read of a Gradle build turned up
+#[derive(Debug, Default)]
+pub struct GradleProject {
+    pub group: Option<String>,
+    pub name: String,
+    pub version: Option<String>,
+    pub java_version: Option<String>,
+    pub main_class: Option<String>,
+    pub dependencies: Vec<GradleDependency>,
+    pub repositories: Vec<(String, String)>,
+    pub warnings: Vec<String>,
+}
+
+/// `gradle/libs.versions.toml`
+#[derive(Debug, Default)]
+pub struct VersionCatalog {
+    libraries: HashMap<String, (String, String, Option<String>)>,
+    bundles: HashMap<String, Vec<String>>,
+}
+
+// `guava-core`, `guava_core` and `guava.core` are all `libs.guava.core` in the build script
+fn accessor(alias: &str) -> String {
+    alias.replace(['-', '_'], ".")
+}
+
+impl VersionCatalog {
+    pub fn parse(content: &str) -> anyhow::Result<Self> {
+        let doc: toml::Table = toml::from_str(content)?;
+        let empty = toml::Table::new();
+        let versions = doc.get("versions").and_then(|v| v.as_table()).unwrap_or(&empty);
+
+        let version_of = |value: &toml::Value| -> Option<String> {
+            match value {
+                toml::Value::String(v) => Some(v.clone()),
+                // rich versions, the preferred one is what Gradle would pick first
+                toml::Value::Table(rich) => ["strictly", "require", "prefer"].iter()
+                    .find_map(|key| rich.get(*key).and_then(|v| v.as_str()).map(String::from)),
+                _ => None,
+            }
+        };
+
+        let mut catalog = Self::default();
+        for (alias, library) in doc.get("libraries").and_then(|v| v.as_table()).unwrap_or(&empty) {
+            let entry = match library {
+                toml::Value::String(notation) => {
+                    let mut parts = notation.splitn(3, ':');
+                    match (parts.next(), parts.next()) {
+                        (Some(group), Some(name)) => Some((group.to_string(), name.to_string(), parts.next().map(String::from))),
+                        _ => None,
+                    }
+                }
+                toml::Value::Table(table) => {
+                    let (group, name) = match table.get("module").and_then(|m| m.as_str()) {
+                        Some(module) => module.split_once(':')
+                            .map(|(g, n)| (g.to_string(), n.to_string()))
+                            .unzip(),
+                        None => (
+                            table.get("group").and_then(|g| g.as_str()).map(String::from),
+                            table.get("name").and_then(|n| n.as_str()).map(String::from),
+                        ),
+                    };
+                    let version = match table.get("version") {
+                        Some(toml::Value::Table(version)) if version.contains_key("ref") => version.get("ref")
+                            .and_then(|r| r.as_str())
+                            .and_then(|r| versions.get(r))
+                            .and_then(version_of),
+                        Some(version) => version_of(version),
+                        None => table.get("version.ref")
+                            .and_then(|r| r.as_str())
+                            .and_then(|r| versions.get(r))
+                            .and_then(version_of),
+                    };
+                    group.zip(name).map(|(group, name)| (group, name, version))
+                }
+                _ => None,
+            };
+
+            if let Some(entry) = entry {
+                catalog.libraries.insert(accessor(alias), entry);
+            }
+        }
+
+        for (alias, bundle) in doc.get("bundles").and_then(|v| v.as_table()).unwrap_or(&empty) {
+            let members = bundle.as_array()
+                .map(|members| members.iter().filter_map(|m| m.as_str()).map(accessor).collect())
+                .unwrap_or_default();
+            catalog.bundles.insert(accessor(alias), members);
+        }
+
+        Ok(catalog)
+    }
+}
+
+// Drops // and /* */ comments, leaving string literals alone
+fn strip_comments(script: &str) -> String {
+    let chars: Vec<char> = script.chars().collect();
+    let mut out = String::with_capacity(script.len());
+    let mut quote = None;
+    let mut i = 0;
+
+    while i < chars.len() {
+        let c = chars[i];
+        match quote {
+            Some(q) => {
+                out.push(c);
+                if c == '\\' && i + 1 < chars.len() {
+                    out.push(chars[i + 1]);
+                    i += 1;
+                } else if c == q {
+                    quote = None;
+                }
+            }
+            None if c == '"' || c == '\'' => {
+                quote = Some(c);
+                out.push(c);
+            }
+            None if c == '/' && chars.get(i + 1) == Some(&'/') => {
+                while i < chars.len() && chars[i] != '\n' {
+                    i += 1;
+                }
+                continue;
+            }
+            None if c == '/' && chars.get(i + 1) == Some(&'*') => {
+                i += 2;
+                while i + 1 < chars.len() && !(chars[i] == '*' && chars[i + 1] == '/') {
+                    i += 1;
+                }
+                i += 2;
+                continue;
+            }
+            None => out.push(c),
+        }
+        i += 1;
+    }
+
+    out
+}
+
+// Contents of every `name { ... }` block that isn't inside a block named in `skip`
+fn find_blocks(script: &str, name: &str, skip: &[&str]) -> Vec<String> {
+    let chars: Vec<char> = script.chars().collect();
+    let mut stack: Vec<(String, usize)> = Vec::new();
+    let mut blocks = Vec::new();
+    let mut quote = None;
+
+    for (i, &c) in chars.iter().enumerate() {
+        if let Some(q) = quote {
+            if c == q && chars.get(i.wrapping_sub(1)) != Some(&'\\') {
+                quote = None;
+            }
+            continue;
+        }
+
+        match c {
+            '"' | '\'' => quote = Some(c),
+            '{' => stack.push((block_name(&chars[..i]), i + 1)),
+            '}' => {
+                if let Some((block, start)) = stack.pop() {
+                    let nested_in_skipped = stack.iter().any(|(outer, _)| skip.contains(&outer.as_str()));
+                    if block == name && !nested_in_skipped {
+                        blocks.push(chars[start..i].iter().collect());
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    blocks
+}
+
+// The identifier right before a `{`, skipping over an argument list like `maven("...") {`
+fn block_name(before: &[char]) -> String {
+    let mut end = before.len();
+    while end > 0 && before[end - 1].is_whitespace() {
+        end -= 1;
+    }
+
+    if end > 0 && before[end - 1] == ')' {
+        let mut depth = 0;
+        while end > 0 {
+            end -= 1;
+            match before[end] {
+                ')' => depth += 1,
+                '(' => {
+                    depth -= 1;
+                    if depth == 0 {
+                        break;
+                    }
+                }
+                _ => {}
+            }
+        }
+        while end > 0 && before[end - 1].is_whitespace() {
+            end -= 1;
+        }
+    }
+
+    let mut start = end;
+    while start > 0 && (before[start - 1].is_alphanumeric() || before[start - 1] == '_') {
+        start -= 1;
+    }
+    before[start..end].iter().collect()
+}
+
+// Splits a block body at newlines and semicolons that aren't inside brackets or strings
+fn statements(block: &str) -> Vec<String> {
+    let mut result = Vec::new();
+    let mut current = String::new();
+    let mut depth = 0i32;
+    let mut quote = None;
+
+    for c in block.chars() {
+        if let Some(q) = quote {
+            if c == q {
+                quote = None;
+            }
+            current.push(c);
+            continue;
+        }
+
+        match c {
+            '"' | '\'' => quote = Some(c),
+            '(' | '{' | '[' => depth += 1,
+            ')' | '}' | ']' => depth -= 1,
+            // a line ending in a comma continues on the next one
+            '\n' | ';' if depth <= 0 && !current.trim_end().ends_with(',') => {
+                result.push(std::mem::take(&mut current));
+                continue;
+            }
+            _ => {}
+        }
+        current.push(c);
+    }
+    result.push(current);
+
+    result.into_iter()
+        .map(|s| s.trim().to_string())
+        .filter(|s| !s.is_empty())
+        .collect()
+}
+
+// Value of a string literal starting at `s`, with $name and ${name} resolved from `vars`
+fn string_literal(s: &str, vars: &HashMap<String, String>) -> Option<(String, usize)> {
+    let quote = s.chars().next().filter(|c| *c == '"' || *c == '\'')?;
+    let end = s[1..].find(quote)? + 1;
+    let raw = &s[1..end];
+
+    // single quotes are plain strings in Groovy
+    if quote == '\'' {
+        return Some((raw.to_string(), end + 1));
+    }
+
+    let mut out = String::new();
+    let mut rest = raw;
+    while let Some(pos) = rest.find('$') {
+        out.push_str(&rest[..pos]);
+        let after = &rest[pos + 1..];
+        let (name, consumed) = if let Some(inner) = after.strip_prefix('{') {
+            match inner.find('}') {
+                Some(close) => (&inner[..close], close + 2),
+                None => (inner, inner.len() + 1),
+            }
+        } else {
+            let len = after.find(|c: char| !(c.is_alphanumeric() || c == '_' || c == '.')).unwrap_or(after.len());
+            (&after[..len], len)
+        };
+
+        match vars.get(name.trim_start_matches("project.").trim_start_matches("rootProject.")) {
+            Some(value) => out.push_str(value),
+            None => out.push_str(&format!("${{{}}}", name)),
+        }
+        rest = &after[consumed..];
+    }
+    out.push_str(rest);
+
+    Some((out, end + 1))
+}
+
+// `(...)` contents for a call starting at `s`, and the rest after the closing paren
+fn call_args(s: &str) -> Option<(&str, &str)> {
+    let s = s.trim_start();
+    if !s.starts_with('(') {
+        return None;
+    }
+
+    let mut depth = 0;
+    let mut quote = None;
+    for (i, c) in s.char_indices() {
+        if let Some(q) = quote {
+            if c == q {
+                quote = None;
+            }
+            continue;
+        }
+        match c {
+            '"' | '\'' => quote = Some(c),
+            '(' => depth += 1,
+            ')' => {
+                depth -= 1;
+                if depth == 0 {
+                    return Some((&s[1..i], &s[i + 1..]));
+                }
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+// `key: 'value'` (Groovy) or `key = "value"` (Kotlin) named arguments
+fn named_arg(args: &str, key: &str, vars: &HashMap<String, String>) -> Option<String> {
+    let mut search = args;
+    while let Some(pos) = search.find(key) {
+        let before_ok = pos == 0 || !search[..pos].ends_with(|c: char| c.is_alphanumeric() || c == '_');
+        let after = search[pos + key.len()..].trim_start();
+        if before_ok {
+            if let Some(value) = after.strip_prefix(':').or_else(|| after.strip_prefix('=')) {
+                if let Some((literal, _)) = string_literal(value.trim_start(), vars) {
+                    return Some(literal);
+                }
+            }
+        }
+        search = &search[pos + key.len()..];
+    }
+    None
+}
+
+fn exclusions(closure: &str, vars: &HashMap<String, String>) -> Vec<String> {
+    let mut found = Vec::new();
+    for statement in statements(closure) {
+        let Some(rest) = statement.strip_prefix("exclude") else {
+            continue;
+        };
+        let args = call_args(rest).map(|(args, _)| args).unwrap_or(rest);
+        let group = named_arg(args, "group", vars).unwrap_or_else(|| "*".to_string());
+        let module = named_arg(args, "module", vars).unwrap_or_else(|| "*".to_string());
+        found.push(format!("{}:{}", group, module));
+    }
+    found
+}
+
+// group:name[:version[:classifier]][@extension]
+fn parse_notation(notation: &str) -> Option<GradleDependency> {
+    let (notation, extension) = match notation.split_once('@') {
+        Some((notation, extension)) => (notation, Some(extension.to_string())),
+        None => (notation, None),
+    };
+    let mut parts = notation.split(':');
+    Some(GradleDependency {
+        group: parts.next()?.to_string(),
+        name: parts.next()?.to_string(),
+        version: parts.next().filter(|v| !v.is_empty()).map(String::from),
+        classifier: parts.next().filter(|c| !c.is_empty()).map(String::from),
+        extension,
+        ..Default::default()
+    })
+}
+
+struct ScriptContext<'a> {
+    vars: &'a HashMap<String, String>,
+    catalog: Option<&'a VersionCatalog>,
+}
+
+impl ScriptContext<'_> {
+    fn dependency(&self, statement: &str, warnings: &mut Vec<String>) -> Vec<GradleDependency> {
+        let configuration: String = statement.chars().take_while(|c| c.is_alphanumeric() || *c == '_').collect();
+        let rest = statement[configuration.len()..].trim_start();
+
+        // implementation("...") { closure } or implementation '...'
+        let (mut args, closure) = match call_args(rest) {
+            Some((args, after)) => (args.trim(), after.trim()),
+            None => match rest.find('{') {
+                Some(open) => (rest[..open].trim().trim_end_matches(','), &rest[open..]),
+                None => (rest, ""),
+            },
+        };
+        let excluded = exclusions(closure.trim_start_matches('{').trim_end_matches('}'), self.vars);
+
+        let mut platform = false;
+        for wrapper in ["enforcedPlatform", "platform"] {
+            if let Some((inner, _)) = args.strip_prefix(wrapper).and_then(call_args) {
+                platform = true;
+                args = inner.trim();
+                break;
+            }
+        }
+
+        let with = |group: String, name: String, version: Option<String>| GradleDependency {
+            configuration: configuration.clone(),
+            group,
+            name,
+            version,
+            platform,
+            exclusions: excluded.clone(),
+            ..Default::default()
+        };
+
+        if let Some((literal, _)) = string_literal(args, self.vars) {
+            return match parse_notation(&literal) {
+                Some(dep) => vec![GradleDependency { classifier: dep.classifier, extension: dep.extension, ..with(dep.group, dep.name, dep.version) }],
+                None => {
+                    warnings.push(format!("couldn't understand dependency notation '{}'", literal));
+                    vec![]
+                }
+            };
+        }
+
+        if let Some(alias) = args.strip_prefix("libs.") {
+            let alias = alias.trim_end_matches(".get()");
+            let Some(catalog) = self.catalog else {
+                warnings.push(format!("{} refers to a version catalog, but gradle/libs.versions.toml wasn't found", args));
+                return vec![];
+            };
+
+            let aliases = match alias.strip_prefix("bundles.") {
+                Some(bundle) => catalog.bundles.get(bundle).cloned().unwrap_or_default(),
+                None => vec![alias.to_string()],
+            };
+            if aliases.is_empty() {
+                warnings.push(format!("unknown version catalog bundle {}", args));
+            }
+
+            return aliases.iter()
+                .filter_map(|alias| match catalog.libraries.get(alias) {
+                    Some((group, name, version)) => Some(with(group.clone(), name.clone(), version.clone())),
+                    None => {
+                        warnings.push(format!("unknown version catalog entry libs.{}", alias));
+                        None
+                    }
+                })
+                .collect();
+        }
+
+        if let (Some(group), Some(name)) = (named_arg(args, "group", self.vars), named_arg(args, "name", self.vars)) {
+            return vec![GradleDependency {
+                classifier: named_arg(args, "classifier", self.vars),
+                extension: named_arg(args, "ext", self.vars),
+                ..with(group, name, named_arg(args, "version", self.vars))
+            }];
+        }
+
+        warnings.push(format!("couldn't translate `{}`", statement.lines().next().unwrap_or_default()));
+        vec![]
+    }
+}
+
+// `name = "value"` style assignments and gradle.properties entries, used for $name interpolation
+fn collect_vars(script: &str, vars: &mut HashMap<String, String>) {
+    for line in script.lines() {
+        let line = line.trim();
+        let line = ["def ", "val ", "var ", "project.ext.", "ext."].iter()
+            .fold(line, |line, prefix| line.strip_prefix(prefix).unwrap_or(line));
+
+        let Some((name, value)) = line.split_once('=') else {
+            continue;
+        };
+        let name = name.trim().split(':').next().unwrap_or_default().trim();
+        if name.is_empty() || !name.chars().all(|c| c.is_alphanumeric() || c == '_' || c == '.') {
+            continue;
+        }
+        // only plain literals, not `"a" + b` or method calls on them
+        let value = value.trim();
+        if let Some((literal, len)) = string_literal(value, vars) {
+            if len == value.len() {
+                vars.insert(name.to_string(), literal);
+            }
+        }
+    }
+}
+
+fn java_version(script: &str) -> Option<String> {
+    for marker in ["JavaLanguageVersion.of(", "jvmToolchain("] {
+        if let Some(pos) = script.find(marker) {
+            let digits: String = script[pos + marker.len()..].chars().take_while(|c| c.is_ascii_digit()).collect();
+            if !digits.is_empty() {
+                return Some(digits);
+            }
+        }
+    }
+
+    // `sourceCompatibility = ...`, or Groovy's `sourceCompatibility ...` without the `=`
+    let rest = script.lines().find_map(|l| l.trim_start().strip_prefix("sourceCompatibility"))?.trim_start();
+    let value = rest.strip_prefix('=').unwrap_or(rest);
+    let value = value.trim().trim_matches(|c| c == '"' || c == '\'');
+    let value = value.strip_prefix("JavaVersion.VERSION_").map(|v| v.replace('_', ".")).unwrap_or(value.to_string());
+    // javac --release only takes the short form, 1.8 is 8
+    Some(value.strip_prefix("1.").filter(|v| !v.is_empty()).map(String::from).unwrap_or(value))
+}
+
+// `mainClass = '...'`, `mainClass.set("...")` or the older `mainClassName = '...'`
+fn main_class(script: &str, vars: &HashMap<String, String>) -> Option<String> {
+    for marker in ["mainClass.set(", "mainClassName", "mainClass"] {
+        for (pos, _) in script.match_indices(marker) {
+            let rest = script[pos + marker.len()..].trim_start();
+            let rest = rest.strip_prefix('=').unwrap_or(rest).trim_start();
+            if let Some((literal, _)) = string_literal(rest, vars) {
+                return Some(literal);
+            }
+        }
+    }
+    None
+}
+
+fn repositories(script: &str, vars: &HashMap<String, String>, warnings: &mut Vec<String>) -> Vec<(String, String)> {
+    let mut repos = Vec::new();
+
+    for block in find_blocks(script, "repositories", &["buildscript", "pluginManagement"]) {
+        for statement in statements(&block) {
+            if statement.starts_with("mavenCentral") || statement.starts_with("mavenLocal") || statement.starts_with("gradlePluginPortal") {
+                continue;
+            }
+            if statement.starts_with("google") {
+                repos.push(("google".to_string(), "https://maven.google.com/".to_string()));
+                continue;
+            }
+
+            // the first URL-looking string literal is the repository URL
+            let url = statement.match_indices(['"', '\''])
+                .filter_map(|(pos, _)| string_literal(&statement[pos..], vars))
+                .map(|(literal, _)| literal)
+                .find(|literal| literal.contains("://"));
+            let Some(url) = url else {
+                warnings.push(format!("couldn't translate repository `{}`", statement.lines().next().unwrap_or_default()));
+                continue;
+            };
+
+            let name = named_arg(&statement, "name", vars)
+                .or_else(|| statement.find("name ").and_then(|pos| string_literal(statement[pos + 5..].trim_start(), vars)).map(|(n, _)| n))
+                .unwrap_or_else(|| url.split("://").nth(1).unwrap_or(&url).split('/').next().unwrap_or_default().replace('.', "-"));
+            repos.push((name, url));
+        }
+    }
+
+    repos
+}
+
+/// The build script in `dir`, Kotlin DSL first
+pub fn build_file(dir: &Path) -> Option<PathBuf> {
+    ["build.gradle.kts", "build.gradle"].iter()
+        .map(|name| dir.join(name))
+        .find(|path| path.exists())
+}
+
+/// Reads the build script, settings, gradle.properties and version catalog in `dir`
+pub fn read_project(dir: &Path) -> anyhow::Result<GradleProject> {
+    let script_path = build_file(dir).ok_or_else(|| anyhow::anyhow!("no build.gradle or build.gradle.kts in {}", dir.display()))?;
+    let script = strip_comments(&fs::read_to_string(&script_path)?);
+
+    let mut vars = HashMap::new();
+    if let Ok(properties) = fs::read_to_string(dir.join("gradle.properties")) {
+        for line in properties.lines().filter(|l| !l.trim_start().starts_with('#')) {
+            if let Some((key, value)) = line.split_once('=') {
+                vars.insert(key.trim().to_string(), value.trim().to_string());
+            }
+        }
+    }
+    for settings in ["settings.gradle.kts", "settings.gradle"] {
+        if let Ok(content) = fs::read_to_string(dir.join(settings)) {
+            collect_vars(&strip_comments(&content), &mut vars);
+        }
+    }
+    collect_vars(&script, &mut vars);
+
+    let catalog = match fs::read_to_string(dir.join("gradle").join("libs.versions.toml")) {
+        Ok(content) => Some(VersionCatalog::parse(&content)?),
+        Err(_) => None,
+    };
+
+    Ok(parse_script(&script, &vars, catalog.as_ref(), dir))
+}
+
+fn parse_script(script: &str, vars: &HashMap<String, String>, catalog: Option<&VersionCatalog>, dir: &Path) -> GradleProject {
+    let mut project = GradleProject {
+        group: vars.get("group").cloned(),
+        name: vars.get("rootProject.name").cloned()
+            .or_else(|| dir.file_name().map(|n| n.to_string_lossy().into_owned()))
+            .unwrap_or_default(),
+        version: vars.get("version").cloned(),
+        java_version: java_version(script),
+        main_class: main_class(script, vars),
+        ..Default::default()
+    };
+
+    let context = ScriptContext { vars, catalog };
+    for block in find_blocks(script, "dependencies", &["buildscript", "subprojects", "allprojects"]) {
+        for statement in statements(&block) {
+            let deps = context.dependency(&statement, &mut project.warnings);
+            project.dependencies.extend(deps);
+        }
+    }
+    project.repositories = repositories(script, vars, &mut project.warnings);
+
+    project
+}
+
+fn scope_for(configuration: &str) -> Result<Option<&'static str>, ()> {
+    match configuration {
+        "implementation" | "api" | "compile" => Ok(None),
+        "compileOnly" | "compileOnlyApi" => Ok(Some("provided")),
+        "runtimeOnly" | "runtime" => Ok(Some("runtime")),
+        "testImplementation" | "testCompileOnly" | "testRuntimeOnly" | "testCompile" | "testRuntime" => Ok(Some("test")),
+        _ => Err(()),
+    }
+}
+
+/// Translates the project into a manifest. `managed` holds the versions the
+/// declared platforms pin, used for dependencies declared without one.
+pub fn convert(project: &GradleProject, managed: &HashMap<String, String>) -> Import {
+    let mut warnings = project.warnings.clone();
+    let mut deps = crate::manifest::Dependencies::new();
+
+    for dep in &project.dependencies {
+        let key = dep.key();
+        if dep.platform {
+            warnings.push(format!("platform {} was flattened into explicit versions", key));
+            continue;
+        }
+
+        let scope = match scope_for(&dep.configuration) {
+            Ok(scope) => scope,
+            Err(()) => {
+                warnings.push(format!("{} uses configuration '{}', which has no gallade equivalent", key, dep.configuration));
+                continue;
+            }
+        };
+
+        let Some(version) = dep.version.clone().or_else(|| managed.get(&key).cloned()) else {
+            warnings.push(format!("{} has no version and no platform pins it", key));
+            continue;
+        };
+        if version.contains("${") {
+            warnings.push(format!("{} has an unresolved version {}", key, version));
+            continue;
+        }
+
+        let spec = if scope.is_none() && dep.exclusions.is_empty() {
+            DepSpec::Simple(version)
+        } else {
+            DepSpec::Detailed {
+                version,
+                scope: scope.map(String::from),
+                optional: false,
+                exclusions: dep.exclusions.clone(),
+                classifier: None,
+                kind: None,
+            }
+        };
+        deps.insert(dep.manifest_key(), spec);
+    }
+
+    if project.group.is_none() {
+        warnings.push("no group is set, add project.group to gallade.toml before installing or publishing".to_string());
+    }
+
+    let repositories = project.repositories.iter()
+        .map(|(name, url)| (name.clone(), RepositoryConfig { url: url.clone(), username: None, password: None }))
+        .collect();
+
+    Import {
+        manifest: Manifest {
+            project: ProjectConfig {
+                group: project.group.clone(),
+                name: project.name.clone(),
+                version: project.version.clone().unwrap_or_else(|| "1.0.0".to_string()),
+                main_class: project.main_class.clone(),
+                java_version: project.java_version.clone(),
+            },
+            deps,
+            http: None,
+            repositories,
+            routing: Default::default(),
+            pom_repositories: Vec::new(),
+            overrides: HashMap::new(),
+            constraints: HashMap::new(),
+            exclusions: Vec::new(),
+        },
+        warnings,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_groovy_build() -> anyhow::Result<()> {
+        let temp = TempDir::new()?;
+        fs::write(temp.path().join("gradle.properties"), "jacksonVersion=2.15.2\n")?;
+        fs::write(temp.path().join("settings.gradle"), "rootProject.name = 'ledger'\n")?;
+        fs::write(temp.path().join("build.gradle"), r#"
+            buildscript {
+                dependencies { classpath 'com.example:plugin:1.0' }
+            }
+            plugins { id 'application' }
+
+            group = 'com.ourcorp'
+            version = '2.1.0'
+            def guavaVersion = "31.1-jre"
+            sourceCompatibility = JavaVersion.VERSION_17
+            application { mainClass = 'com.ourcorp.ledger.Main' }
+
+            repositories {
+                mavenCentral()
+                maven { url 'https://packages.confluent.io/maven/' }
+            }
+
+            dependencies {
+                implementation "com.google.guava:guava:$guavaVersion" // comment
+                implementation("com.fasterxml.jackson.core:jackson-databind:${jacksonVersion}") {
+                    exclude group: 'com.fasterxml.jackson.core', module: 'jackson-annotations'
+                }
+                compileOnly group: 'org.projectlombok', name: 'lombok', version: '1.18.30'
+                /* runtimeOnly 'org.postgresql:postgresql:42.6.0' */
+                testImplementation 'junit:junit:4.13.2'
+                implementation project(':core')
+            }
+        "#)?;
+
+        let project = read_project(temp.path())?;
+        assert_eq!(project.name, "ledger");
+        assert_eq!(project.java_version.as_deref(), Some("17"));
+        assert_eq!(project.main_class.as_deref(), Some("com.ourcorp.ledger.Main"));
+        assert_eq!(project.repositories, vec![("packages-confluent-io".to_string(), "https://packages.confluent.io/maven/".to_string())]);
+
+        let import = convert(&project, &HashMap::new());
+        let deps = &import.manifest.deps;
+        assert_eq!(deps.len(), 4);
+        assert!(matches!(&deps["com.google.guava:guava"], DepSpec::Simple(v) if v == "31.1-jre"));
+        assert!(matches!(&deps["org.projectlombok:lombok"], DepSpec::Detailed { scope: Some(s), .. } if s == "provided"));
+        match &deps["com.fasterxml.jackson.core:jackson-databind"] {
+            DepSpec::Detailed { version, exclusions, .. } => {
+                assert_eq!(version, "2.15.2");
+                assert_eq!(exclusions, &vec!["com.fasterxml.jackson.core:jackson-annotations".to_string()]);
+            }
+            other => panic!("expected a detailed spec, got {:?}", other),
+        }
+        assert!(!deps.contains_key("com.example:plugin"));
+        assert!(import.warnings.iter().any(|w| w.contains("project(':core')")));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_kotlin_build_with_catalog() -> anyhow::Result<()> {
+        let temp = TempDir::new()?;
+        fs::create_dir_all(temp.path().join("gradle"))?;
+        fs::write(temp.path().join("gradle/libs.versions.toml"), r#"
+            [versions]
+            junit = "5.10.0"
+
+            [libraries]
+            junit-bom = { module = "org.junit:junit-bom", version.ref = "junit" }
+            junit-jupiter = { module = "org.junit.jupiter:junit-jupiter" }
+            slf4j-api = "org.slf4j:slf4j-api:2.0.9"
+            logback = { group = "ch.qos.logback", name = "logback-classic", version = { strictly = "1.4.11" } }
+
+            [bundles]
+            logging = ["slf4j-api", "logback"]
+        "#)?;
+        fs::write(temp.path().join("build.gradle.kts"), r#"
+            group = "com.ourcorp"
+            java { toolchain { languageVersion.set(JavaLanguageVersion.of(21)) } }
+
+            dependencies {
+                implementation(libs.bundles.logging)
+                testImplementation(platform(libs.junit.bom))
+                testImplementation(libs.junit.jupiter)
+                annotationProcessor("org.mapstruct:mapstruct-processor:1.5.5.Final")
+            }
+        "#)?;
+
+        let project = read_project(temp.path())?;
+        assert_eq!(project.java_version.as_deref(), Some("21"));
+
+        let bom = project.dependencies.iter().find(|d| d.platform).unwrap();
+        assert_eq!((bom.key().as_str(), bom.version.as_deref()), ("org.junit:junit-bom", Some("5.10.0")));
+
+        let managed = HashMap::from([("org.junit.jupiter:junit-jupiter".to_string(), "5.10.0".to_string())]);
+        let import = convert(&project, &managed);
+        let deps = &import.manifest.deps;
+        assert!(matches!(&deps["org.slf4j:slf4j-api"], DepSpec::Simple(v) if v == "2.0.9"));
+        assert!(matches!(&deps["ch.qos.logback:logback-classic"], DepSpec::Simple(v) if v == "1.4.11"));
+        assert!(matches!(&deps["org.junit.jupiter:junit-jupiter"], DepSpec::Detailed { version, .. } if version == "5.10.0"));
+        assert!(import.warnings.iter().any(|w| w.contains("annotationProcessor")));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_strip_comments_keeps_urls() {
+        let script = "url 'https://repo.example.com/maven' // mirror\n/* a\nb */x";
+        assert_eq!(strip_comments(script), "url 'https://repo.example.com/maven' \nx");
+    }
+
+    #[test]
+    fn test_java_version_short_form() {
+        assert_eq!(java_version("sourceCompatibility = JavaVersion.VERSION_1_8").as_deref(), Some("8"));
+        assert_eq!(java_version("sourceCompatibility = '1.8'").as_deref(), Some("8"));
+        assert_eq!(java_version("sourceCompatibility = JavaVersion.VERSION_11").as_deref(), Some("11"));
+        assert_eq!(java_version("java {\n    sourceCompatibility JavaVersion.VERSION_17\n}").as_deref(), Some("17"));
+    }
+}
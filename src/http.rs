@@ -0,0 +1,592 @@
+use std::fs::{self, File, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+use anyhow::Context;
+use rand::Rng;
+use reqwest::header::{CONTENT_RANGE, ETAG, IF_RANGE, LAST_MODIFIED, RANGE, RETRY_AFTER};
+use reqwest::{Certificate, Client, Identity, NoProxy, Proxy, RequestBuilder, Response, StatusCode};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+const DEFAULT_CONNECT_TIMEOUT: u64 = 10;
+const DEFAULT_READ_TIMEOUT: u64 = 30;
+const DEFAULT_RETRIES: u32 = 4;
+
+/// `[http]` section of gallade.toml. Every repository talks through a client
+/// built from this, so proxy and TLS settings only have to be declared once.
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub struct HttpConfig {
+    pub proxy: Option<ProxyConfig>,
+    /// Extra PEM bundles trusted on top of the system roots
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub ca_certs: Vec<PathBuf>,
+    /// Client certificate for repositories that require mTLS
+    pub client_cert: Option<ClientCertConfig>,
+    /// Seconds allowed to establish a connection
+    pub connect_timeout: Option<u64>,
+    /// Seconds allowed between reads before the request is abandoned
+    pub read_timeout: Option<u64>,
+    /// How many times a transient failure is retried before giving up
+    pub retries: Option<u32>,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct ProxyConfig {
+    pub url: String,
+    pub username: Option<String>,
+    pub password: Option<String>,
+    /// Comma separated hosts that bypass the proxy, same syntax as NO_PROXY
+    pub no_proxy: Option<String>,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct ClientCertConfig {
+    /// PEM encoded certificate chain
+    pub cert: PathBuf,
+    /// PEM encoded PKCS#8 private key
+    pub key: PathBuf,
+}
+
+impl ProxyConfig {
+    // HTTPS_PROXY wins over HTTP_PROXY since every repository we talk to is https
+    fn from_env_with(var: impl Fn(&str) -> Option<String>) -> Option<Self> {
+        let lookup = |name: &str| var(name).or_else(|| var(&name.to_lowercase()))
+            .filter(|v| !v.trim().is_empty());
+
+        let url = lookup("HTTPS_PROXY").or_else(|| lookup("HTTP_PROXY"))?;
+
+        Some(Self {
+            url,
+            username: None,
+            password: None,
+            no_proxy: lookup("NO_PROXY"),
+        })
+    }
+
+    pub fn from_env() -> Option<Self> {
+        Self::from_env_with(|name| std::env::var(name).ok())
+    }
+
+    fn to_proxy(&self) -> anyhow::Result<Proxy> {
+        let mut proxy = Proxy::all(&self.url)
+            .with_context(|| format!("invalid proxy url: {}", self.url))?;
+
+        if let Some(username) = &self.username {
+            proxy = proxy.basic_auth(username, self.password.as_deref().unwrap_or(""));
+        }
+
+        // an explicit proxy without its own list still honors NO_PROXY
+        let no_proxy = self.no_proxy.as_deref().map_or_else(NoProxy::from_env, NoProxy::from_string);
+        proxy = proxy.no_proxy(no_proxy);
+
+        Ok(proxy)
+    }
+}
+
+impl HttpConfig {
+    /// The explicit `[http.proxy]` table if present, otherwise whatever the
+    /// standard proxy environment variables say
+    pub fn effective_proxy(&self) -> Option<ProxyConfig> {
+        self.proxy.clone().or_else(ProxyConfig::from_env)
+    }
+
+    pub fn build_client(&self) -> anyhow::Result<HttpClient> {
+        let mut builder = Client::builder()
+            .user_agent(concat!("gallade/", env!("CARGO_PKG_VERSION")))
+            .connect_timeout(Duration::from_secs(self.connect_timeout.unwrap_or(DEFAULT_CONNECT_TIMEOUT)))
+            .read_timeout(Duration::from_secs(self.read_timeout.unwrap_or(DEFAULT_READ_TIMEOUT)));
+
+        builder = match self.effective_proxy() {
+            Some(proxy) => builder.proxy(proxy.to_proxy()?),
+            None => builder.no_proxy(),
+        };
+
+        for path in &self.ca_certs {
+            let pem = fs::read(path)
+                .with_context(|| format!("failed to read CA bundle {}", path.display()))?;
+            let certs = Certificate::from_pem_bundle(&pem)
+                .with_context(|| format!("invalid CA bundle {}", path.display()))?;
+            for cert in certs {
+                builder = builder.add_root_certificate(cert);
+            }
+        }
+
+        if let Some(client_cert) = &self.client_cert {
+            let cert = fs::read(&client_cert.cert)
+                .with_context(|| format!("failed to read client certificate {}", client_cert.cert.display()))?;
+            let key = fs::read(&client_cert.key)
+                .with_context(|| format!("failed to read client key {}", client_cert.key.display()))?;
+            let identity = Identity::from_pkcs8_pem(&cert, &key)
+                .context("invalid client certificate or key")?;
+            builder = builder.identity(identity);
+        }
+
+        Ok(HttpClient {
+            client: builder.build()?,
+            retry: RetryPolicy {
+                max_retries: self.retries.unwrap_or(DEFAULT_RETRIES),
+                ..Default::default()
+            },
+            credentials: None,
+        })
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct RetryPolicy {
+    pub max_retries: u32,
+    pub base_delay: Duration,
+    pub max_delay: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_retries: DEFAULT_RETRIES,
+            base_delay: Duration::from_millis(500),
+            max_delay: Duration::from_secs(30),
+        }
+    }
+}
+
+impl RetryPolicy {
+    // full jitter: anywhere between zero and the exponential ceiling, so a
+    // burst of parallel failures doesn't come back in lockstep
+    pub fn backoff(&self, attempt: u32) -> Duration {
+        let ceiling = self.base_delay
+            .saturating_mul(2u32.saturating_pow(attempt))
+            .min(self.max_delay);
+        ceiling.mul_f64(rand::thread_rng().gen::<f64>())
+    }
+}
+
+fn is_transient_status(status: StatusCode) -> bool {
+    status == StatusCode::TOO_MANY_REQUESTS
+        || status == StatusCode::REQUEST_TIMEOUT
+        || status.is_server_error()
+}
+
+fn is_transient_error(err: &reqwest::Error) -> bool {
+    err.is_timeout() || err.is_connect() || err.is_request() || err.is_body()
+}
+
+fn retry_after(response: &Response) -> Option<Duration> {
+    let value = response.headers().get(RETRY_AFTER)?.to_str().ok()?.trim();
+
+    if let Ok(seconds) = value.parse::<u64>() {
+        return Some(Duration::from_secs(seconds));
+    }
+
+    httpdate::parse_http_date(value)
+        .ok()
+        .map(|at| at.duration_since(SystemTime::now()).unwrap_or_default())
+}
+
+// One per origin, so bytes from two repositories never end up spliced into one file
+fn partial_path(dest: &Path, url: &str) -> PathBuf {
+    let origin = hex::encode(Sha256::digest(url.as_bytes()));
+    let mut name = dest.file_name().unwrap_or_default().to_os_string();
+    name.push(format!(".{}.part", &origin[..12]));
+    dest.with_file_name(name)
+}
+
+// What the server said identifies the version `partial` holds bytes of, sent as
+// If-Range so a file that changed since comes back whole instead of spliced
+fn validator_path(partial: &Path) -> PathBuf {
+    let mut name = partial.file_name().unwrap_or_default().to_os_string();
+    name.push(".validator");
+    partial.with_file_name(name)
+}
+
+// A strong ETag, or else Last-Modified, weak ETags can't be used with If-Range
+fn validator(response: &Response) -> Option<String> {
+    let header = |name| response.headers().get(name).and_then(|v| v.to_str().ok());
+    header(ETAG).filter(|etag| !etag.starts_with("W/"))
+        .or_else(|| header(LAST_MODIFIED))
+        .map(String::from)
+}
+
+// Where the bytes of a 206 start, from `Content-Range: bytes 5-9/10`
+fn content_range_start(response: &Response) -> Option<u64> {
+    let range = response.headers().get(CONTENT_RANGE)?.to_str().ok()?;
+    range.strip_prefix("bytes ")?.split('-').next()?.trim().parse().ok()
+}
+
+fn remove_if_exists(path: &Path) -> std::io::Result<()> {
+    match fs::remove_file(path) {
+        Err(err) if err.kind() != std::io::ErrorKind::NotFound => Err(err),
+        _ => Ok(()),
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Credentials {
+    pub username: String,
+    pub password: Option<String>,
+}
+
+/// reqwest client plus the retry policy every repository request goes through
+#[derive(Clone, Debug)]
+pub struct HttpClient {
+    client: Client,
+    retry: RetryPolicy,
+    credentials: Option<Credentials>,
+}
+
+impl HttpClient {
+    /// Same connection pool, but every request carries basic auth for one repository
+    pub fn with_credentials(&self, credentials: Option<Credentials>) -> Self {
+        Self {
+            credentials,
+            ..self.clone()
+        }
+    }
+
+    fn authorize(&self, request: RequestBuilder) -> RequestBuilder {
+        match &self.credentials {
+            Some(creds) => request.basic_auth(&creds.username, creds.password.as_ref()),
+            None => request,
+        }
+    }
+
+    fn request(&self, url: &str) -> RequestBuilder {
+        self.authorize(self.client.get(url))
+    }
+
+    pub async fn get(&self, url: &str) -> anyhow::Result<Response> {
+        self.send(|| self.request(url)).await
+    }
+
+    /// Uploads `body` to `url`, failing on anything but a 2xx
+    pub async fn put(&self, url: &str, body: Vec<u8>) -> anyhow::Result<()> {
+        let response = self.send(|| self.authorize(self.client.put(url)).body(body.clone())).await?;
+        if !response.status().is_success() {
+            anyhow::bail!("upload to {} failed: HTTP {}", url, response.status());
+        }
+        Ok(())
+    }
+
+    /// Sends the request built by `request`, rebuilding and resending it after
+    /// connection failures and 408/429/5xx responses. The final response is
+    /// returned as-is, so callers still decide what a 404 means.
+    pub async fn send(&self, request: impl Fn() -> RequestBuilder) -> anyhow::Result<Response> {
+        let mut attempt = 0;
+
+        loop {
+            let result = request().send().await;
+            let retries_left = attempt < self.retry.max_retries;
+
+            let delay = match result {
+                Ok(response) if retries_left && is_transient_status(response.status()) => {
+                    tracing::debug!("{} returned {}, retrying", response.url(), response.status());
+                    retry_after(&response)
+                        .map(|delay| delay.min(self.retry.max_delay))
+                        .unwrap_or_else(|| self.retry.backoff(attempt))
+                }
+                Ok(response) => return Ok(response),
+                Err(err) if retries_left && is_transient_error(&err) => {
+                    tracing::debug!("request failed, retrying: {}", err);
+                    self.retry.backoff(attempt)
+                }
+                Err(err) => {
+                    return Err(anyhow::Error::new(err)
+                        .context(format!("request failed after {} attempts", attempt + 1)));
+                }
+            };
+
+            tokio::time::sleep(delay).await;
+            attempt += 1;
+        }
+    }
+
+    /// Streams `url` into `dest`. Bytes land in `<dest>.part` first, and an
+    /// interrupted transfer picks up from there with a Range request instead
+    /// of starting over, both on retry and on the next run. It only resumes
+    /// when the server can vouch the file is still the one the bytes came from.
+    pub async fn download_to(&self, url: &str, dest: &Path) -> anyhow::Result<()> {
+        if let Some(parent) = dest.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        let partial = partial_path(dest, url);
+        let validator_file = validator_path(&partial);
+        let mut attempt = 0;
+
+        loop {
+            let mut offset = fs::metadata(&partial).map(|m| m.len()).unwrap_or(0);
+            // bytes nothing can vouch for are no better than none
+            let if_range = fs::read_to_string(&validator_file).ok();
+            if offset > 0 && if_range.is_none() {
+                fs::remove_file(&partial)?;
+                offset = 0;
+            }
+
+            let response = self.send(|| {
+                let request = self.request(url);
+                match &if_range {
+                    Some(if_range) if offset > 0 => request
+                        .header(RANGE, format!("bytes={}-", offset))
+                        .header(IF_RANGE, if_range.as_str()),
+                    _ => request,
+                }
+            }).await?;
+
+            let mut file = match response.status() {
+                StatusCode::PARTIAL_CONTENT if content_range_start(&response) == Some(offset) => {
+                    OpenOptions::new().create(true).append(true).open(&partial)?
+                }
+                StatusCode::PARTIAL_CONTENT if offset == 0 => {
+                    anyhow::bail!("{} sent a partial response to a request for the whole file", url);
+                }
+                // whatever we had on disk no longer lines up with the remote file
+                StatusCode::PARTIAL_CONTENT | StatusCode::RANGE_NOT_SATISFIABLE => {
+                    remove_if_exists(&validator_file)?;
+                    fs::remove_file(&partial)?;
+                    continue;
+                }
+                status if status.is_success() => {
+                    match validator(&response) {
+                        Some(validator) => fs::write(&validator_file, validator)?,
+                        None => remove_if_exists(&validator_file)?,
+                    }
+                    File::create(&partial)?
+                }
+                status => anyhow::bail!("HTTP {}", status),
+            };
+
+            match Self::stream_into(response, &mut file).await {
+                Ok(()) => break,
+                Err(err) if attempt < self.retry.max_retries => {
+                    tracing::debug!("download of {} interrupted, resuming: {}", url, err);
+                    tokio::time::sleep(self.retry.backoff(attempt)).await;
+                    attempt += 1;
+                }
+                Err(err) => {
+                    return Err(err.context(format!("download interrupted after {} attempts", attempt + 1)));
+                }
+            }
+        }
+
+        fs::rename(&partial, dest)?;
+        remove_if_exists(&validator_file)?;
+        Ok(())
+    }
+
+    async fn stream_into(mut response: Response, file: &mut File) -> anyhow::Result<()> {
+        while let Some(chunk) = response.chunk().await? {
+            file.write_all(&chunk)?;
+        }
+        file.flush()?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    #[test]
+    fn test_proxy_from_env() {
+        let env: HashMap<&str, &str> = HashMap::from([
+            ("http_proxy", "http://plain.proxy:3128"),
+            ("HTTPS_PROXY", "http://corp.proxy:8080"),
+            ("no_proxy", "localhost,.ourcorp.com"),
+        ]);
+
+        let proxy = ProxyConfig::from_env_with(|name| env.get(name).map(|v| v.to_string())).unwrap();
+        assert_eq!(proxy.url, "http://corp.proxy:8080");
+        assert_eq!(proxy.no_proxy.as_deref(), Some("localhost,.ourcorp.com"));
+
+        assert!(ProxyConfig::from_env_with(|_| None).is_none());
+    }
+
+    #[test]
+    fn test_build_client_from_config() -> anyhow::Result<()> {
+        let config: HttpConfig = toml::from_str(r#"
+            connect_timeout = 5
+            read_timeout = 120
+
+            [proxy]
+            url = "http://proxy.internal:3128"
+            username = "build"
+            password = "secret"
+            no_proxy = "nexus.internal"
+        "#)?;
+
+        assert_eq!(config.read_timeout, Some(120));
+        config.build_client()?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_backoff_stays_under_ceiling() {
+        let policy = RetryPolicy::default();
+        for attempt in 0..10 {
+            let ceiling = (policy.base_delay * 2u32.pow(attempt)).min(policy.max_delay);
+            assert!(policy.backoff(attempt) <= ceiling);
+        }
+    }
+
+    // Answers each incoming connection with the next canned response and
+    // hands back the request heads it saw
+    async fn serve(responses: Vec<Vec<u8>>) -> (String, tokio::task::JoinHandle<Vec<String>>) {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/guava.jar", listener.local_addr().unwrap());
+
+        let handle = tokio::spawn(async move {
+            let mut requests = Vec::new();
+            for response in responses {
+                let (mut socket, _) = listener.accept().await.unwrap();
+                let mut buf = vec![0u8; 4096];
+                let n = socket.read(&mut buf).await.unwrap();
+                requests.push(String::from_utf8_lossy(&buf[..n]).to_lowercase());
+                socket.write_all(&response).await.unwrap();
+                socket.shutdown().await.unwrap();
+            }
+            requests
+        });
+
+        (url, handle)
+    }
+
+    fn test_client() -> HttpClient {
+        HttpClient {
+            client: Client::builder().no_proxy().build().unwrap(),
+            retry: RetryPolicy {
+                max_retries: 3,
+                base_delay: Duration::from_millis(1),
+                max_delay: Duration::from_millis(5),
+            },
+            credentials: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_retries_transient_status() -> anyhow::Result<()> {
+        let (url, server) = serve(vec![
+            b"HTTP/1.1 503 Service Unavailable\r\nRetry-After: 0\r\nContent-Length: 0\r\n\r\n".to_vec(),
+            b"HTTP/1.1 429 Too Many Requests\r\nContent-Length: 0\r\n\r\n".to_vec(),
+            b"HTTP/1.1 200 OK\r\nContent-Length: 2\r\n\r\nok".to_vec(),
+        ]).await;
+
+        let response = test_client().get(&url).await?;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.text().await?, "ok");
+        assert_eq!(server.await?.len(), 3);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_download_resumes_with_range() -> anyhow::Result<()> {
+        // the first response promises ten bytes but the connection drops after five
+        let (url, server) = serve(vec![
+            b"HTTP/1.1 200 OK\r\nETag: \"v1\"\r\nContent-Length: 10\r\n\r\n01234".to_vec(),
+            b"HTTP/1.1 206 Partial Content\r\nContent-Length: 5\r\nContent-Range: bytes 5-9/10\r\n\r\n56789".to_vec(),
+        ]).await;
+
+        let temp = tempfile::TempDir::new()?;
+        let dest = temp.path().join("nested").join("guava.jar");
+        test_client().download_to(&url, &dest).await?;
+
+        assert_eq!(fs::read_to_string(&dest)?, "0123456789");
+        assert!(!partial_path(&dest, &url).exists());
+
+        let requests = server.await?;
+        assert!(!requests[0].contains("range:"));
+        assert!(requests[1].contains("range: bytes=5-"));
+        assert!(requests[1].contains("if-range: \"v1\""));
+        assert!(!validator_path(&partial_path(&dest, &url)).exists());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_download_restarts_on_mismatched_range() -> anyhow::Result<()> {
+        // the server ignores where we asked to start, so the file starts over
+        let (url, server) = serve(vec![
+            b"HTTP/1.1 206 Partial Content\r\nContent-Length: 7\r\nContent-Range: bytes 3-9/10\r\n\r\n3456789".to_vec(),
+            b"HTTP/1.1 200 OK\r\nContent-Length: 10\r\n\r\n0123456789".to_vec(),
+        ]).await;
+
+        let temp = tempfile::TempDir::new()?;
+        let dest = temp.path().join("guava.jar");
+        let partial = partial_path(&dest, &url);
+        fs::write(&partial, "abcde")?;
+        fs::write(validator_path(&partial), "\"v1\"")?;
+        test_client().download_to(&url, &dest).await?;
+
+        assert_eq!(fs::read_to_string(&dest)?, "0123456789");
+        let requests = server.await?;
+        assert!(requests[0].contains("range: bytes=5-"));
+        assert!(!requests[1].contains("range:"));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_download_does_not_resume_unvalidated_partial() -> anyhow::Result<()> {
+        let (url, server) = serve(vec![
+            b"HTTP/1.1 200 OK\r\nContent-Length: 10\r\n\r\n0123456789".to_vec(),
+        ]).await;
+
+        // left behind by a server that sent neither an ETag nor Last-Modified
+        let temp = tempfile::TempDir::new()?;
+        let dest = temp.path().join("guava.jar");
+        fs::write(partial_path(&dest, &url), "abcde")?;
+        test_client().download_to(&url, &dest).await?;
+
+        assert_eq!(fs::read_to_string(&dest)?, "0123456789");
+        assert!(!server.await?[0].contains("range:"));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_download_ignores_partial_from_another_repository() -> anyhow::Result<()> {
+        let (url, server) = serve(vec![
+            b"HTTP/1.1 200 OK\r\nContent-Length: 10\r\n\r\n0123456789".to_vec(),
+        ]).await;
+
+        let temp = tempfile::TempDir::new()?;
+        let dest = temp.path().join("guava.jar");
+        fs::write(partial_path(&dest, "https://mirror.internal/guava.jar"), "abcde")?;
+        test_client().download_to(&url, &dest).await?;
+
+        assert_eq!(fs::read_to_string(&dest)?, "0123456789");
+        assert!(!server.await?[0].contains("range:"));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_download_does_not_retry_not_found() {
+        let (url, server) = serve(vec![
+            b"HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\n\r\n".to_vec(),
+        ]).await;
+
+        let temp = tempfile::TempDir::new().unwrap();
+        let dest = temp.path().join("guava.jar");
+        let err = test_client().download_to(&url, &dest).await.unwrap_err();
+
+        assert!(err.to_string().contains("404"));
+        assert!(!dest.exists());
+        assert_eq!(server.await.unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_missing_ca_bundle() {
+        let config = HttpConfig {
+            ca_certs: vec![PathBuf::from("/does/not/exist.pem")],
+            ..Default::default()
+        };
+
+        let err = config.build_client().unwrap_err();
+        assert!(err.to_string().contains("/does/not/exist.pem"));
+    }
+}
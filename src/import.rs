@@ -0,0 +1,289 @@
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::Path;
+
+use crate::coordinates::Coordinate;
+use crate::effective::{declared_parent, EffectivePom, ModelBuilder, ModelError};
+use crate::manifest::{DepSpec, Manifest, ProjectConfig, RepositoryConfig};
+use crate::platform::CLASSIFIER_VARIABLE;
+use crate::xml::Element;
+
+// plugins whose configuration we carry over, or that gallade does the job of anyway
+const KNOWN_PLUGINS: &[&str] = &[
+    "maven-compiler-plugin",
+    "maven-jar-plugin",
+    "maven-resources-plugin",
+    "maven-surefire-plugin",
+    "maven-install-plugin",
+    "maven-deploy-plugin",
+    "maven-source-plugin",
+    "maven-javadoc-plugin",
+    "exec-maven-plugin",
+];
+
+/// Adds every parent that can be found on disk through `<relativePath>`,
+/// the way Maven does for a multi-module checkout
+pub fn add_local_parents(builder: &mut ModelBuilder, pom_path: &Path) -> anyhow::Result<()> {
+    let mut path = pom_path.to_path_buf();
+    let mut seen = HashSet::new();
+
+    loop {
+        let xml = fs::read_to_string(&path)?;
+        let Some((parent, Some(relative))) = declared_parent(&xml)? else {
+            return Ok(());
+        };
+
+        let mut candidate = path.parent().unwrap_or(Path::new(".")).join(relative);
+        if candidate.is_dir() {
+            candidate = candidate.join("pom.xml");
+        }
+        if !candidate.exists() || !seen.insert(candidate.clone()) {
+            return Ok(());
+        }
+
+        // relativePath only counts when it points at the parent that was declared
+        let parent_xml = fs::read_to_string(&candidate)?;
+        let root = Element::parse(&parent_xml)?;
+        if root.text_at("artifactId").as_deref() != Some(parent.name.as_str()) {
+            return Ok(());
+        }
+
+        builder.add(&parent, parent_xml);
+        path = candidate;
+    }
+}
+
+/// Effective model of a pom.xml on disk, when every parent and BOM it needs is local
+pub fn local_effective_pom(pom_path: &Path) -> anyhow::Result<EffectivePom> {
+    let mut builder = ModelBuilder::new();
+    add_local_parents(&mut builder, pom_path)?;
+
+    match builder.build(&fs::read_to_string(pom_path)?) {
+        Ok(pom) => Ok(pom),
+        Err(ModelError::Missing(coord)) => anyhow::bail!(
+            "{} needs {}, which isn't available locally - run gallade import to fetch it",
+            pom_path.display(),
+            coord
+        ),
+        Err(ModelError::Invalid(err)) => Err(err),
+    }
+}
+
+/// gallade.toml equivalent of a Maven project, plus everything that couldn't be carried over
+pub struct Import {
+    pub manifest: Manifest,
+    pub warnings: Vec<String>,
+}
+
+/// Translates the effective POM into a manifest. `raw` is the pom.xml as
+/// written, used to spot things the effective model doesn't cover.
+pub fn convert(pom: &EffectivePom, raw: &str) -> anyhow::Result<Import> {
+    let mut warnings = Vec::new();
+    let root = Element::parse(raw)?;
+
+    if pom.packaging != "jar" {
+        warnings.push(format!("packaging '{}' isn't supported, the project will be built as a jar", pom.packaging));
+    }
+    if root.child("modules").is_some() {
+        warnings.push("<modules> isn't supported, import each module separately".to_string());
+    }
+    if root.child("profiles").is_some() {
+        warnings.push("<profiles> were ignored".to_string());
+    }
+    if let Some(dir) = &pom.source_directory {
+        if dir != "src/main/java" {
+            warnings.push(format!("custom sourceDirectory '{}' isn't supported, gallade uses src/main/java", dir));
+        }
+    }
+    if pom.resource_directories.iter().any(|dir| dir != "src/main/resources") {
+        warnings.push("custom resource directories aren't supported, gallade uses src/main/resources".to_string());
+    }
+
+    let plugins = root.find("build/plugins")
+        .map(|plugins| plugins.children("plugin").filter_map(|p| p.text_at("artifactId")).collect::<Vec<_>>())
+        .unwrap_or_default();
+    for plugin in plugins {
+        if !KNOWN_PLUGINS.contains(&plugin.as_str()) {
+            warnings.push(format!("plugin {} has no gallade equivalent and was dropped", plugin));
+        }
+    }
+
+    let mut deps = crate::manifest::Dependencies::new();
+    for dep in &pom.dependencies {
+        // the key spells out the extension and classifier, if any, native ones as the variable
+        // so the manifest works on every platform
+        let mut coord = Coordinate { version: None, ..dep.coordinate() };
+        if dep.native {
+            coord.classifier = Some(CLASSIFIER_VARIABLE.to_string());
+        }
+        let key = coord.to_string();
+
+        let Some(version) = dep.version.clone() else {
+            warnings.push(format!("{} has no version, even after applying dependencyManagement", key));
+            continue;
+        };
+        if version.contains("${") {
+            warnings.push(format!("{} has an unresolved version {}", key, version));
+            continue;
+        }
+        if dep.scope.as_deref() == Some("system") {
+            warnings.push(format!("{} is system-scoped and was skipped", key));
+            continue;
+        }
+
+        let scope = dep.scope.clone().filter(|scope| scope != "compile");
+        let spec = if scope.is_none() && !dep.optional && dep.exclusions.is_empty() {
+            DepSpec::Simple(version)
+        } else {
+            DepSpec::Detailed {
+                version,
+                scope,
+                optional: dep.optional,
+                exclusions: dep.exclusions.iter().map(|ex| ex.to_string()).collect(),
+                classifier: None,
+                kind: None,
+            }
+        };
+        deps.insert(key, spec);
+    }
+
+    let mut repositories = crate::manifest::Repositories::new();
+    for repo in &pom.repositories {
+        repositories.insert(repo.id.clone(), RepositoryConfig {
+            url: repo.url.clone(),
+            username: None,
+            password: None,
+        });
+    }
+
+    let coord = &pom.coordinate;
+    Ok(Import {
+        manifest: Manifest {
+            project: ProjectConfig {
+                group: Some(coord.namespace.clone()),
+                name: coord.name.clone(),
+                version: coord.version.clone().unwrap_or_default(),
+                main_class: pom.main_class.clone(),
+                java_version: pom.compiler_release.clone(),
+            },
+            deps,
+            http: None,
+            repositories,
+            routing: Default::default(),
+            pom_repositories: Vec::new(),
+            overrides: HashMap::new(),
+            constraints: HashMap::new(),
+            exclusions: Vec::new(),
+        },
+        warnings,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_import_with_local_parent() -> anyhow::Result<()> {
+        let temp = TempDir::new()?;
+        fs::write(temp.path().join("pom.xml"), r#"
+            <project>
+                <groupId>com.ourcorp</groupId>
+                <artifactId>platform</artifactId>
+                <version>3.0.0</version>
+                <packaging>pom</packaging>
+                <modules><module>ledger</module></modules>
+                <properties><maven.compiler.release>21</maven.compiler.release></properties>
+                <dependencyManagement><dependencies>
+                    <dependency>
+                        <groupId>org.slf4j</groupId>
+                        <artifactId>slf4j-api</artifactId>
+                        <version>2.0.9</version>
+                    </dependency>
+                </dependencies></dependencyManagement>
+            </project>
+        "#)?;
+
+        let module = temp.path().join("ledger");
+        fs::create_dir_all(&module)?;
+        let raw = r#"
+            <project>
+                <parent>
+                    <groupId>com.ourcorp</groupId>
+                    <artifactId>platform</artifactId>
+                    <version>3.0.0</version>
+                </parent>
+                <artifactId>ledger</artifactId>
+                <repositories>
+                    <repository><id>confluent</id><url>https://packages.confluent.io/maven/</url></repository>
+                </repositories>
+                <dependencies>
+                    <dependency>
+                        <groupId>org.slf4j</groupId>
+                        <artifactId>slf4j-api</artifactId>
+                    </dependency>
+                    <dependency>
+                        <groupId>junit</groupId>
+                        <artifactId>junit</artifactId>
+                        <version>4.13.2</version>
+                        <scope>test</scope>
+                        <exclusions>
+                            <exclusion><groupId>org.hamcrest</groupId><artifactId>hamcrest-core</artifactId></exclusion>
+                        </exclusions>
+                    </dependency>
+                </dependencies>
+                <build><plugins>
+                    <plugin>
+                        <groupId>org.codehaus.mojo</groupId>
+                        <artifactId>exec-maven-plugin</artifactId>
+                        <configuration><mainClass>com.ourcorp.ledger.Main</mainClass></configuration>
+                    </plugin>
+                    <plugin><artifactId>maven-shade-plugin</artifactId></plugin>
+                </plugins></build>
+            </project>
+        "#;
+        fs::write(module.join("pom.xml"), raw)?;
+
+        let pom = local_effective_pom(&module.join("pom.xml"))?;
+        let import = convert(&pom, raw)?;
+        let manifest = import.manifest;
+
+        assert_eq!(manifest.project.group.as_deref(), Some("com.ourcorp"));
+        assert_eq!(manifest.project.version, "3.0.0");
+        assert_eq!(manifest.project.java_version.as_deref(), Some("21"));
+        assert_eq!(manifest.project.main_class.as_deref(), Some("com.ourcorp.ledger.Main"));
+        assert!(matches!(&manifest.deps["org.slf4j:slf4j-api"], DepSpec::Simple(v) if v == "2.0.9"));
+        match &manifest.deps["junit:junit"] {
+            DepSpec::Detailed { scope, exclusions, .. } => {
+                assert_eq!(scope.as_deref(), Some("test"));
+                assert_eq!(exclusions, &vec!["org.hamcrest:hamcrest-core".to_string()]);
+            }
+            other => panic!("expected a detailed spec, got {:?}", other),
+        }
+        assert_eq!(manifest.repositories["confluent"].url, "https://packages.confluent.io/maven/");
+
+        assert_eq!(import.warnings, vec!["plugin maven-shade-plugin has no gallade equivalent and was dropped"]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_missing_remote_parent() {
+        let temp = TempDir::new().unwrap();
+        let pom = temp.path().join("pom.xml");
+        fs::write(&pom, r#"
+            <project>
+                <parent>
+                    <groupId>org.springframework.boot</groupId>
+                    <artifactId>spring-boot-starter-parent</artifactId>
+                    <version>3.2.0</version>
+                </parent>
+                <artifactId>app</artifactId>
+            </project>
+        "#).unwrap();
+
+        let err = local_effective_pom(&pom).unwrap_err().to_string();
+        assert!(err.contains("org.springframework.boot:spring-boot-starter-parent:3.2.0"));
+    }
+}
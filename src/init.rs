@@ -1,5 +1,5 @@
-use std::{env, fs};
-use std::path::{Path, PathBuf};
+use std::fs;
+use std::path::Path;
 use std::process::Command;
 use rust_embed::RustEmbed;
 use tera::{Context, Tera};
@@ -63,12 +63,20 @@ impl ProjectInitializer {
 
         let manifest = Manifest {
             project: ProjectConfig {
+                group: Some(self.group_id.clone()),
                 name: self.artifact_id.clone(),
-                version: "0.1.0".to_string(),
+                version: self.version.clone(),
                 main_class: Some(main_class.clone()),
                 java_version: self.java_version.clone(),
             },
             deps: Default::default(),
+            http: None,
+            repositories: Default::default(),
+            routing: Default::default(),
+            pom_repositories: Vec::new(),
+            overrides: Default::default(),
+            constraints: Default::default(),
+            exclusions: Vec::new(),
         };
 
         manifest.save(&artifact_path.join("gallade.toml"))?;
@@ -0,0 +1,72 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::coordinates::Coordinate;
+use crate::pom::update_metadata;
+
+/// Installs artifacts into a Maven local repository, the same way `mvn install`
+/// would, so Maven and Gradle builds on this machine can depend on them
+pub struct Installer {
+    root: PathBuf,
+}
+
+impl Installer {
+    pub fn new(root: PathBuf) -> Self {
+        Self { root }
+    }
+
+    /// Copies the jar and POM into place and records the version in
+    /// maven-metadata-local.xml, returning the version directory
+    pub fn install(&self, coord: &Coordinate, version: &str, jar: &Path, pom: &str) -> anyhow::Result<PathBuf> {
+        let artifact_dir = self.root.join(coord.to_path());
+        let dir = artifact_dir.join(version);
+        fs::create_dir_all(&dir)?;
+
+        let jar_name = format!("{}-{}.jar", coord.name, version);
+        let pom_name = format!("{}-{}.pom", coord.name, version);
+        fs::copy(jar, dir.join(&jar_name))?;
+        fs::write(dir.join(&pom_name), pom)?;
+
+        // an empty repository id is how Maven marks files that were installed locally
+        fs::write(
+            dir.join("_remote.repositories"),
+            format!("#NOTE: This is a Maven Resolver internal implementation file, its format can be changed without prior notice.\n{}>=\n{}>=\n", jar_name, pom_name),
+        )?;
+
+        let metadata_path = artifact_dir.join("maven-metadata-local.xml");
+        let existing = fs::read_to_string(&metadata_path).ok();
+        fs::write(&metadata_path, update_metadata(existing.as_deref(), coord, version)?)?;
+
+        Ok(dir)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::download::{parse_metadata_versions, ArtifactRepository};
+    use crate::m2::{LocalMavenRepository, M2};
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_install_is_readable_by_local_maven() -> anyhow::Result<()> {
+        let temp = TempDir::new()?;
+        let m2 = temp.path().join("repository");
+        let jar = temp.path().join("ledger.jar");
+        fs::write(&jar, "jar")?;
+
+        let coord = Coordinate::parse("com.ourcorp:ledger")?;
+        let installer = Installer::new(m2.clone());
+        installer.install(&coord, "1.0.0", &jar, "<project/>")?;
+        let dir = installer.install(&coord, "1.1.0", &jar, "<project/>")?;
+
+        assert!(dir.join("ledger-1.1.0.jar").exists());
+        let metadata = fs::read_to_string(m2.join("com/ourcorp/ledger/maven-metadata-local.xml"))?;
+        assert_eq!(parse_metadata_versions(&metadata)?, vec!["1.1.0", "1.0.0"]);
+
+        let local = LocalMavenRepository::new(m2, vec![]);
+        assert_eq!(local.origin(&coord, "1.1.0", "ledger-1.1.0.jar").as_deref(), Some(M2));
+
+        Ok(())
+    }
+}
@@ -3,23 +3,52 @@ use std::fs;
 use std::io::Write;
 use std::path::Path;
 use serde::{Deserialize, Serialize};
-use sha2::{Sha256, Digest};
 use tempfile::NamedTempFile;
 use crate::coordinates::Coordinate;
-use crate::download::RepositoryManager;
+use crate::download::CENTRAL;
+use crate::platform::Platform;
+use crate::repository::Repository;
 use crate::resolver::DependencyGraph;
+use crate::routing::RoutingRules;
+use crate::version::MavenVersion;
 
-#[derive(Debug, Deserialize, Serialize)]
+const LEGACY_CENTRAL: &str = "MavenCentral";
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct Lockfile {
     version: u32,
     pub deps: HashMap<String, PackageInfo>
 }
 
-#[derive(Deserialize, Serialize, Debug)]
+#[derive(Deserialize, Serialize, Debug, Clone)]
 pub struct PackageInfo {
-    version: String,
+    pub version: String,
     repository: String,
-    integrity: String, pub deps: Vec<String>
+    /// Exact URL the jar was downloaded from
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    url: Option<String>,
+    integrity: String,
+    /// Set on artifacts that only apply to one platform, e.g. a native classifier
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub platform: Option<String>,
+    /// Set on modules that only redirect to others and have no jar of their own
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub metadata_only: bool,
+    /// Set on modules `[overrides]` forced to their version
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub overridden: bool,
+    /// Where a module whose POM says it moved went to
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub relocated: Option<String>,
+    /// Dependencies, with `${os.detected.classifier}` standing in for native classifiers
+    pub deps: Vec<String>
+}
+
+impl PackageInfo {
+    /// Dependencies with the native classifiers for `platform` filled in
+    pub fn deps_on(&self, platform: &Platform) -> Vec<String> {
+        self.deps.iter().map(|dep| platform.expand(dep)).collect()
+    }
 }
 
 impl Lockfile {
@@ -42,7 +71,14 @@ impl Lockfile {
 
         if path.exists() {
             let content = fs::read_to_string(path)?;
-            Ok(serde_json::from_str(&content)?)
+            let mut lockfile: Self = serde_json::from_str(&content)?;
+            // Maven Central was known as MavenCentral before repositories had ids
+            for info in lockfile.deps.values_mut() {
+                if info.repository == LEGACY_CENTRAL {
+                    info.repository = CENTRAL.to_string();
+                }
+            }
+            Ok(lockfile)
         } else {
             Ok(Self::new())
         }
@@ -63,37 +99,52 @@ impl Lockfile {
         Ok(())
     }
 
-    pub async fn merge_graph(
+    pub fn merge_graph(
         &mut self,
         graph: &DependencyGraph,
-        repo_manager: &RepositoryManager
+        repo: &Repository,
+        routing: &RoutingRules,
+        platform: &Platform,
     ) -> anyhow::Result<()> {
         for (coord, version) in graph.resolved.iter() {
-            // If this exact version is already in the lockfile, skip recomputing hash
             let key = coord.to_string();
-            if let Some(existing) = self.deps.get(&key) {
+            // native edges are recorded platform-neutral so every platform's entries can share them
+            let deps = Self::get_stringified_edges(graph, coord, platform);
+            // If this exact version is already in the lockfile, skip recomputing hash
+            if let Some(existing) = self.deps.get_mut(&key) {
                 if existing.version == version.to_string() {
+                    routing.check(coord, &existing.repository)?;
+                    existing.overridden = graph.overridden.contains(coord);
+                    // exclusions may have cut some of them since
+                    existing.deps = deps;
                     continue;
                 }
             }
 
-            let jar = repo_manager.download_jar(coord, &version.to_string()).await?;
-
-            let mut hasher = Sha256::new();
-            hasher.update(&jar);
-            let hash_bytes = hasher.finalize();
-            let hash = format!("sha256:{}", hex::encode(hash_bytes));
+            // the resolver already put the jar in the project repository
+            let metadata_only = graph.metadata_only.contains(coord);
+            // metadata-only modules are pinned by the POM or .module they were resolved from
+            let hash = match graph.metadata_files.get(coord) {
+                Some(file) => repo.integrity(file, &version.to_string())?,
+                None if metadata_only => String::new(),
+                None => repo.integrity(coord, &version.to_string())?,
+            };
 
-            let repo_name = repo_manager.fetch_source_repo(coord).await?;
-
-            let deps = Self::get_stringified_edges(graph, coord);
+            let source = graph.sources.get(coord)
+                .ok_or_else(|| anyhow::anyhow!("no download source recorded for {}", coord))?;
+            routing.check(coord, &source.repository)?;
 
             self.deps.insert(
                 key,
                 PackageInfo {
                     version: version.to_string(),
-                    repository: repo_name.to_string(),
+                    repository: source.repository.clone(),
+                    url: Some(source.url.clone()),
                     integrity: hash,
+                    platform: graph.native.contains(coord).then(|| platform.classifier()),
+                    metadata_only,
+                    overridden: graph.overridden.contains(coord),
+                    relocated: graph.relocations.get(coord).map(|to| to.to_string()),
                     deps,
                 }
             );
@@ -102,11 +153,32 @@ impl Lockfile {
         Ok(())
     }
 
-    fn get_stringified_edges(graph: &DependencyGraph, coord: &Coordinate) -> Vec<String> {
+    /// The version of every locked module
+    pub fn versions(&self) -> HashMap<Coordinate, MavenVersion> {
+        self.deps.iter()
+            .filter_map(|(coord, info)| Some((Coordinate::parse_unversioned(coord).ok()?, info.version.parse().ok()?)))
+            .collect()
+    }
+
+    /// Modules whose version isn't what it was in `before`, as (module, old, new)
+    /// sorted by module, with None for ones that weren't locked before or aren't anymore
+    pub fn changes_since(&self, before: &Lockfile) -> Vec<(String, Option<String>, Option<String>)> {
+        let version = |lockfile: &Lockfile, coord: &str| lockfile.deps.get(coord).map(|info| info.version.clone());
+        let mut changes: Vec<_> = self.deps.keys().chain(before.deps.keys())
+            .collect::<std::collections::BTreeSet<_>>()
+            .into_iter()
+            .map(|coord| (coord.clone(), version(before, coord), version(self, coord)))
+            .filter(|(_, old, new)| old != new)
+            .collect();
+        changes.sort();
+        changes
+    }
+
+    fn get_stringified_edges(graph: &DependencyGraph, coord: &Coordinate, platform: &Platform) -> Vec<String> {
         graph.edges.get(coord)
             .map(|dep| {
                 dep.iter()
-                    .map(|d| d.to_string())
+                    .map(|d| if graph.native.contains(d) { platform.template(&d.to_string()) } else { d.to_string() })
                     .collect()
             })
             .unwrap_or_default()
@@ -117,6 +189,7 @@ impl Lockfile {
 mod tests {
     use super::*;
     use tempfile::TempDir;
+    use crate::download::ArtifactSource;
 
     #[test]
     fn test_lockfile_atomic_write() -> anyhow::Result<()> {
@@ -130,7 +203,12 @@ mod tests {
             PackageInfo {
                 version: "1.0.0".to_string(),
                 repository: "test-repo".to_string(),
+                url: None,
                 integrity: "sha256:test".to_string(),
+                platform: None,
+                metadata_only: false,
+                overridden: false,
+                relocated: None,
                 deps: vec![]
             }
         );
@@ -168,4 +246,54 @@ mod tests {
 
         Ok(())
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_legacy_central_name() -> anyhow::Result<()> {
+        let temp = TempDir::new()?;
+        let path = temp.path().join("gallade.lock");
+        fs::write(&path, r#"{"version": 1, "deps": {
+            "org.slf4j:slf4j-api": { "version": "2.0.9", "repository": "MavenCentral", "integrity": "", "deps": [] }
+        }}"#)?;
+
+        let lockfile = Lockfile::read(&path)?;
+        assert_eq!(lockfile.deps["org.slf4j:slf4j-api"].repository, CENTRAL);
+        let rules = RoutingRules::new(&HashMap::from([("org.slf4j".to_string(), vec![CENTRAL.to_string()])]));
+        rules.check(&Coordinate::parse("org.slf4j:slf4j-api")?, &lockfile.deps["org.slf4j:slf4j-api"].repository)?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_merge_graph_templates_native_only() -> anyhow::Result<()> {
+        let temp = TempDir::new()?;
+        let platform = Platform::new("linux", "x86_64");
+        let app = Coordinate::parse_unversioned("com.ourcorp:app")?;
+        let epoll = Coordinate::parse_unversioned("io.netty:netty-transport-native-epoll:jar:linux-x86_64")?;
+        let protoc = Coordinate::parse_unversioned("com.google.protobuf:protoc:exe:linux-x86_64")?;
+
+        // epoll was asked for with ${os.detected.classifier}, protoc with a fixed classifier
+        let mut graph = DependencyGraph::new();
+        for coord in [&app, &epoll, &protoc] {
+            graph.add_resolution(coord, "1.0".parse()?);
+            graph.metadata_only.insert(coord.clone());
+            graph.add_source(coord, ArtifactSource { repository: CENTRAL.to_string(), url: String::new() });
+        }
+        graph.add_edge(&app, &epoll);
+        graph.add_edge(&app, &protoc);
+        graph.native.insert(epoll.clone());
+
+        let mut lockfile = Lockfile::new();
+        lockfile.merge_graph(&graph, &Repository::new(temp.path().to_path_buf()), &RoutingRules::default(), &platform)?;
+
+        let mut deps = lockfile.deps["com.ourcorp:app"].deps.clone();
+        deps.sort();
+        assert_eq!(deps, vec![
+            "com.google.protobuf:protoc:exe:linux-x86_64",
+            "io.netty:netty-transport-native-epoll:jar:${os.detected.classifier}",
+        ]);
+        assert_eq!(lockfile.deps[&epoll.to_string()].platform.as_deref(), Some("linux-x86_64"));
+        assert_eq!(lockfile.deps[&protoc.to_string()].platform, None);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,234 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use serde::Deserialize;
+
+use crate::coordinates::Coordinate;
+use crate::download::ArtifactRepository;
+
+/// Name the local Maven repository goes by in `[routing]` and gallade.lock
+pub const M2: &str = "m2";
+
+// Maven resolver's record of which remote repository each file came from
+const REMOTE_MARKER: &str = "_remote.repositories";
+
+#[derive(Debug, Default, Deserialize)]
+struct Settings {
+    #[serde(rename = "localRepository")]
+    local_repository: Option<String>,
+}
+
+fn home_dir() -> Option<PathBuf> {
+    std::env::var_os("HOME")
+        .or_else(|| std::env::var_os("USERPROFILE"))
+        .filter(|v| !v.is_empty())
+        .map(PathBuf::from)
+}
+
+// settings.xml may refer to ${user.home} and ${env.NAME}
+fn expand(value: &str, home: &Path) -> String {
+    let mut expanded = value.trim().replace("${user.home}", &home.to_string_lossy());
+
+    while let Some(start) = expanded.find("${env.") {
+        let Some(len) = expanded[start..].find('}') else {
+            break;
+        };
+        let name = &expanded[start + 6..start + len];
+        let value = std::env::var(name).unwrap_or_default();
+        expanded.replace_range(start..start + len + 1, &value);
+    }
+
+    expanded
+}
+
+fn settings_local_repository(settings: &Path, home: &Path) -> Option<PathBuf> {
+    let content = fs::read_to_string(settings).ok()?;
+    let settings: Settings = quick_xml::de::from_str(&content).ok()?;
+    settings.local_repository
+        .map(|path| expand(&path, home))
+        .filter(|path| !path.is_empty())
+        .map(PathBuf::from)
+}
+
+/// `<localRepository>` from ~/.m2/settings.xml, otherwise ~/.m2/repository
+pub fn local_repository_path() -> Option<PathBuf> {
+    let home = home_dir()?;
+    let m2 = home.join(".m2");
+
+    settings_local_repository(&m2.join("settings.xml"), &home)
+        .or_else(|| Some(m2.join("repository")))
+}
+
+/// Read-through access to a Maven local repository, so jars Maven already
+/// downloaded on this machine don't get fetched a second time.
+///
+/// Files are attributed to the remote repository Maven got them from, as
+/// recorded in `_remote.repositories`. Anything that came from a repository
+/// gallade doesn't know about is left alone rather than mis-attributed, while
+/// artifacts installed locally with `mvn install` are attributed to `m2`.
+pub struct LocalMavenRepository {
+    root: PathBuf,
+    known: Vec<String>,
+}
+
+impl LocalMavenRepository {
+    pub fn new(root: PathBuf, known: Vec<String>) -> Self {
+        Self { root, known }
+    }
+
+    fn file_path(&self, coord: &Coordinate, version: &str, file: &str) -> PathBuf {
+        self.root.join(coord.to_path()).join(version).join(file)
+    }
+
+    // Lines look like `guava-31.1-jre.jar>central=`, an empty id means installed locally
+    fn marker_origins(&self, coord: &Coordinate, version: &str, file: &str) -> Vec<String> {
+        let marker = self.file_path(coord, version, REMOTE_MARKER);
+        let Ok(content) = fs::read_to_string(marker) else {
+            return vec![];
+        };
+
+        content.lines()
+            .filter(|line| !line.starts_with('#'))
+            .filter_map(|line| line.split_once('>'))
+            .filter(|(name, _)| *name == file)
+            .map(|(_, rest)| rest.trim_end_matches('=').trim().to_string())
+            .collect()
+    }
+}
+
+#[async_trait::async_trait]
+impl ArtifactRepository for LocalMavenRepository {
+    fn name(&self) -> &str {
+        M2
+    }
+
+    fn is_local(&self) -> bool {
+        true
+    }
+
+    fn artifact_url(&self, coord: &Coordinate, version: &str, file: &str) -> String {
+        format!("file://{}", self.file_path(coord, version, file).display())
+    }
+
+    fn origin(&self, coord: &Coordinate, version: &str, file: &str) -> Option<String> {
+        if !self.file_path(coord, version, file).exists() {
+            return None;
+        }
+
+        self.marker_origins(coord, version, file)
+            .into_iter()
+            .find_map(|origin| {
+                if origin.is_empty() {
+                    Some(M2.to_string())
+                } else {
+                    self.known.iter().find(|name| **name == origin).cloned()
+                }
+            })
+    }
+
+    async fn search(&self, coord: &Coordinate) -> anyhow::Result<Vec<String>> {
+        let dir = self.root.join(coord.to_path());
+        if !dir.exists() {
+            return Ok(vec![]);
+        }
+
+        let mut versions = Vec::new();
+        for entry in fs::read_dir(dir)?.flatten() {
+            let version = entry.file_name().to_string_lossy().into_owned();
+            let pom = coord.pom_file_name(&version);
+            if entry.path().join(&pom).exists() {
+                versions.push(version);
+            }
+        }
+
+        Ok(versions)
+    }
+
+    async fn fetch_jar(&self, coord: &Coordinate, version: &str) -> anyhow::Result<Vec<u8>> {
+        let path = self.file_path(coord, version, &coord.file_name(version));
+        Ok(fs::read(&path).map_err(|e| anyhow::anyhow!("{}: {}", path.display(), e))?)
+    }
+
+    async fn fetch_jar_to(&self, coord: &Coordinate, version: &str, dest: &Path) -> anyhow::Result<()> {
+        let path = self.file_path(coord, version, &coord.file_name(version));
+
+        if let Some(parent) = dest.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        if dest.exists() {
+            fs::remove_file(dest)?;
+        }
+
+        fs::copy(&path, dest).map_err(|e| anyhow::anyhow!("{}: {}", path.display(), e))?;
+        Ok(())
+    }
+
+    async fn fetch_metadata(&self, coord: &Coordinate, version: &str) -> anyhow::Result<String> {
+        let path = self.file_path(coord, version, &coord.pom_file_name(version));
+        Ok(fs::read_to_string(&path).map_err(|e| anyhow::anyhow!("{}: {}", path.display(), e))?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn install(root: &Path, coord: &Coordinate, version: &str, marker: Option<&str>) {
+        let dir = root.join(coord.to_path()).join(version);
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join(format!("{}-{}.jar", coord.name, version)), "jar").unwrap();
+        fs::write(dir.join(format!("{}-{}.pom", coord.name, version)), "<project/>").unwrap();
+
+        if let Some(id) = marker {
+            let jar = format!("{}-{}.jar", coord.name, version);
+            let pom = coord.pom_file_name(version);
+            fs::write(
+                dir.join(REMOTE_MARKER),
+                format!("#NOTE: internal file\n{}>{}=\n{}>{}=\n", jar, id, pom, id),
+            ).unwrap();
+        }
+    }
+
+    #[test]
+    fn test_origin_from_marker() {
+        let temp = TempDir::new().unwrap();
+        let repo = LocalMavenRepository::new(temp.path().to_path_buf(), vec!["central".to_string()]);
+
+        let guava = Coordinate::parse("com.google.guava:guava").unwrap();
+        let ours = Coordinate::parse("com.ourcorp:ledger").unwrap();
+        let jboss = Coordinate::parse("org.jboss:jboss-common").unwrap();
+        let unmarked = Coordinate::parse("org.slf4j:slf4j-api").unwrap();
+
+        install(temp.path(), &guava, "31.1-jre", Some("central"));
+        install(temp.path(), &ours, "1.0.0", Some(""));
+        install(temp.path(), &jboss, "2.0", Some("jboss-releases"));
+        install(temp.path(), &unmarked, "1.7.36", None);
+
+        assert_eq!(repo.origin(&guava, "31.1-jre", "guava-31.1-jre.jar").as_deref(), Some("central"));
+        assert_eq!(repo.origin(&ours, "1.0.0", "ledger-1.0.0.jar").as_deref(), Some(M2));
+        assert_eq!(repo.origin(&jboss, "2.0", "jboss-common-2.0.jar"), None);
+        assert_eq!(repo.origin(&unmarked, "1.7.36", "slf4j-api-1.7.36.jar"), None);
+        assert_eq!(repo.origin(&guava, "32.0-jre", "guava-32.0-jre.jar"), None);
+    }
+
+    #[test]
+    fn test_settings_local_repository() -> anyhow::Result<()> {
+        let temp = TempDir::new()?;
+        let settings = temp.path().join("settings.xml");
+        fs::write(&settings, r#"
+            <settings>
+                <localRepository>${user.home}/maven-cache</localRepository>
+            </settings>
+        "#)?;
+
+        assert_eq!(
+            settings_local_repository(&settings, Path::new("/home/dev")),
+            Some(PathBuf::from("/home/dev/maven-cache"))
+        );
+
+        fs::write(&settings, "<settings><offline>true</offline></settings>")?;
+        assert_eq!(settings_local_repository(&settings, Path::new("/home/dev")), None);
+
+        Ok(())
+    }
+}
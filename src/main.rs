@@ -11,22 +11,62 @@ mod classpath;
 mod build;
 mod init;
 mod run;
+mod http;
+mod routing;
+mod cache;
+mod m2;
+mod pom;
+mod package;
+mod install;
+mod publish;
+mod xml;
+mod effective;
+mod import;
+mod gradle;
+mod platform;
+mod module;
+mod update;
+mod outdated;
+mod why;
+#[cfg(test)]
+mod testing;
 
 use clap::{Parser, Subcommand};
 use coordinates::Coordinate;
 use download::RepositoryManager;
-use projects::Project;
+use projects::{BuildSystemType, Project};
 use repository::Repository;
-use resolver::DependencyResolver;
-use std::collections::HashSet;
+use resolver::{DependencyGraph, DependencyResolver};
+use std::collections::{HashMap, HashSet};
+use std::path::PathBuf;
+use crate::cache::{format_size, parse_age, ArtifactCache};
+use crate::effective::Exclusion;
 use crate::lockfile::Lockfile;
+use crate::update::Updater;
 use crate::manifest::Manifest;
+use crate::prune::DependencyPruner;
+use crate::routing::RoutingRules;
 use crate::run::{RunOptions, Runner};
 
 #[derive(Parser, Debug)]
 struct Cli {
     #[command(subcommand)]
-    command: Commands
+    command: Commands,
+    /// POM profiles to activate, `!id` to deactivate one
+    #[arg(short = 'P', global = true, value_delimiter = ',')]
+    profiles: Vec<String>,
+    /// Properties for POM profile activation and interpolation, as name=value
+    #[arg(short = 'D', global = true, value_parser = parse_property)]
+    properties: Vec<(String, String)>,
+}
+
+fn parse_property(arg: &str) -> Result<(String, String), String> {
+    // a bare -Dname is set to true, like mvn does
+    match arg.split_once('=') {
+        Some((name, value)) => Ok((name.to_string(), value.to_string())),
+        None if !arg.is_empty() => Ok((arg.to_string(), "true".to_string())),
+        None => Err("expected name=value".to_string()),
+    }
 }
 
 #[derive(Subcommand, Debug)]
@@ -35,6 +75,9 @@ enum Commands {
         coordinate: String,
         #[arg(short, long)]
         dev: bool,
+        /// `group:artifact` to leave out of its dependencies, `*` matches anything
+        #[arg(long = "exclude", value_name = "PATTERN")]
+        exclusions: Vec<String>,
     },
     Del {
         coordinate: String
@@ -53,6 +96,28 @@ enum Commands {
         #[arg(short, long)]
         debug: bool,
     },
+    /// Move locked versions as far as gallade.toml allows, for everything or only the given artifacts
+    Update {
+        coordinates: Vec<String>,
+        /// Bump the requirements in gallade.toml to the newest releases too
+        #[arg(long)]
+        latest: bool,
+        /// Put the one artifact given at exactly this version
+        #[arg(long, conflicts_with = "latest", requires = "coordinates")]
+        precise: Option<String>,
+        /// Only show what would change
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// Show locked versions that have newer ones, within gallade.toml's requirements and overall
+    Outdated {
+        #[arg(long, value_enum, default_value_t = OutputFormat::Table)]
+        format: OutputFormat,
+    },
+    /// Show every path from gallade.toml to an artifact in gallade.lock, and why its version won
+    Why {
+        coordinate: String,
+    },
     Init {
         #[arg(long = "groupId")]
         group_id: String,
@@ -68,26 +133,182 @@ enum Commands {
         debug: bool,
         #[arg(last = true)]
         args: Vec<String>
+    },
+    /// Build the project and install its jar and POM into the Maven local repository
+    Install,
+    /// Build the project and upload it to a repository from `[repositories]`
+    Publish {
+        #[arg(short, long)]
+        repository: String,
+        /// Sign every artifact with gpg and upload the .asc signatures
+        #[arg(long)]
+        sign: bool,
+    },
+    /// Convert the pom.xml or Gradle build in this project into gallade.toml
+    Import {
+        /// Overwrite an existing gallade.toml
+        #[arg(long)]
+        force: bool,
+    },
+    Export {
+        #[command(subcommand)]
+        format: ExportCommands,
+    },
+    Cache {
+        #[command(subcommand)]
+        command: CacheCommands,
     }
 }
 
+#[derive(clap::ValueEnum, Clone, Copy, Debug)]
+enum OutputFormat {
+    Table,
+    Json,
+}
+
+#[derive(Subcommand, Debug)]
+enum ExportCommands {
+    /// Write a pom.xml equivalent to gallade.toml
+    Pom {
+        /// Pin every version from gallade.lock in <dependencyManagement>
+        #[arg(long)]
+        locked: bool,
+        #[arg(short, long, default_value = "pom.xml")]
+        output: PathBuf,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+enum CacheCommands {
+    /// Artifacts in the project repository
+    List {
+        /// Show the user-level cache instead
+        #[arg(long)]
+        global: bool,
+    },
+    /// Disk usage broken down by group
+    Size {
+        #[arg(long)]
+        global: bool,
+    },
+    /// Remove artifacts gallade.lock no longer references
+    Prune {
+        /// Only report what would be removed
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// Remove entries from the user-level cache that haven't been used recently
+    Clean {
+        #[arg(long, default_value = "30d")]
+        older_than: String,
+        /// Only report what would be removed
+        #[arg(long)]
+        dry_run: bool,
+    },
+}
+
+fn print_sizes(sizes: Vec<(Coordinate, String, u64)>, total: u64) {
+    let mut groups: HashMap<String, u64> = HashMap::new();
+    for (coord, _, size) in &sizes {
+        *groups.entry(coord.namespace.clone()).or_default() += size;
+    }
+
+    let mut groups: Vec<_> = groups.into_iter().collect();
+    groups.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+
+    for (group, size) in groups {
+        println!("  {:>10}  {}", format_size(size), group);
+    }
+    println!("{} in {} artifacts", format_size(total), sizes.len());
+}
+
 fn print_tree(
+    graph: &DependencyGraph,
     coord: &Coordinate,
-    version: &str,
     seen: &mut HashSet<String>,
     depth: usize,
     detailed: bool,
 ) {
+    let version = graph.resolved.get(coord).map(|v| v.to_string()).unwrap_or_default();
     let key = format!("{}:{}", coord, version);
     let prefix = "  ".repeat(depth);
+    let mut label = if detailed { key.clone() } else { coord.to_string() };
+    if graph.overridden.contains(coord) {
+        label.push_str(" (overridden)");
+    }
+
+    // anything below a repeated artifact was already printed the first time round
+    if !seen.insert(key) {
+        println!("{}{} (*)", prefix, label);
+        return;
+    }
+    println!("{}{}", prefix, label);
+
+    let mut children: Vec<_> = graph.edges.get(coord).into_iter().flatten().collect();
+    children.sort_by_key(|c| c.to_string());
+    for child in children {
+        print_tree(graph, child, seen, depth + 1, detailed);
+    }
+}
+
+// gallade.toml when there is one, otherwise what a plain Maven project's effective POM says
+async fn load_manifest(project: &Project, resolver: &DependencyResolver) -> anyhow::Result<(Manifest, build::Layout)> {
+    let manifest_path = project.root().join("gallade.toml");
+    if manifest_path.exists() {
+        return Ok((Manifest::load(&manifest_path)?, build::Layout::standard(project.root())));
+    }
+
+    match project.build_system() {
+        BuildSystemType::Maven => {
+            let pom_path = project.root().join("pom.xml");
+            let raw = std::fs::read_to_string(&pom_path)?;
+            let mut builder = resolver.project_model(project.root());
+            import::add_local_parents(&mut builder, &pom_path)?;
+            let pom = resolver.complete_model(&raw, builder).await?;
+
+            let import = import::convert(&pom, &raw)?;
+            for warning in &import.warnings {
+                tracing::debug!("pom.xml: {}", warning);
+            }
+            Ok((import.manifest, build::Layout::from_pom(project.root(), &pom)))
+        }
+        _ => anyhow::bail!("no gallade.toml in {} - run gallade import first", project.root().display()),
+    }
+}
 
-    if detailed {
-        println!("{}{}:{}", prefix, coord, version);
-    } else {
-        println!("{}{}", prefix, coord);
+// The manifest's direct dependencies, test-scoped ones only when `with_test` is set
+fn direct_dependencies(manifest: &Manifest, with_test: bool) -> anyhow::Result<Vec<(Coordinate, String)>> {
+    let mut deps = Vec::new();
+    for (name, spec) in &manifest.deps {
+        if let manifest::DepSpec::Detailed { scope: Some(scope), .. } = spec {
+            if scope == "test" && !with_test {
+                continue;
+            }
+        }
+        deps.push((spec.coordinate(name)?, spec.version().to_string()));
+    }
+    deps.sort_by_key(|(coord, _)| coord.to_string());
+    Ok(deps)
+}
+
+// gallade.toml projects compile against their direct dependencies as before, plain Maven
+// projects against the graph their effective POM resolves to, at its maven.compiler release
+async fn builder_for(project: &Project, resolver: DependencyResolver, repo: Repository) -> anyhow::Result<build::Builder> {
+    let (manifest, layout) = load_manifest(project, &resolver).await?;
+    if project.root().join("gallade.toml").exists() {
+        return Ok(build::Builder::new(manifest, repo, layout));
     }
 
-    seen.insert(key);
+    let resolver = resolver.with_manifest(&manifest)?;
+    let lockfile_path = project.gallade_dir().join("gallade.lock");
+    let mut lockfile = Lockfile::read(&lockfile_path)?;
+    let graph = resolver.resolve_project(&direct_dependencies(&manifest, false)?, &mut lockfile).await?;
+    lockfile.write(&lockfile_path)?;
+
+    let release = manifest.project.java_version.clone();
+    Ok(build::Builder::new(manifest, repo, layout)
+        .with_resolved(graph.artifacts())
+        .with_release(release))
 }
 
 #[tokio::main]
@@ -112,51 +333,115 @@ async fn main() -> anyhow::Result<()> {
             println!("✨ initialized new gallade project: {}", artifact_id);
             return Ok(());
         }
+        Commands::Cache { command: CacheCommands::Clean { older_than, dry_run } } => {
+            let cache = ArtifactCache::open_default()
+                .ok_or_else(|| anyhow::anyhow!("couldn't locate the user cache directory"))?;
+            let report = cache.clean(parse_age(older_than)?, *dry_run)?;
+
+            let verb = if *dry_run { "would remove" } else { "removed" };
+            println!(
+                "{} {} unused entries and {} objects ({})",
+                verb, report.entries, report.objects, format_size(report.bytes)
+            );
+            return Ok(());
+        }
         _ => {
             let project = Project::find()?;
             project.ensure_dirs()?;
 
-            let repo = Repository::new(project.repository_dir());
-            let manager = RepositoryManager::new()?;
-            let resolver = DependencyResolver::new(repo.clone(), manager.clone());
+            let manifest_path = project.root().join("gallade.toml");
+            let settings = if manifest_path.exists() {
+                Some(Manifest::load(&manifest_path)?)
+            } else {
+                None
+            };
+
+            let http_config = settings.as_ref().and_then(|m| m.http.clone()).unwrap_or_default();
+            let repositories = settings.as_ref().map(|m| m.repositories.clone()).unwrap_or_default();
+            let routing = settings.as_ref()
+                .map(|m| RoutingRules::new(&m.routing))
+                .unwrap_or_default();
+
+            let mut repo = Repository::new(project.repository_dir());
+            if let Some(cache) = ArtifactCache::open_default() {
+                repo = repo.with_cache(cache);
+            }
+            let mut manager = RepositoryManager::new(&http_config, &repositories)?;
+            if let Some(local) = m2::local_repository_path().filter(|path| path.is_dir()) {
+                manager = manager.with_local_maven(local);
+            }
+            let pom_repositories = settings.as_ref().map(|m| m.pom_repositories.clone()).unwrap_or_default();
+            let manager = manager.with_routing(routing)?.with_pom_repository_policy(pom_repositories);
+            let resolver = DependencyResolver::new(repo.clone(), manager.clone())
+                .with_profiles(cli.profiles.clone())
+                .with_properties(cli.properties.iter().cloned().collect());
+            let resolver = match &settings {
+                Some(manifest) => resolver.with_manifest(manifest)?,
+                None => resolver,
+            };
 
             match cli.command {
-                Commands::Add { coordinate, dev } => {
+                Commands::Add { coordinate, dev, exclusions } => {
                     let coord = Coordinate::parse(&coordinate)?;
                     println!("resolving dependency {} and its dependencies...", coord);
 
+                    let lockfile_path = project.gallade_dir().join("gallade.lock");
+                    let mut lockfile = Lockfile::read(&lockfile_path)?;
+                    // the graph and the lockfile know modules by their unversioned name
+                    let module = Coordinate { version: None, ..coord.clone() };
+                    let locked = lockfile.deps.get(&module.to_string());
+
                     let version = if let Some(v) = coord.version.clone() {
                         v
+                    } else if let Some(info) = locked {
+                        info.version.clone()
                     } else {
-                        let versions = manager.search_versions(&coord).await?;
-                        if versions.is_empty() {
-                            anyhow::bail!("no versions found for {}", coord);
+                        update::newest(&manager, &coord).await?
+                            .ok_or_else(|| anyhow::anyhow!("no versions found for {}", coord))?
+                            .to_string()
+                    };
+
+                    // the whole project is solved again, so what the others need is weighed too
+                    let (resolver, direct, manifest) = match settings {
+                        Some(mut manifest) => {
+                            manifest.add_dep(&module, &version, dev, &exclusions)?;
+                            let direct = direct_dependencies(&manifest, true)?;
+                            (resolver.with_manifest(&manifest)?, direct, Some(manifest))
+                        }
+                        None => {
+                            let excluded = exclusions.iter().map(|e| Exclusion::parse(e)).collect();
+                            let resolver = resolver.with_exclusions(Vec::new(), HashMap::from([(module.clone(), excluded)]));
+                            (resolver, vec![(module.clone(), version.clone())], None)
                         }
-                        versions[0].clone()
                     };
 
-                    let graph = resolver.resolve(&coord, &version).await?;
+                    let before = lockfile.clone();
+                    let graph = resolver.resolve_project(&direct, &mut lockfile).await?;
+                    // recorded in gallade.toml when there is one, so build picks it up
+                    if let Some(manifest) = manifest {
+                        manifest.save(&manifest_path)?;
+                    }
 
                     println!("\nResolved dependency tree:");
                     let mut seen = HashSet::new();
-                    print_tree(&coord, &version, &mut seen, 0, true);
+                    print_tree(&graph, &module, &mut seen, 0, true);
 
                     println!("\nSuccessfully added {} and its dependencies", coord);
                     if dev {
                         println!("Added as a development dependency");
                     }
+                    if !exclusions.is_empty() {
+                        println!("Excluding {}", exclusions.join(", "));
+                    }
 
-                    let lockfile_path = project.gallade_dir().join("gallade.lock");
-                    let mut lockfile = Lockfile::read(&lockfile_path)?;
-
-                    lockfile.merge_graph(&graph, &manager).await?;
-                    lockfile.write(&project.gallade_dir().join("gallade.lock"))?;
+                    lockfile.write(&lockfile_path)?;
 
-                    println!("\nSuccessfully updated gallade.lock");
+                    println!();
+                    update::print_changes(&lockfile.changes_since(&before));
                 }
 
                 Commands::Del { coordinate } => {
-                    let coord = Coordinate::parse(&coordinate)?;
+                    let coord = Coordinate::parse_unversioned(&coordinate)?;
 
                     let lockfile_path = project.gallade_dir().join("gallade.lock");
                     let mut lockfile = if lockfile_path.exists() {
@@ -165,7 +450,9 @@ async fn main() -> anyhow::Result<()> {
                         anyhow::bail!("no gallade.lock found - nothing to remove");
                     };
 
-                    let previous_deps: HashSet<String> = lockfile.deps.keys().cloned().collect();
+                    let previous_deps: HashMap<String, String> = lockfile.deps.iter()
+                        .map(|(key, info)| (key.clone(), info.version.clone()))
+                        .collect();
 
                     if let Err(e) = resolver.remove(coord.clone(), &mut lockfile) {
                         anyhow::bail!("failed to remove {}: {}", coordinate, e);
@@ -173,21 +460,15 @@ async fn main() -> anyhow::Result<()> {
 
                     lockfile.write(&lockfile_path)?;
 
-                    let current_deps: HashSet<String> = lockfile.deps.keys().cloned().collect();
-                    let removed_deps = previous_deps.difference(&current_deps);
-
                     let mut cleaned_count = 0;
-                    for dep_str in removed_deps {
-                        if let Ok(dep_coord) = Coordinate::parse(dep_str) {
-                            if let Some(version) = &dep_coord.version {
-                                repo.remove_artifacts(&dep_coord, version)?;
-                                cleaned_count += 1;
-                            } else {
-                                for version in repo.list_versions(&dep_coord)? {
-                                    repo.remove_artifacts(&dep_coord, &version)?;
-                                    cleaned_count += 1;
-                                }
-                            }
+                    for (dep_str, version) in &previous_deps {
+                        if lockfile.deps.contains_key(dep_str) {
+                            continue;
+                        }
+                        // lock keys carry extension and classifier but never the version
+                        if let Ok(dep_coord) = Coordinate::parse_unversioned(dep_str) {
+                            repo.remove_artifacts(&dep_coord, version)?;
+                            cleaned_count += 1;
                         }
                     }
 
@@ -199,7 +480,7 @@ async fn main() -> anyhow::Result<()> {
                 }
 
                 Commands::List { coordinate } => {
-                    let coord = Coordinate::parse(&coordinate)?;
+                    let coord = Coordinate::parse_unversioned(&coordinate)?;
                     let versions = repo.list_versions(&coord)?;
                     if versions.is_empty() {
                         println!("no versions of {} found locally", coord);
@@ -212,7 +493,7 @@ async fn main() -> anyhow::Result<()> {
                 }
 
                 Commands::Search { coordinate } => {
-                    let coord = Coordinate::parse(&coordinate)?;
+                    let coord = Coordinate::parse_unversioned(&coordinate)?;
                     let versions = manager.search_versions(&coord).await?;
                     if versions.is_empty() {
                         println!("no versions found for {}", coord);
@@ -225,23 +506,114 @@ async fn main() -> anyhow::Result<()> {
                 }
 
                 Commands::Tree { detailed } => {
-                    println!("dependency tree for current project:");
-                    // TODO: Read project's direct dependencies and show full tree
-                    println!("(tree visualization coming soon)");
+                    let (manifest, _) = load_manifest(&project, &resolver).await?;
+                    let resolver = resolver.with_manifest(&manifest)?;
+                    let direct = direct_dependencies(&manifest, true)?;
+
+                    // gallade.lock belongs to gallade update for gallade.toml projects, a pom.xml has nothing else to lock it
+                    let lockfile_path = project.gallade_dir().join("gallade.lock");
+                    let mut lockfile = Lockfile::read(&lockfile_path)?;
+                    let graph = if project.root().join("gallade.toml").exists() {
+                        resolver.locked_graph(&direct, &lockfile)?
+                            .ok_or_else(|| anyhow::anyhow!("gallade.lock is missing or out of date - run gallade update first"))?
+                    } else {
+                        let graph = resolver.resolve_project(&direct, &mut lockfile).await?;
+                        lockfile.write(&lockfile_path)?;
+                        graph
+                    };
+
+                    println!("{} {}", manifest.project.name, manifest.project.version);
+                    let mut seen = HashSet::new();
+                    for (coord, _) in &direct {
+                        print_tree(&graph, coord, &mut seen, 1, detailed);
+                    }
                 }
 
                 Commands::Build { debug } => {
-                    let manifest = manifest::Manifest::load(&project.root().join("gallade.toml"))?;
-                    let builder = build::Builder::new(manifest, repo);
+                    let builder = builder_for(&project, resolver, repo).await?;
 
                     builder.build(build::BuildOptions {
                         debug,
                     })?;
                 }
 
+                Commands::Update { coordinates, latest, precise, dry_run } => {
+                    let manifest_path = project.root().join("gallade.toml");
+                    let mut manifest = Manifest::load(&manifest_path)?;
+                    let resolver = resolver.with_manifest(&manifest)?;
+                    let lockfile_path = project.gallade_dir().join("gallade.lock");
+                    let mut lockfile = Lockfile::read(&lockfile_path)?;
+
+                    let targets = coordinates.iter()
+                        .map(|c| Coordinate::parse_unversioned(c))
+                        .collect::<anyhow::Result<Vec<_>>>()?;
+                    let updater = Updater::new(&manager, targets, &manifest, &lockfile)?;
+                    let mut preferred = updater.preferred(&lockfile);
+                    let bumped = if latest { updater.bump_latest(&mut manifest).await? } else { Vec::new() };
+                    let mut manifest_changed = !bumped.is_empty();
+                    if let Some(version) = &precise {
+                        manifest_changed |= updater.pin(&mut manifest, &mut preferred, version)?;
+                    }
+                    if !bumped.is_empty() {
+                        println!("gallade.toml requirements:");
+                        for (name, old, new) in &bumped {
+                            println!("  {} {} -> {}", name, old, new);
+                        }
+                        println!();
+                    }
+
+                    let direct = direct_dependencies(&manifest, true)?;
+                    if dry_run {
+                        // only metadata is fetched, nothing is downloaded or written
+                        let planned = resolver.plan(&direct, &preferred).await?;
+                        if let Some(version) = &precise {
+                            updater.check_pinned(&planned, version)?;
+                        }
+                        update::print_changes(&update::planned_changes(&lockfile, &planned));
+                        println!("\ndry run, gallade.toml and gallade.lock were left alone");
+                    } else {
+                        let before = lockfile.clone();
+                        let graph = resolver.update_project(&direct, &mut lockfile, &preferred).await?;
+                        if let Some(version) = &precise {
+                            updater.check_pinned(&graph.resolved, version)?;
+                        }
+
+                        update::print_changes(&lockfile.changes_since(&before));
+                        if manifest_changed {
+                            manifest.save(&manifest_path)?;
+                        }
+                        lockfile.write(&lockfile_path)?;
+                    }
+                }
+
+                Commands::Outdated { format } => {
+                    let (manifest, _) = load_manifest(&project, &resolver).await?;
+                    let resolver = resolver.with_manifest(&manifest)?;
+                    let lockfile = Lockfile::read(&project.gallade_dir().join("gallade.lock"))?;
+                    if lockfile.deps.is_empty() {
+                        anyhow::bail!("nothing is locked yet - run gallade build or gallade update first");
+                    }
+
+                    let direct = direct_dependencies(&manifest, true)?;
+                    let wanted = resolver.plan(&direct, &HashMap::new()).await?;
+                    let report = outdated::report(&lockfile, &manager, &direct, &wanted).await?;
+                    match format {
+                        OutputFormat::Table => outdated::print_table(&report),
+                        OutputFormat::Json => println!("{}", serde_json::to_string_pretty(&report)?),
+                    }
+                }
+
+                Commands::Why { coordinate } => {
+                    let (manifest, _) = load_manifest(&project, &resolver).await?;
+                    let resolver = resolver.with_manifest(&manifest)?;
+                    let lockfile = Lockfile::read(&project.gallade_dir().join("gallade.lock"))?;
+                    let target = Coordinate::parse_unversioned(&coordinate)?;
+
+                    why::Explainer::new(&resolver, &manifest, &lockfile)?.explain(&target).await?;
+                }
+
                 Commands::Run {debug, args} => {
-                    let manifest = Manifest::load(&project.root().join("gallade.toml"))?;
-                    let runner = Runner::new(manifest, repo);
+                    let runner = Runner::new(builder_for(&project, resolver, repo).await?);
 
                     runner.run(RunOptions {
                         debug,
@@ -249,6 +621,198 @@ async fn main() -> anyhow::Result<()> {
                     })?;
                 }
 
+                Commands::Install => {
+                    let builder = builder_for(&project, resolver, repo).await?;
+                    let manifest = builder.manifest().clone();
+                    let coord = manifest.coordinate()?;
+                    let version = manifest.project.version.clone();
+                    let local = m2::local_repository_path()
+                        .ok_or_else(|| anyhow::anyhow!("couldn't locate the Maven local repository"))?;
+
+                    builder.build(build::BuildOptions { debug: false })?;
+                    let jar = package::Packager::new(manifest.clone(), project.root().to_path_buf(), builder.layout().clone()).package()?;
+
+                    let dir = install::Installer::new(local).install(&coord, &version, &jar, &pom::generate_pom(&manifest)?)?;
+                    println!("installed {} to {}", coord, dir.display());
+                }
+
+                Commands::Publish { repository, sign } => {
+                    let builder = builder_for(&project, resolver, repo).await?;
+                    let manifest = builder.manifest().clone();
+                    let coord = manifest.coordinate()?;
+                    let version = manifest.project.version.clone();
+                    let target = manifest.repositories.get(&repository)
+                        .ok_or_else(|| anyhow::anyhow!("no repository named '{}' in gallade.toml", repository))?;
+
+                    builder.build(build::BuildOptions { debug: false })?;
+
+                    let packager = package::Packager::new(manifest.clone(), project.root().to_path_buf(), builder.layout().clone());
+                    let javadoc = project.root().join("target").join("javadoc");
+                    builder.javadoc(&javadoc)?;
+
+                    let pom = packager.target_path(None, "pom");
+                    std::fs::write(&pom, pom::generate_pom(&manifest)?)?;
+
+                    let files = vec![
+                        packager.package()?,
+                        packager.package_classified("sources", &builder.layout().sources)?,
+                        packager.package_classified("javadoc", &javadoc)?,
+                        pom,
+                    ];
+                    // only signatures made just now, an old .asc next to a rebuilt jar wouldn't match it
+                    let signatures = if sign {
+                        files.iter().map(|file| publish::sign(file)).collect::<anyhow::Result<Vec<_>>>()?
+                    } else {
+                        Vec::new()
+                    };
+
+                    let client = http_config.build_client()?.with_credentials(target.credentials());
+                    let publisher = publish::Publisher::new(publish::PublishTarget::new(&target.url, client)?);
+                    let uploaded = publisher.publish(&coord, &version, &files, &signatures).await?;
+
+                    println!("published {} to {} ({} files)", coord, repository, uploaded.len());
+                }
+
+                Commands::Import { force } => {
+                    if manifest_path.exists() && !force {
+                        anyhow::bail!("{} already exists, pass --force to overwrite it", manifest_path.display());
+                    }
+
+                    let pom_path = project.root().join("pom.xml");
+                    if !pom_path.exists() {
+                        if gradle::build_file(project.root()).is_none() {
+                            anyhow::bail!("no pom.xml, build.gradle or build.gradle.kts found in {}", project.root().display());
+                        }
+
+                        let gradle_project = gradle::read_project(project.root())?;
+
+                        // platforms only pin versions, so fold their dependencyManagement into the deps
+                        let mut managed = HashMap::new();
+                        for platform in gradle_project.dependencies.iter().filter(|dep| dep.platform) {
+                            let Some(version) = &platform.version else {
+                                continue;
+                            };
+                            let bom = resolver.effective_pom(&Coordinate::parse(&platform.key())?, version).await?;
+                            for dep in bom.dependency_management {
+                                if let Some(version) = dep.version {
+                                    managed.entry(format!("{}:{}", dep.group_id, dep.artifact_id)).or_insert(version);
+                                }
+                            }
+                        }
+
+                        let import = gradle::convert(&gradle_project, &managed);
+                        import.manifest.save(&manifest_path)?;
+
+                        println!("imported {} with {} dependencies into gallade.toml", gradle_project.name, import.manifest.deps.len());
+                        for warning in &import.warnings {
+                            println!("  warning: {}", warning);
+                        }
+                        return Ok(());
+                    }
+
+                    let raw = std::fs::read_to_string(&pom_path)?;
+                    let mut builder = resolver.project_model(project.root());
+                    import::add_local_parents(&mut builder, &pom_path)?;
+                    let pom = resolver.complete_model(&raw, builder).await?;
+
+                    let import = import::convert(&pom, &raw)?;
+                    import.manifest.save(&manifest_path)?;
+
+                    println!("imported {} with {} dependencies into gallade.toml", pom.coordinate, import.manifest.deps.len());
+                    for warning in &import.warnings {
+                        println!("  warning: {}", warning);
+                    }
+                }
+
+                Commands::Export { format: ExportCommands::Pom { locked, output } } => {
+                    let manifest = Manifest::load(&project.root().join("gallade.toml"))?;
+                    let lockfile = if locked {
+                        Some(Lockfile::read(&project.gallade_dir().join("gallade.lock"))?)
+                    } else {
+                        None
+                    };
+
+                    let output = project.root().join(output);
+                    std::fs::write(&output, pom::export_pom(&manifest, lockfile.as_ref())?)?;
+                    println!("wrote {}", output.display());
+                }
+
+                Commands::Cache { command } => match command {
+                    CacheCommands::List { global: false } => {
+                        for (coord, version) in repo.list_artifacts()? {
+                            println!("  {}:{}  {}", coord, version, format_size(repo.artifact_size(&coord, &version)));
+                        }
+                    }
+
+                    CacheCommands::List { global: true } => {
+                        let cache = ArtifactCache::open_default()
+                            .ok_or_else(|| anyhow::anyhow!("couldn't locate the user cache directory"))?;
+                        for (coord, version, entry, size) in cache.entries() {
+                            println!("  {}:{}  {}  {}", coord, version, format_size(size), &entry.sha256[..12]);
+                        }
+                    }
+
+                    CacheCommands::Size { global: false } => {
+                        let sizes: Vec<_> = repo.list_artifacts()?
+                            .into_iter()
+                            .map(|(coord, version)| {
+                                let size = repo.artifact_size(&coord, &version);
+                                (coord, version, size)
+                            })
+                            .collect();
+                        let total = sizes.iter().map(|(_, _, size)| size).sum();
+
+                        println!("{}", project.repository_dir().display());
+                        print_sizes(sizes, total);
+                    }
+
+                    CacheCommands::Size { global: true } => {
+                        let cache = ArtifactCache::open_default()
+                            .ok_or_else(|| anyhow::anyhow!("couldn't locate the user cache directory"))?;
+
+                        // identical files share one object, count those once in the total
+                        let mut objects = HashSet::new();
+                        let mut total = 0;
+                        let mut sizes = Vec::new();
+                        for (coord, version, entry, size) in cache.entries() {
+                            if objects.insert(entry.sha256) {
+                                total += size;
+                            }
+                            sizes.push((coord, version, size));
+                        }
+
+                        println!("{}", cache.objects_dir().display());
+                        print_sizes(sizes, total);
+                    }
+
+                    CacheCommands::Prune { dry_run } => {
+                        let lockfile = Lockfile::read(&project.gallade_dir().join("gallade.lock"))?;
+
+                        let mut pruner = DependencyPruner::new();
+                        pruner.mark_locked(&lockfile);
+
+                        let mut removed = 0;
+                        let mut freed = 0;
+                        for (coord, version) in repo.list_artifacts()? {
+                            if pruner.locks_version(&lockfile, &coord, &version) {
+                                continue;
+                            }
+
+                            println!("  {}:{}", coord, version);
+                            freed += repo.artifact_size(&coord, &version);
+                            removed += 1;
+                            if !dry_run {
+                                repo.remove_artifacts(&coord, &version)?;
+                            }
+                        }
+
+                        let verb = if dry_run { "would remove" } else { "removed" };
+                        println!("{} {} unreferenced artifacts ({})", verb, removed, format_size(freed));
+                    }
+
+                    CacheCommands::Clean { .. } => unreachable!(),
+                },
+
                 Commands::Init { .. } => unreachable!(),
             }
         }
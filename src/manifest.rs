@@ -1,10 +1,18 @@
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::fs;
 use std::path::Path;
 use serde::{Deserialize, Serialize};
+use crate::coordinates::Coordinate;
+use crate::effective::Exclusion;
+use crate::http::{Credentials, HttpConfig};
+use crate::platform::{Platform, CLASSIFIER_VARIABLE};
+use crate::version::{MavenVersion, VersionReq};
 
 #[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct ProjectConfig {
+    /// Maven groupId the project is installed and published under
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub group: Option<String>,
     pub name: String,
     pub version: String,
     pub main_class: Option<String>,
@@ -16,6 +24,26 @@ pub struct Manifest {
     pub project: ProjectConfig,
     #[serde(default)]
     pub deps: Dependencies,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub http: Option<HttpConfig>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub repositories: Repositories,
+    /// Group pattern to the only repositories allowed to serve it
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub routing: HashMap<String, Vec<String>>,
+    /// URL prefixes of the repositories declared in dependency POMs that may be
+    /// used, `*` for any over https. None are by default.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub pom_repositories: Vec<String>,
+    /// `group:artifact` to the version it's forced to wherever it turns up
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub overrides: HashMap<String, String>,
+    /// `group:artifact` to a version or range it has to satisfy, if anything pulls it in
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub constraints: HashMap<String, String>,
+    /// `group:artifact` patterns left out of every dependency's subtree
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub exclusions: Vec<String>,
 }
 
 #[derive(Debug, Deserialize, Serialize, Clone)]
@@ -24,13 +52,93 @@ pub enum DepSpec {
     Simple(String),
     Detailed {
         version: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
         scope: Option<String>,
-        optional: bool
+        #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+        optional: bool,
+        /// `group:artifact` patterns left out of this dependency's subtree, `*` matches anything
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        exclusions: Vec<String>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        classifier: Option<String>,
+        /// Maven dependency type, e.g. `zip`, `war` or `test-jar`
+        #[serde(rename = "type", default, skip_serializing_if = "Option::is_none")]
+        kind: Option<String>,
     }
 }
 
+impl DepSpec {
+    pub fn version(&self) -> &str {
+        match self {
+            DepSpec::Simple(version) => version,
+            DepSpec::Detailed { version, .. } => version,
+        }
+    }
+
+    pub fn scope(&self) -> Option<&str> {
+        match self {
+            DepSpec::Simple(_) => None,
+            DepSpec::Detailed { scope, .. } => scope.as_deref(),
+        }
+    }
+
+    pub fn exclusions(&self) -> Vec<Exclusion> {
+        match self {
+            DepSpec::Simple(_) => Vec::new(),
+            DepSpec::Detailed { exclusions, .. } => exclusions.iter().map(|e| Exclusion::parse(e)).collect(),
+        }
+    }
+
+    pub fn set_version(&mut self, new: String) {
+        match self {
+            DepSpec::Simple(version) => *version = new,
+            DepSpec::Detailed { version, .. } => *version = new,
+        }
+    }
+
+    /// The artifact a `[deps]` entry refers to. The key may spell out the extension
+    /// and classifier itself, `type` and `classifier` in the spec override them.
+    /// `${os.detected.classifier}` and friends are filled in for this machine.
+    pub fn coordinate(&self, key: &str) -> anyhow::Result<Coordinate> {
+        let platform = Platform::current();
+        let coord = Coordinate::parse_unversioned(&platform.expand(key))?;
+        match self {
+            DepSpec::Detailed { classifier, kind, .. } if classifier.is_some() || kind.is_some() => {
+                let classifier = classifier.as_deref().map(|c| platform.expand(c)).or_else(|| coord.classifier.clone());
+                let kind = kind.clone().or_else(|| coord.extension.clone());
+                Ok(coord.with_type(kind.as_deref(), classifier.as_deref()))
+            }
+            _ => Ok(coord),
+        }
+    }
+
+    /// Whether the entry asks for this platform's native classifier rather than a fixed one
+    pub fn is_native(&self, key: &str) -> bool {
+        match self {
+            DepSpec::Detailed { classifier: Some(classifier), .. } => classifier.contains(CLASSIFIER_VARIABLE),
+            _ => key.contains(CLASSIFIER_VARIABLE),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct RepositoryConfig {
+    pub url: String,
+    pub username: Option<String>,
+    pub password: Option<String>,
+}
+
+impl RepositoryConfig {
+    pub fn credentials(&self) -> Option<Credentials> {
+        self.username.as_ref().map(|username| Credentials {
+            username: username.clone(),
+            password: self.password.clone(),
+        })
+    }
+}
 
 pub type Dependencies = HashMap<String, DepSpec>;
+pub type Repositories = BTreeMap<String, RepositoryConfig>;
 
 impl Manifest {
     pub fn load(path: &Path) -> anyhow::Result<Manifest> {
@@ -43,6 +151,78 @@ impl Manifest {
         fs::write(path, contents)?;
         Ok(())
     }
+
+    /// Records `coord` at `version` in `[deps]`. An entry already naming it keeps its
+    /// key as written and its other settings, `dev` and `exclusions` only add to them.
+    pub fn add_dep(&mut self, coord: &Coordinate, version: &str, dev: bool, exclusions: &[String]) -> anyhow::Result<()> {
+        let mut key = None;
+        for (name, spec) in &self.deps {
+            if &spec.coordinate(name)? == coord {
+                key = Some(name.clone());
+                break;
+            }
+        }
+        let key = key.unwrap_or_else(|| coord.to_string());
+
+        let spec = match self.deps.remove(&key) {
+            Some(DepSpec::Detailed { scope, optional, exclusions: mut kept, classifier, kind, .. }) => {
+                for exclusion in exclusions {
+                    if !kept.contains(exclusion) {
+                        kept.push(exclusion.clone());
+                    }
+                }
+                DepSpec::Detailed {
+                    version: version.to_string(),
+                    scope: if dev { Some("test".to_string()) } else { scope },
+                    optional,
+                    exclusions: kept,
+                    classifier,
+                    kind,
+                }
+            }
+            _ if dev || !exclusions.is_empty() => DepSpec::Detailed {
+                version: version.to_string(),
+                scope: dev.then(|| "test".to_string()),
+                optional: false,
+                exclusions: exclusions.to_vec(),
+                classifier: None,
+                kind: None,
+            },
+            _ => DepSpec::Simple(version.to_string()),
+        };
+        self.deps.insert(key, spec);
+        Ok(())
+    }
+
+    pub fn parsed_overrides(&self) -> anyhow::Result<HashMap<Coordinate, MavenVersion>> {
+        self.overrides.iter()
+            .map(|(coord, version)| Ok((Coordinate::parse_unversioned(coord)?, version.parse()?)))
+            .collect()
+    }
+
+    pub fn parsed_constraints(&self) -> anyhow::Result<HashMap<Coordinate, VersionReq>> {
+        self.constraints.iter()
+            .map(|(coord, version)| Ok((Coordinate::parse_unversioned(coord)?, VersionReq::parse(version)?)))
+            .collect()
+    }
+
+    pub fn parsed_exclusions(&self) -> Vec<Exclusion> {
+        self.exclusions.iter().map(|e| Exclusion::parse(e)).collect()
+    }
+
+    /// groupId:artifactId:version the project itself is known by in Maven repositories
+    pub fn coordinate(&self) -> anyhow::Result<Coordinate> {
+        let Some(group) = &self.project.group else {
+            anyhow::bail!("gallade.toml has no project.group - add one to install or publish the project");
+        };
+
+        Ok(Coordinate {
+            namespace: group.clone(),
+            name: self.project.name.clone(),
+            version: Some(self.project.version.clone()),
+            ..Default::default()
+        })
+    }
 }
 
 #[cfg(test)]
@@ -75,5 +255,132 @@ mod tests {
 
         Ok(())
     }
-}
 
+    #[test]
+    fn test_parse_repositories_and_routing() -> anyhow::Result<()> {
+        let manifest: Manifest = toml::from_str(r#"
+            [project]
+            name = "billing"
+            version = "1.0.0"
+
+            [repositories]
+            internal = { url = "https://nexus.ourcorp.com/repository/releases", username = "ci" }
+
+            [routing]
+            "com.ourcorp.*" = ["internal"]
+        "#)?;
+
+        let internal = &manifest.repositories["internal"];
+        assert_eq!(internal.credentials().unwrap().username, "ci");
+        assert_eq!(manifest.routing["com.ourcorp.*"], vec!["internal"]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_classifier_and_type() -> anyhow::Result<()> {
+        let manifest: Manifest = toml::from_str(r#"
+            [project]
+            name = "billing"
+            version = "1.0.0"
+
+            [deps]
+            "io.netty:netty-transport-native-epoll:jar:linux-x86_64" = "4.1.100.Final"
+            "com.ourcorp:ledger" = { version = "1.0.0", optional = false, type = "test-jar" }
+            "com.ourcorp:assets" = { version = "2.0.0", optional = false, type = "zip", classifier = "web" }
+        "#)?;
+
+        let coordinate = |key: &str| manifest.deps[key].coordinate(key).map(|c| c.to_string());
+        assert_eq!(coordinate("io.netty:netty-transport-native-epoll:jar:linux-x86_64")?, "io.netty:netty-transport-native-epoll:jar:linux-x86_64");
+        assert_eq!(coordinate("com.ourcorp:ledger")?, "com.ourcorp:ledger:jar:tests");
+        assert_eq!(coordinate("com.ourcorp:assets")?, "com.ourcorp:assets:zip:web");
+        assert_eq!(manifest.deps["com.ourcorp:assets"].version(), "2.0.0");
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_overrides_and_constraints() -> anyhow::Result<()> {
+        let manifest: Manifest = toml::from_str(r#"
+            [project]
+            name = "app"
+            version = "1.0"
+
+            [overrides]
+            "org.yaml:snakeyaml" = "2.2"
+
+            [constraints]
+            "com.fasterxml.jackson.core:jackson-databind" = "[2.15.0,)"
+        "#)?;
+
+        let overrides = manifest.parsed_overrides()?;
+        assert_eq!(overrides[&Coordinate::parse("org.yaml:snakeyaml")?].to_string(), "2.2");
+        let constraints = manifest.parsed_constraints()?;
+        let databind = &constraints[&Coordinate::parse("com.fasterxml.jackson.core:jackson-databind")?];
+        assert!(databind.is_hard() && databind.matches(&"2.16.1".parse()?));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_exclusions_and_partial_specs() -> anyhow::Result<()> {
+        let manifest: Manifest = toml::from_str(r#"
+            exclusions = ["commons-logging:commons-logging"]
+
+            [project]
+            name = "app"
+            version = "1.0"
+
+            [deps]
+            "junit:junit" = { version = "4.13.2", scope = "test" }
+            "org.apache.hadoop:hadoop-client" = { version = "3.3.6", exclusions = ["org.slf4j:*", "log4j"] }
+        "#)?;
+
+        assert_eq!(manifest.deps["junit:junit"].scope(), Some("test"));
+        let excluded: Vec<String> = manifest.deps["org.apache.hadoop:hadoop-client"].exclusions().iter().map(|e| e.to_string()).collect();
+        assert_eq!(excluded, ["org.slf4j:*", "log4j:*"]);
+        assert_eq!(manifest.parsed_exclusions()[0].to_string(), "commons-logging:commons-logging");
+
+        // survives a round trip without growing defaults
+        let temp = tempfile::TempDir::new()?;
+        manifest.save(&temp.path().join("gallade.toml"))?;
+        let saved = fs::read_to_string(temp.path().join("gallade.toml"))?;
+        assert!(!saved.contains("optional"));
+        assert_eq!(Manifest::load(&temp.path().join("gallade.toml"))?.exclusions, manifest.exclusions);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_add_dep() -> anyhow::Result<()> {
+        let mut manifest: Manifest = toml::from_str(r#"
+            [project]
+            name = "app"
+            version = "1.0"
+
+            [deps]
+            "org.apache.hadoop:hadoop-client" = { version = "3.3.6", exclusions = ["org.slf4j:*"] }
+            "io.netty:netty-transport-native-epoll:jar:${os.detected.classifier}" = "4.1.100.Final"
+        "#)?;
+
+        // merged into the existing entry rather than replacing it
+        let hadoop = Coordinate::parse("org.apache.hadoop:hadoop-client")?;
+        manifest.add_dep(&hadoop, "3.4.0", true, &["log4j:log4j".to_string()])?;
+        let spec = &manifest.deps["org.apache.hadoop:hadoop-client"];
+        assert_eq!(spec.version(), "3.4.0");
+        assert_eq!(spec.scope(), Some("test"));
+        let excluded: Vec<String> = spec.exclusions().iter().map(|e| e.to_string()).collect();
+        assert_eq!(excluded, ["org.slf4j:*", "log4j:log4j"]);
+
+        // found under the key it was written with
+        let epoll = Coordinate::parse_unversioned(&format!("io.netty:netty-transport-native-epoll:jar:{}", Platform::current().classifier()))?;
+        manifest.add_dep(&epoll, "4.1.101.Final", false, &[])?;
+        assert_eq!(manifest.deps.len(), 2);
+        assert_eq!(manifest.deps["io.netty:netty-transport-native-epoll:jar:${os.detected.classifier}"].version(), "4.1.101.Final");
+
+        manifest.add_dep(&Coordinate::parse("junit:junit")?, "4.13.2", false, &[])?;
+        assert!(matches!(&manifest.deps["junit:junit"], DepSpec::Simple(version) if version == "4.13.2"));
+
+        Ok(())
+    }
+}
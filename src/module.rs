@@ -0,0 +1,399 @@
+use std::collections::HashMap;
+
+use serde::Deserialize;
+use serde_json::Value;
+
+use crate::coordinates::Coordinate;
+use crate::effective::Exclusion;
+use crate::resolver::{DependencyRequest, MetadataParser};
+use crate::version::VersionReq;
+
+/// Comment Gradle leaves in the POMs of modules it also published a `.module` file for
+pub const GRADLE_METADATA_MARKER: &str = "do_not_remove: published-with-gradle-metadata";
+
+const CATEGORY: &str = "org.gradle.category";
+const USAGE: &str = "org.gradle.usage";
+const LIBRARY_ELEMENTS: &str = "org.gradle.libraryelements";
+const JVM_VERSION: &str = "org.gradle.jvm.version";
+const JVM_ENVIRONMENT: &str = "org.gradle.jvm.environment";
+const KOTLIN_PLATFORM: &str = "org.jetbrains.kotlin.platform.type";
+
+/// A Gradle Module Metadata file, only the parts dependency resolution needs
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ModuleMetadata {
+    pub format_version: String,
+    pub component: Option<Component>,
+    #[serde(default)]
+    pub variants: Vec<Variant>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct Component {
+    pub group: String,
+    pub module: String,
+    pub version: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Variant {
+    pub name: String,
+    #[serde(default)]
+    pub attributes: HashMap<String, Value>,
+    /// The variant lives in another module, e.g. the `-jvm` one of a Kotlin multiplatform library
+    #[serde(rename = "available-at")]
+    pub available_at: Option<AvailableAt>,
+    #[serde(default)]
+    pub dependencies: Vec<ModuleDependency>,
+    #[serde(default)]
+    pub files: Vec<VariantFile>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct VariantFile {
+    /// Relative to the `.module` file
+    pub url: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct AvailableAt {
+    pub group: String,
+    pub module: String,
+    pub version: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ModuleDependency {
+    pub group: String,
+    pub module: String,
+    pub version: Option<VersionConstraint>,
+    #[serde(default)]
+    pub excludes: Vec<ModuleExclude>,
+    pub third_party_compatibility: Option<ThirdPartyCompatibility>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct VersionConstraint {
+    pub strictly: Option<String>,
+    pub requires: Option<String>,
+    pub prefers: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ModuleExclude {
+    pub group: String,
+    pub module: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ThirdPartyCompatibility {
+    pub artifact_selector: Option<ArtifactSelector>,
+}
+
+/// Points at a classified or non-jar artifact of a module only published with a POM
+#[derive(Debug, Deserialize)]
+pub struct ArtifactSelector {
+    #[serde(rename = "type")]
+    pub kind: Option<String>,
+    pub extension: Option<String>,
+    pub classifier: Option<String>,
+}
+
+impl ModuleMetadata {
+    pub fn parse(content: &str) -> anyhow::Result<Self> {
+        let module: Self = serde_json::from_str(content)?;
+        if !module.format_version.starts_with("1.") {
+            anyhow::bail!("unsupported Gradle module metadata format {}", module.format_version);
+        }
+        Ok(module)
+    }
+}
+
+impl Variant {
+    fn attribute(&self, name: &str) -> Option<String> {
+        self.attributes.get(name).map(|value| match value {
+            Value::String(s) => s.clone(),
+            other => other.to_string(),
+        })
+    }
+
+    fn jvm_version(&self) -> Option<u32> {
+        self.attribute(JVM_VERSION).and_then(|v| v.parse().ok())
+    }
+
+    /// The artifact of `coord` the variant puts on the classpath, e.g. the
+    /// `-java11` jar, None when it has no file of its own. Gradle publishes the
+    /// files next to the `.module` with Maven's naming, so they're classified
+    /// artifacts of the same module.
+    pub fn artifact(&self, coord: &Coordinate, version: &str) -> anyhow::Result<Option<Coordinate>> {
+        let Some(file) = self.files.first().filter(|_| self.available_at.is_none()) else {
+            return Ok(None);
+        };
+
+        let unexpected = || anyhow::anyhow!("variant {} of {}:{} has a file at {} gallade can't fetch", self.name, coord, version, file.url);
+        let rest = file.url.strip_prefix(&format!("{}-{}", coord.name, version))
+            .filter(|rest| !rest.contains('/'))
+            .ok_or_else(unexpected)?;
+        let (classifier, extension) = rest.rsplit_once('.').ok_or_else(unexpected)?;
+        let classifier = match classifier {
+            "" => None,
+            classifier => Some(classifier.strip_prefix('-').ok_or_else(unexpected)?.to_string()),
+        };
+
+        Ok(Some(Coordinate {
+            version: None,
+            extension: Some(extension.to_string()).filter(|e| e != "jar"),
+            classifier,
+            ..coord.clone()
+        }))
+    }
+}
+
+/// Picks the variant of a `.module` file a plain JVM project would, and reads
+/// its dependencies
+pub struct ModuleParser {
+    java_version: Option<u32>,
+}
+
+impl ModuleParser {
+    /// `java_version` as written in gallade.toml, `1.8` and `8` being the same
+    pub fn new(java_version: Option<&str>) -> Self {
+        let java_version = java_version
+            .map(|v| v.strip_prefix("1.").unwrap_or(v))
+            .and_then(|v| v.parse().ok());
+        Self { java_version }
+    }
+
+    // Runtime variants rank above api ones since the same classpath is used to
+    // compile and run, None for anything that isn't a jar for the JVM
+    fn rank(&self, variant: &Variant) -> Option<(u8, u32)> {
+        let is = |name: &str, allowed: &[&str]| variant.attribute(name).is_none_or(|v| allowed.contains(&v.as_str()));
+        if !is(CATEGORY, &["library"])
+            || !is(LIBRARY_ELEMENTS, &["jar"])
+            || !is(JVM_ENVIRONMENT, &["standard-jvm"])
+            || !is(KOTLIN_PLATFORM, &["jvm"]) {
+            return None;
+        }
+
+        let usage = match variant.attribute(USAGE).as_deref() {
+            Some("java-runtime" | "kotlin-runtime") => 2,
+            Some("java-api" | "kotlin-api") => 1,
+            _ => return None,
+        };
+
+        let jvm = variant.jvm_version();
+        if let (Some(needed), Some(ours)) = (jvm, self.java_version) {
+            if needed > ours {
+                return None;
+            }
+        }
+        Some((usage, jvm.unwrap_or(0)))
+    }
+
+    /// The variant matching the project's Java version most closely
+    pub fn select<'a>(&self, module: &'a ModuleMetadata) -> anyhow::Result<&'a Variant> {
+        module.variants.iter()
+            .filter_map(|variant| self.rank(variant).map(|rank| (rank, variant)))
+            .max_by_key(|(rank, _)| *rank)
+            .map(|(_, variant)| variant)
+            .ok_or_else(|| {
+                let name = module.component.as_ref()
+                    .map(|c| format!("{}:{}:{}", c.group, c.module, c.version))
+                    .unwrap_or_else(|| "module".to_string());
+                let java = self.java_version.map(|v| format!(" on java {}", v)).unwrap_or_default();
+                let offered: Vec<_> = module.variants.iter()
+                    .map(|v| match v.jvm_version() {
+                        Some(jvm) => format!("{} (java {})", v.name, jvm),
+                        None => v.name.clone(),
+                    })
+                    .collect();
+                anyhow::anyhow!("no variant of {} can be used{}, it offers {}", name, java, offered.join(", "))
+            })
+    }
+}
+
+impl MetadataParser for ModuleParser {
+    type Metadata = ModuleMetadata;
+
+    fn parse_dependencies(&self, module: &ModuleMetadata) -> anyhow::Result<Vec<DependencyRequest>> {
+        let variant = self.select(module)?;
+
+        // a redirected variant is whatever the other module publishes, at exactly that version
+        if let Some(target) = &variant.available_at {
+            return Ok(vec![DependencyRequest {
+                coordinate: Coordinate {
+                    namespace: target.group.clone(),
+                    name: target.module.clone(),
+                    ..Default::default()
+                },
+                version_req: VersionReq::parse(&target.version)?,
+                scope: None,
+                exclusions: Vec::new(),
+                depth: 0,
+                native: false,
+            }]);
+        }
+
+        let mut requests = Vec::new();
+        for dep in &variant.dependencies {
+            let mut coordinate = Coordinate {
+                namespace: dep.group.clone(),
+                name: dep.module.clone(),
+                ..Default::default()
+            };
+            if let Some(selector) = dep.third_party_compatibility.as_ref().and_then(|t| t.artifact_selector.as_ref()) {
+                let kind = selector.extension.as_deref().or(selector.kind.as_deref());
+                coordinate = coordinate.with_type(kind, selector.classifier.as_deref());
+            }
+
+            // a strict version is a requirement, anything else is only a preference
+            let version_req = match dep.version.as_ref() {
+                Some(VersionConstraint { strictly: Some(v), .. }) if !v.starts_with(['[', '(']) => {
+                    VersionReq::parse(&format!("[{}]", v))?
+                }
+                Some(v) => match v.strictly.as_ref().or(v.requires.as_ref()).or(v.prefers.as_ref()) {
+                    Some(v) => VersionReq::parse(v)?,
+                    None => VersionReq::Latest,
+                },
+                None => VersionReq::Latest,
+            };
+
+            requests.push(DependencyRequest {
+                coordinate,
+                version_req,
+                scope: None,
+                exclusions: dep.excludes.iter()
+                    .map(|ex| Exclusion { group_id: ex.group.clone(), artifact_id: ex.module.clone() })
+                    .collect(),
+                depth: 0,
+                native: false,
+            });
+        }
+
+        Ok(requests)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const COROUTINES: &str = r#"{
+        "formatVersion": "1.1",
+        "component": { "group": "org.jetbrains.kotlinx", "module": "kotlinx-coroutines-core", "version": "1.7.3" },
+        "variants": [
+            {
+                "name": "metadataApiElements",
+                "attributes": { "org.gradle.category": "library", "org.gradle.usage": "kotlin-metadata",
+                    "org.jetbrains.kotlin.platform.type": "common" },
+                "files": [ { "name": "kotlinx-coroutines-core-metadata-1.7.3.jar", "url": "kotlinx-coroutines-core-metadata-1.7.3.jar" } ]
+            },
+            {
+                "name": "jvmRuntimeElements-published",
+                "attributes": { "org.gradle.category": "library", "org.gradle.usage": "java-runtime",
+                    "org.gradle.libraryelements": "jar", "org.jetbrains.kotlin.platform.type": "jvm" },
+                "available-at": { "url": "../../kotlinx-coroutines-core-jvm/1.7.3/kotlinx-coroutines-core-jvm-1.7.3.module",
+                    "group": "org.jetbrains.kotlinx", "module": "kotlinx-coroutines-core-jvm", "version": "1.7.3" }
+            },
+            {
+                "name": "jsRuntimeElements-published",
+                "attributes": { "org.gradle.category": "library", "org.gradle.usage": "kotlin-runtime",
+                    "org.jetbrains.kotlin.platform.type": "js" },
+                "available-at": { "url": "../../kotlinx-coroutines-core-js/1.7.3/kotlinx-coroutines-core-js-1.7.3.module",
+                    "group": "org.jetbrains.kotlinx", "module": "kotlinx-coroutines-core-js", "version": "1.7.3" }
+            }
+        ]
+    }"#;
+
+    const LEDGER: &str = r#"{
+        "formatVersion": "1.1",
+        "component": { "group": "com.ourcorp", "module": "ledger", "version": "2.0.0" },
+        "variants": [
+            {
+                "name": "apiElements",
+                "attributes": { "org.gradle.category": "library", "org.gradle.usage": "java-api", "org.gradle.jvm.version": 17 },
+                "dependencies": [ { "group": "org.slf4j", "module": "slf4j-api", "version": { "requires": "2.0.9" } } ],
+                "files": [ { "name": "ledger-2.0.0.jar", "url": "ledger-2.0.0.jar" } ]
+            },
+            {
+                "name": "runtimeElements",
+                "attributes": { "org.gradle.category": "library", "org.gradle.usage": "java-runtime", "org.gradle.jvm.version": 17 },
+                "dependencies": [
+                    { "group": "org.slf4j", "module": "slf4j-api", "version": { "requires": "2.0.9" } },
+                    { "group": "com.google.guava", "module": "guava", "version": { "strictly": "[32.0,33.0)", "prefers": "32.1.3" },
+                      "excludes": [ { "group": "com.google.code.findbugs", "module": "*" } ] },
+                    { "group": "io.netty", "module": "netty-transport-native-epoll", "version": { "requires": "4.1.100" },
+                      "thirdPartyCompatibility": { "artifactSelector": { "name": "netty-transport-native-epoll", "type": "jar",
+                          "classifier": "linux-x86_64" } } }
+                ],
+                "files": [ { "name": "ledger-2.0.0.jar", "url": "ledger-2.0.0.jar" } ]
+            },
+            {
+                "name": "java11RuntimeElements",
+                "attributes": { "org.gradle.category": "library", "org.gradle.usage": "java-runtime", "org.gradle.jvm.version": 11 },
+                "dependencies": [ { "group": "org.slf4j", "module": "slf4j-api", "version": { "requires": "1.7.36" } } ],
+                "files": [ { "name": "ledger-2.0.0-java11.jar", "url": "ledger-2.0.0-java11.jar" } ]
+            },
+            {
+                "name": "javadocElements",
+                "attributes": { "org.gradle.category": "documentation", "org.gradle.usage": "java-runtime" },
+                "files": [ { "name": "ledger-2.0.0-javadoc.jar", "url": "ledger-2.0.0-javadoc.jar" } ]
+            }
+        ]
+    }"#;
+
+    #[test]
+    fn test_variant_for_java_version() -> anyhow::Result<()> {
+        let module = ModuleMetadata::parse(LEDGER)?;
+
+        let newest = ModuleParser::new(Some("21"));
+        assert_eq!(newest.select(&module)?.name, "runtimeElements");
+        let deps = newest.parse_dependencies(&module)?;
+        let coords: Vec<_> = deps.iter().map(|d| d.coordinate.to_string()).collect();
+        assert_eq!(coords, vec![
+            "org.slf4j:slf4j-api",
+            "com.google.guava:guava",
+            "io.netty:netty-transport-native-epoll:jar:linux-x86_64",
+        ]);
+        // strictly wins over prefers
+        assert!(matches!(deps[1].version_req, VersionReq::Range { .. }));
+        assert_eq!(deps[1].exclusions[0].to_string(), "com.google.code.findbugs:*");
+
+        let ledger = Coordinate::parse("com.ourcorp:ledger")?;
+        assert_eq!(newest.select(&module)?.artifact(&ledger, "2.0.0")?, Some(ledger.clone()));
+
+        let java11 = ModuleParser::new(Some("11")).select(&module)?;
+        assert_eq!(java11.name, "java11RuntimeElements");
+        // its own jar, not the one Maven would pick
+        let artifact = java11.artifact(&ledger, "2.0.0")?.expect("java11RuntimeElements has a file");
+        assert_eq!(artifact.file_name("2.0.0"), "ledger-2.0.0-java11.jar");
+        assert_eq!(ModuleParser::new(None).select(&module)?.name, "runtimeElements");
+
+        let err = ModuleParser::new(Some("1.8")).select(&module).unwrap_err().to_string();
+        assert!(err.contains("com.ourcorp:ledger:2.0.0"), "{}", err);
+        assert!(err.contains("on java 8"), "{}", err);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_available_at() -> anyhow::Result<()> {
+        let module = ModuleMetadata::parse(COROUTINES)?;
+        let parser = ModuleParser::new(Some("17"));
+
+        let variant = parser.select(&module)?;
+        assert_eq!(variant.name, "jvmRuntimeElements-published");
+        let core = Coordinate::parse("org.jetbrains.kotlinx:kotlinx-coroutines-core")?;
+        assert_eq!(variant.artifact(&core, "1.7.3")?, None);
+
+        let deps = parser.parse_dependencies(&module)?;
+        assert_eq!(deps.len(), 1);
+        assert_eq!(deps[0].coordinate.to_string(), "org.jetbrains.kotlinx:kotlinx-coroutines-core-jvm");
+        assert!(matches!(&deps[0].version_req, VersionReq::Exact(v) if v.to_string() == "1.7.3"));
+
+        Ok(())
+    }
+}
@@ -0,0 +1,139 @@
+use std::collections::{HashMap, HashSet};
+
+use serde::Serialize;
+
+use crate::coordinates::Coordinate;
+use crate::download::RepositoryManager;
+use crate::lockfile::Lockfile;
+use crate::platform::Platform;
+use crate::update::newest;
+use crate::version::MavenVersion;
+
+/// A locked module that has something newer
+#[derive(Debug, Serialize)]
+pub struct Outdated {
+    pub module: String,
+    pub current: String,
+    /// What `gallade update` would move it to, None when it would drop it
+    pub wanted: Option<String>,
+    /// The newest release in any repository
+    pub latest: Option<String>,
+    /// Declared in the manifest rather than pulled in by something else
+    pub direct: bool,
+    /// The latest version is a new major version
+    pub major: bool,
+}
+
+/// Every entry of `lockfile` whose wanted or latest version isn't the locked one,
+/// sorted by module. `wanted` is what resolving without the lockfile picks, on this
+/// platform, so native artifacts locked for other platforms are left out.
+pub async fn report(
+    lockfile: &Lockfile,
+    manager: &RepositoryManager,
+    direct: &[(Coordinate, String)],
+    wanted: &HashMap<Coordinate, MavenVersion>,
+) -> anyhow::Result<Vec<Outdated>> {
+    let direct: HashSet<&Coordinate> = direct.iter().map(|(coord, _)| coord).collect();
+    let mut entries: Vec<_> = lockfile.deps.iter().collect();
+    entries.sort_by_key(|(coord, _)| coord.as_str());
+
+    let current_platform = Platform::current().classifier();
+    let mut report = Vec::new();
+    for (key, info) in entries {
+        if info.platform.as_ref().is_some_and(|p| *p != current_platform) {
+            continue;
+        }
+        let coord = Coordinate::parse_unversioned(key)?;
+        let current: MavenVersion = info.version.parse()?;
+        let wanted = wanted.get(&coord);
+        let latest = newest(manager, &coord).await?;
+
+        if wanted == Some(&current) && latest.as_ref().is_none_or(|latest| latest <= &current) {
+            continue;
+        }
+        report.push(Outdated {
+            module: key.clone(),
+            current: current.to_string(),
+            wanted: wanted.map(|v| v.to_string()),
+            major: latest.as_ref().is_some_and(|latest| latest.major() > current.major()),
+            latest: latest.map(|v| v.to_string()),
+            direct: direct.contains(&coord),
+        });
+    }
+    Ok(report)
+}
+
+/// Prints the report as an aligned table, direct dependencies marked with a *
+pub fn print_table(report: &[Outdated]) {
+    if report.is_empty() {
+        println!("everything is up to date");
+        return;
+    }
+
+    let rows: Vec<[String; 5]> = report.iter()
+        .map(|entry| [
+            format!("{}{}", entry.module, if entry.direct { " *" } else { "" }),
+            entry.current.clone(),
+            entry.wanted.clone().unwrap_or_else(|| "(removed)".to_string()),
+            entry.latest.clone().unwrap_or_else(|| "-".to_string()),
+            if entry.major { "major".to_string() } else { String::new() },
+        ])
+        .collect();
+    let header = ["module", "current", "wanted", "latest", ""].map(String::from);
+
+    let mut widths = [0; 4];
+    for row in std::iter::once(&header).chain(&rows) {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.len());
+        }
+    }
+    for row in std::iter::once(&header).chain(&rows) {
+        let line = format!(
+            "{:w0$}  {:w1$}  {:w2$}  {:w3$}  {}",
+            row[0], row[1], row[2], row[3], row[4],
+            w0 = widths[0], w1 = widths[1], w2 = widths[2], w3 = widths[3],
+        );
+        println!("{}", line.trim_end());
+    }
+    if report.iter().any(|entry| entry.direct) {
+        println!("\n* declared in gallade.toml");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{offline_manager, publish};
+
+    #[tokio::test]
+    async fn test_report() -> anyhow::Result<()> {
+        let m2 = tempfile::TempDir::new()?;
+        for (artifact, version) in [("b", "1.0"), ("b", "1.2"), ("b", "2.0"), ("c", "4.0")] {
+            let pom = format!("{}-{}.pom", artifact, version);
+            publish(m2.path(), &format!("com.ourcorp:{}", artifact), version, &[(&pom, b"<project/>")])?;
+        }
+        let manager = offline_manager(m2.path())?;
+
+        let elsewhere = if Platform::current().name == "osx" { "windows-x86_64" } else { "osx-aarch_64" };
+        let lockfile: Lockfile = serde_json::from_str(&format!(r#"{{"version": 1, "deps": {{
+            "com.ourcorp:b": {{ "version": "1.0", "repository": "central", "integrity": "", "deps": [] }},
+            "com.ourcorp:c": {{ "version": "4.0", "repository": "central", "integrity": "", "deps": [] }},
+            "com.ourcorp:c:jar:{0}": {{ "version": "4.0", "repository": "central", "integrity": "",
+                "platform": "{0}", "deps": [] }}
+        }}}}"#, elsewhere))?;
+        let b = Coordinate::parse("com.ourcorp:b")?;
+        let c = Coordinate::parse("com.ourcorp:c")?;
+        let direct = [(b.clone(), "[1.0,2.0)".to_string())];
+        let wanted = HashMap::from([(b, "1.2".parse()?), (c, "4.0".parse()?)]);
+
+        // c is as new as it gets, so only b shows up, and c's native jar for another
+        // platform isn't reported as removed just because this one doesn't want it
+        let report = report(&lockfile, &manager, &direct, &wanted).await?;
+        assert_eq!(report.len(), 1);
+        let b = &report[0];
+        assert_eq!((b.current.as_str(), b.wanted.as_deref(), b.latest.as_deref()), ("1.0", Some("1.2"), Some("2.0")));
+        assert!(b.direct && b.major);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,232 @@
+use std::collections::HashSet;
+use std::fs::{self, File};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use walkdir::WalkDir;
+use zip::write::SimpleFileOptions;
+use zip::ZipWriter;
+
+use crate::build::Layout;
+use crate::manifest::Manifest;
+use crate::pom::generate_pom;
+
+// A jar being written, where an entry name that's already in it is skipped
+// since zip won't take the same name twice
+struct Jar {
+    zip: ZipWriter<File>,
+    names: HashSet<String>,
+}
+
+impl Jar {
+    fn directory(&mut self, name: String) -> anyhow::Result<()> {
+        if self.names.insert(name.clone()) {
+            self.zip.add_directory(name, SimpleFileOptions::default())?;
+        }
+        Ok(())
+    }
+
+    fn file(&mut self, name: String, content: &[u8]) -> anyhow::Result<()> {
+        if self.names.insert(name.clone()) {
+            self.zip.start_file(name, SimpleFileOptions::default())?;
+            self.zip.write_all(content)?;
+        }
+        Ok(())
+    }
+
+    fn finish(self) -> anyhow::Result<()> {
+        self.zip.finish()?;
+        Ok(())
+    }
+}
+
+/// Packages compiled classes and resources into a jar under target/
+pub struct Packager {
+    manifest: Manifest,
+    root: PathBuf,
+    layout: Layout,
+}
+
+impl Packager {
+    pub fn new(manifest: Manifest, root: PathBuf, layout: Layout) -> Self {
+        Self { manifest, root, layout }
+    }
+
+    pub fn jar_path(&self) -> PathBuf {
+        self.target_path(None, "jar")
+    }
+
+    /// target/<name>-<version>[-<classifier>].<extension>
+    pub fn target_path(&self, classifier: Option<&str>, extension: &str) -> PathBuf {
+        let project = &self.manifest.project;
+        let name = match classifier {
+            Some(classifier) => format!("{}-{}-{}.{}", project.name, project.version, classifier, extension),
+            None => format!("{}-{}.{}", project.name, project.version, extension),
+        };
+        self.root.join("target").join(name)
+    }
+
+    fn jar_manifest(&self, main_class: Option<&str>) -> String {
+        let mut mf = String::from("Manifest-Version: 1.0\r\nCreated-By: gallade\r\n");
+        if let Some(main_class) = main_class {
+            mf.push_str(&format!("Main-Class: {}\r\n", main_class));
+        }
+        mf.push_str("\r\n");
+        mf
+    }
+
+    fn start_jar(&self, path: &Path, main_class: Option<&str>) -> anyhow::Result<Jar> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        let mut jar = Jar { zip: ZipWriter::new(File::create(path)?), names: HashSet::new() };
+        jar.directory("META-INF/".to_string())?;
+        jar.file("META-INF/MANIFEST.MF".to_string(), self.jar_manifest(main_class).as_bytes())?;
+        Ok(jar)
+    }
+
+    // Adds everything under `dir` to the jar, relative to `dir`. Whatever was added
+    // first wins, so classes are kept over resources of the same name.
+    fn add_tree(&self, jar: &mut Jar, dir: &Path) -> anyhow::Result<()> {
+        if !dir.exists() {
+            return Ok(());
+        }
+
+        for entry in WalkDir::new(dir).sort_by_file_name().min_depth(1) {
+            let entry = entry?;
+            let relative = entry.path().strip_prefix(dir)?;
+            // jar entries always use forward slashes
+            let name = relative.components()
+                .map(|c| c.as_os_str().to_string_lossy())
+                .collect::<Vec<_>>()
+                .join("/");
+
+            if entry.file_type().is_dir() {
+                jar.directory(format!("{}/", name))?;
+            } else {
+                jar.file(name, &fs::read(entry.path())?)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Builds the jar from the layout's classes and resources, embedding the
+    /// generated POM under META-INF/maven like Maven does
+    pub fn package(&self) -> anyhow::Result<PathBuf> {
+        let coord = self.manifest.coordinate()?;
+        let classes = &self.layout.classes;
+        if !classes.exists() {
+            anyhow::bail!("nothing to package - {} doesn't exist, run gallade build first", classes.display());
+        }
+
+        let path = self.jar_path();
+        let mut jar = self.start_jar(&path, self.manifest.project.main_class.as_deref())?;
+
+        let maven_dir = format!("META-INF/maven/{}/{}", coord.namespace, coord.name);
+        jar.file(format!("{}/pom.xml", maven_dir), generate_pom(&self.manifest)?.as_bytes())?;
+        let properties = format!(
+            "groupId={}\nartifactId={}\nversion={}\n",
+            coord.namespace, coord.name, self.manifest.project.version
+        );
+        jar.file(format!("{}/pom.properties", maven_dir), properties.as_bytes())?;
+
+        self.add_tree(&mut jar, classes)?;
+        for resources in &self.layout.resources {
+            self.add_tree(&mut jar, resources)?;
+        }
+
+        jar.finish()?;
+        Ok(path)
+    }
+
+    /// Jar of everything under `dir`, e.g. the sources or javadoc jar that
+    /// gets published next to the main one
+    pub fn package_classified(&self, classifier: &str, dir: &Path) -> anyhow::Result<PathBuf> {
+        let path = self.target_path(Some(classifier), "jar");
+        let mut jar = self.start_jar(&path, None)?;
+        self.add_tree(&mut jar, dir)?;
+        jar.finish()?;
+        Ok(path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Read;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_package_jar() -> anyhow::Result<()> {
+        let temp = TempDir::new()?;
+        let classes = temp.path().join("target/classes/com/ourcorp");
+        fs::create_dir_all(&classes)?;
+        fs::write(classes.join("App.class"), "bytecode")?;
+        fs::create_dir_all(temp.path().join("src/main/resources"))?;
+        fs::write(temp.path().join("src/main/resources/app.properties"), "name=ledger")?;
+
+        let manifest: Manifest = toml::from_str(r#"
+            [project]
+            group = "com.ourcorp"
+            name = "ledger"
+            version = "1.0.0"
+            main_class = "com.ourcorp.App"
+        "#)?;
+
+        let jar = Packager::new(manifest.clone(), temp.path().to_path_buf(), Layout::standard(temp.path())).package()?;
+        assert!(jar.ends_with("target/ledger-1.0.0.jar"));
+
+        let mut archive = zip::ZipArchive::new(File::open(&jar)?)?;
+        let mut mf = String::new();
+        archive.by_name("META-INF/MANIFEST.MF")?.read_to_string(&mut mf)?;
+        assert!(mf.contains("Main-Class: com.ourcorp.App"));
+
+        assert!(archive.by_name("com/ourcorp/App.class").is_ok());
+        assert!(archive.by_name("app.properties").is_ok());
+        assert!(archive.by_name("META-INF/maven/com.ourcorp/ledger/pom.xml").is_ok());
+
+        // resources come from wherever the layout says, e.g. a pom.xml's <resources>
+        fs::create_dir_all(temp.path().join("conf"))?;
+        fs::write(temp.path().join("conf/ledger.xml"), "<ledger/>")?;
+        let layout = Layout { resources: vec![temp.path().join("conf")], ..Layout::standard(temp.path()) };
+        let jar = Packager::new(manifest, temp.path().to_path_buf(), layout).package()?;
+        let mut archive = zip::ZipArchive::new(File::open(&jar)?)?;
+        assert!(archive.by_name("ledger.xml").is_ok());
+        assert!(archive.by_name("app.properties").is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_package_overlapping_trees() -> anyhow::Result<()> {
+        let temp = TempDir::new()?;
+        let classes = temp.path().join("target/classes");
+        fs::create_dir_all(classes.join("com/ourcorp"))?;
+        fs::write(classes.join("com/ourcorp/App.class"), "bytecode")?;
+        fs::write(classes.join("com/ourcorp/messages.properties"), "compiled")?;
+        let resources = temp.path().join("src/main/resources");
+        fs::create_dir_all(resources.join("com/ourcorp"))?;
+        fs::write(resources.join("com/ourcorp/messages.properties"), "source")?;
+        fs::create_dir_all(resources.join("META-INF/services"))?;
+        fs::write(resources.join("META-INF/services/com.ourcorp.Plugin"), "com.ourcorp.App")?;
+
+        let manifest: Manifest = toml::from_str(r#"
+            [project]
+            group = "com.ourcorp"
+            name = "ledger"
+            version = "1.0.0"
+        "#)?;
+
+        // both trees have com/ and com/ourcorp/, and META-INF/ is already in the jar
+        let jar = Packager::new(manifest, temp.path().to_path_buf(), Layout::standard(temp.path())).package()?;
+        let mut archive = zip::ZipArchive::new(File::open(&jar)?)?;
+        assert!(archive.by_name("META-INF/services/com.ourcorp.Plugin").is_ok());
+        assert!(archive.by_name("com/ourcorp/App.class").is_ok());
+        let mut messages = String::new();
+        archive.by_name("com/ourcorp/messages.properties")?.read_to_string(&mut messages)?;
+        assert_eq!(messages, "compiled");
+
+        Ok(())
+    }
+}
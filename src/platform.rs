@@ -0,0 +1,97 @@
+use std::fmt;
+
+/// Placeholder for the host's native classifier, as set by os-maven-plugin
+pub const CLASSIFIER_VARIABLE: &str = "${os.detected.classifier}";
+
+/// An OS and CPU architecture, named the way os-maven-plugin names them so
+/// `${os.detected.classifier}` means the same thing in gallade and in Maven
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Platform {
+    pub name: String,
+    pub arch: String,
+}
+
+impl Platform {
+    pub fn new(name: &str, arch: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            arch: arch.to_string(),
+        }
+    }
+
+    /// The platform gallade is running on
+    pub fn current() -> Self {
+        let name = match std::env::consts::OS {
+            "macos" | "ios" => "osx",
+            other => other,
+        };
+        let arch = match std::env::consts::ARCH {
+            "x86" => "x86_32",
+            "aarch64" => "aarch_64",
+            "arm" => "arm_32",
+            "powerpc64" => "ppc_64",
+            "s390x" => "s390_64",
+            "loongarch64" => "loongarch_64",
+            other => other,
+        };
+        Self::new(name, arch)
+    }
+
+    /// e.g. `linux-x86_64` or `osx-aarch_64`
+    pub fn classifier(&self) -> String {
+        format!("{}-{}", self.name, self.arch)
+    }
+
+    /// The `os.detected.*` properties os-maven-plugin defines
+    pub fn properties(&self) -> Vec<(String, String)> {
+        vec![
+            ("os.detected.name".to_string(), self.name.clone()),
+            ("os.detected.arch".to_string(), self.arch.clone()),
+            ("os.detected.classifier".to_string(), self.classifier()),
+        ]
+    }
+
+    /// Replaces the `${os.detected.*}` variables in `value`
+    pub fn expand(&self, value: &str) -> String {
+        self.properties().iter().fold(value.to_string(), |value, (name, replacement)| {
+            value.replace(&format!("${{{}}}", name), replacement)
+        })
+    }
+
+    /// The reverse of `expand` for the classifier, so what gets recorded is
+    /// the same on every platform
+    pub fn template(&self, value: &str) -> String {
+        value.replace(&self.classifier(), CLASSIFIER_VARIABLE)
+    }
+}
+
+impl Default for Platform {
+    fn default() -> Self {
+        Self::current()
+    }
+}
+
+impl fmt::Display for Platform {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.classifier())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_expand_and_template() {
+        let platform = Platform::new("linux", "aarch_64");
+        let key = "io.netty:netty-transport-native-epoll:jar:${os.detected.classifier}";
+
+        let expanded = platform.expand(key);
+        assert_eq!(expanded, "io.netty:netty-transport-native-epoll:jar:linux-aarch_64");
+        assert_eq!(platform.template(&expanded), key);
+        assert_eq!(platform.expand("natives-${os.detected.name}"), "natives-linux");
+
+        // anything built for another platform is left as it is
+        assert_eq!(platform.template("protoc:exe:osx-x86_64"), "protoc:exe:osx-x86_64");
+    }
+}
@@ -0,0 +1,376 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+use serde::Serialize;
+
+use crate::coordinates::Coordinate;
+use crate::effective::Exclusion;
+use crate::download::parse_metadata_versions;
+use crate::lockfile::Lockfile;
+use crate::manifest::{DepSpec, Manifest};
+use crate::version::MavenVersion;
+
+const POM_NAMESPACE: &str = "http://maven.apache.org/POM/4.0.0";
+
+#[derive(Serialize)]
+#[serde(rename = "project")]
+struct PomProject {
+    #[serde(rename = "@xmlns")]
+    xmlns: &'static str,
+    #[serde(rename = "modelVersion")]
+    model_version: &'static str,
+    #[serde(rename = "groupId")]
+    group_id: String,
+    #[serde(rename = "artifactId")]
+    artifact_id: String,
+    version: String,
+    packaging: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    properties: Option<PomProperties>,
+    #[serde(rename = "dependencyManagement", skip_serializing_if = "Option::is_none")]
+    dependency_management: Option<DependencyManagement>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    dependencies: Option<PomDependencies>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    repositories: Option<PomRepositories>,
+}
+
+#[derive(Serialize)]
+struct DependencyManagement {
+    dependencies: PomDependencies,
+}
+
+#[derive(Serialize)]
+struct PomRepositories {
+    repository: Vec<PomRepository>,
+}
+
+#[derive(Serialize)]
+struct PomRepository {
+    id: String,
+    url: String,
+}
+
+#[derive(Serialize)]
+struct PomProperties {
+    #[serde(rename = "maven.compiler.release")]
+    release: String,
+}
+
+#[derive(Serialize)]
+struct PomDependencies {
+    dependency: Vec<PomDependency>,
+}
+
+#[derive(Serialize)]
+struct PomDependency {
+    #[serde(rename = "groupId")]
+    group_id: String,
+    #[serde(rename = "artifactId")]
+    artifact_id: String,
+    version: String,
+    #[serde(rename = "type", skip_serializing_if = "Option::is_none")]
+    kind: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    classifier: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    scope: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    optional: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    exclusions: Option<PomExclusions>,
+}
+
+#[derive(Serialize)]
+struct PomExclusions {
+    exclusion: Vec<PomExclusion>,
+}
+
+#[derive(Serialize)]
+struct PomExclusion {
+    #[serde(rename = "groupId")]
+    group_id: String,
+    #[serde(rename = "artifactId")]
+    artifact_id: String,
+}
+
+#[derive(Serialize)]
+#[serde(rename = "metadata")]
+struct Metadata {
+    #[serde(rename = "groupId")]
+    group_id: String,
+    #[serde(rename = "artifactId")]
+    artifact_id: String,
+    versioning: Versioning,
+}
+
+#[derive(Serialize)]
+struct Versioning {
+    latest: String,
+    /// Left out while there are only snapshots
+    #[serde(skip_serializing_if = "Option::is_none")]
+    release: Option<String>,
+    versions: Versions,
+    #[serde(rename = "lastUpdated")]
+    last_updated: String,
+}
+
+#[derive(Serialize)]
+struct Versions {
+    version: Vec<String>,
+}
+
+fn to_xml<T: Serialize>(value: &T) -> anyhow::Result<String> {
+    let mut xml = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    let mut serializer = quick_xml::se::Serializer::new(&mut xml);
+    serializer.indent(' ', 2);
+    value.serialize(serializer)?;
+    xml.push('\n');
+    Ok(xml)
+}
+
+/// POM describing the project and its dependencies, for Maven and Gradle consumers.
+/// This is what gets installed and published, so it never names our repositories.
+pub fn generate_pom(manifest: &Manifest) -> anyhow::Result<String> {
+    build_pom(manifest, None, false)
+}
+
+/// The pom.xml `gallade export pom` writes for building the project with Maven:
+/// every transitive version in `lockfile` pinned in `<dependencyManagement>` so
+/// Maven resolves what gallade did, and `[repositories]` in `<repositories>`
+pub fn export_pom(manifest: &Manifest, lockfile: Option<&Lockfile>) -> anyhow::Result<String> {
+    build_pom(manifest, lockfile, true)
+}
+
+fn build_pom(manifest: &Manifest, lockfile: Option<&Lockfile>, with_repositories: bool) -> anyhow::Result<String> {
+    let coord = manifest.coordinate()?;
+
+    let mut dependency = Vec::new();
+    for (key, spec) in &manifest.deps {
+        let dep = spec.coordinate(key)
+            .map_err(|e| anyhow::anyhow!("dependency '{}' in gallade.toml: {}", key, e))?;
+
+        let (version, scope, optional, exclusions) = match spec {
+            DepSpec::Simple(version) => (version.clone(), None, false, &[][..]),
+            DepSpec::Detailed { version, scope, optional, exclusions, .. } => {
+                (version.clone(), scope.clone(), *optional, exclusions.as_slice())
+            }
+        };
+
+        let exclusion: Vec<PomExclusion> = exclusions.iter()
+            .map(|pattern| {
+                let Exclusion { group_id, artifact_id } = Exclusion::parse(pattern);
+                PomExclusion { group_id, artifact_id }
+            })
+            .collect();
+
+        dependency.push(PomDependency {
+            kind: dep.extension.clone(),
+            classifier: dep.classifier.clone(),
+            group_id: dep.namespace,
+            artifact_id: dep.name,
+            version,
+            scope,
+            optional: optional.then_some(true),
+            exclusions: (!exclusion.is_empty()).then_some(PomExclusions { exclusion }),
+        });
+    }
+    // deps is a HashMap, keep the POM stable between runs
+    dependency.sort_by(|a, b| (&a.group_id, &a.artifact_id, &a.classifier).cmp(&(&b.group_id, &b.artifact_id, &b.classifier)));
+
+    let mut managed = Vec::new();
+    for (key, info) in lockfile.map(|lock| &lock.deps).into_iter().flatten() {
+        // a pin for one platform's native artifact would be wrong on every other one
+        if info.platform.is_some() {
+            continue;
+        }
+        let locked = Coordinate::parse_unversioned(key)?;
+        // direct dependencies already carry their version
+        if dependency.iter().any(|d| d.group_id == locked.namespace && d.artifact_id == locked.name && d.classifier == locked.classifier) {
+            continue;
+        }
+
+        managed.push(PomDependency {
+            kind: locked.extension.clone(),
+            classifier: locked.classifier.clone(),
+            group_id: locked.namespace,
+            artifact_id: locked.name,
+            version: info.version.clone(),
+            scope: None,
+            optional: None,
+            exclusions: None,
+        });
+    }
+    managed.sort_by(|a, b| (&a.group_id, &a.artifact_id).cmp(&(&b.group_id, &b.artifact_id)));
+
+    // credentials stay in gallade.toml, Maven reads those from settings.xml
+    let repository: Vec<PomRepository> = manifest.repositories.iter()
+        .filter(|_| with_repositories)
+        .map(|(name, repo)| PomRepository { id: name.clone(), url: repo.url.clone() })
+        .collect();
+
+    to_xml(&PomProject {
+        xmlns: POM_NAMESPACE,
+        model_version: "4.0.0",
+        group_id: coord.namespace,
+        artifact_id: coord.name,
+        version: manifest.project.version.clone(),
+        packaging: "jar",
+        properties: manifest.project.java_version.clone().map(|release| PomProperties { release }),
+        dependency_management: (!managed.is_empty()).then_some(DependencyManagement {
+            dependencies: PomDependencies { dependency: managed },
+        }),
+        dependencies: (!dependency.is_empty()).then_some(PomDependencies { dependency }),
+        repositories: (!repository.is_empty()).then_some(PomRepositories { repository }),
+    })
+}
+
+// yyyyMMddHHmmss in UTC, the format maven-metadata.xml uses
+fn last_updated(now: SystemTime) -> String {
+    let secs = now.duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0);
+    let (days, rem) = (secs / 86400, secs % 86400);
+
+    // civil-from-days, see http://howardhinnant.github.io/date_algorithms.html
+    let z = days as i64 + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z - era * 146097;
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+
+    format!(
+        "{:04}{:02}{:02}{:02}{:02}{:02}",
+        year, month, day, rem / 3600, rem % 3600 / 60, rem % 60
+    )
+}
+
+/// Artifact-level maven-metadata.xml with `version` added to whatever was there
+pub fn update_metadata(existing: Option<&str>, coord: &Coordinate, version: &str) -> anyhow::Result<String> {
+    // parse_metadata_versions hands them back newest first
+    let mut versions: Vec<String> = match existing {
+        Some(content) => parse_metadata_versions(content)?.into_iter().rev().collect(),
+        None => Vec::new(),
+    };
+    if !versions.iter().any(|v| v == version) {
+        versions.push(version.to_string());
+    }
+
+    // installing an older version or a snapshot doesn't make it the newest
+    let newest = |releases_only: bool| versions.iter()
+        .filter(|v| !releases_only || !v.ends_with("-SNAPSHOT"))
+        .filter_map(|v| Some((v.parse::<MavenVersion>().ok()?, v)))
+        .max_by(|a, b| a.0.cmp(&b.0))
+        .map(|(_, v)| v.clone());
+
+    to_xml(&Metadata {
+        group_id: coord.namespace.clone(),
+        artifact_id: coord.name.clone(),
+        versioning: Versioning {
+            latest: newest(false).unwrap_or_else(|| version.to_string()),
+            release: newest(true),
+            versions: Versions { version: versions },
+            last_updated: last_updated(SystemTime::now()),
+        },
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn test_generate_pom() -> anyhow::Result<()> {
+        let manifest: Manifest = toml::from_str(r#"
+            [project]
+            group = "com.ourcorp"
+            name = "ledger"
+            version = "1.2.0"
+            java_version = "17"
+
+            [deps]
+            "org.slf4j:slf4j-api" = "2.0.9"
+            "com.google.guava:guava" = { version = "31.1-jre", scope = "provided", optional = true, exclusions = ["com.google.code.findbugs:jsr305"] }
+        "#)?;
+
+        let pom = generate_pom(&manifest)?;
+        assert!(pom.contains("<groupId>com.ourcorp</groupId>"));
+        assert!(pom.contains("<artifactId>ledger</artifactId>"));
+        assert!(pom.contains("<maven.compiler.release>17</maven.compiler.release>"));
+        assert!(pom.contains("<scope>provided</scope>"));
+        assert!(pom.find("guava").unwrap() < pom.find("slf4j-api").unwrap());
+
+        // the generated POM has to be readable by our own resolver
+        let effective = crate::effective::ModelBuilder::new().build(&pom)?;
+        assert_eq!(effective.dependencies.len(), 2);
+        assert_eq!(effective.dependencies[0].exclusions[0].to_string(), "com.google.code.findbugs:jsr305");
+        assert_eq!(effective.compiler_release.as_deref(), Some("17"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_pom_pins_locked_versions() -> anyhow::Result<()> {
+        let manifest: Manifest = toml::from_str(r#"
+            [project]
+            group = "com.ourcorp"
+            name = "ledger"
+            version = "1.2.0"
+
+            [deps]
+            "com.google.guava:guava" = "31.1-jre"
+
+            [repositories]
+            internal = { url = "https://nexus.ourcorp.com/repository/releases", username = "ci", password = "secret" }
+        "#)?;
+        let lockfile: Lockfile = serde_json::from_str(r#"{
+            "version": 1,
+            "deps": {
+                "com.google.guava:guava": { "version": "31.1-jre", "repository": "central", "integrity": "sha256:00", "deps": [] },
+                "com.google.guava:failureaccess": { "version": "1.0.1", "repository": "central", "integrity": "sha256:00", "deps": [] }
+            }
+        }"#)?;
+
+        let pom = export_pom(&manifest, Some(&lockfile))?;
+        let management = &pom[pom.find("<dependencyManagement>").unwrap()..pom.find("</dependencyManagement>").unwrap()];
+        assert!(management.contains("<artifactId>failureaccess</artifactId>"));
+        assert!(!management.contains("<artifactId>guava</artifactId>"));
+
+        assert!(pom.contains("<id>internal</id>"));
+        assert!(!pom.contains("secret"));
+        // what's installed or published keeps internal URLs to itself
+        let published = generate_pom(&manifest)?;
+        assert!(!published.contains("dependencyManagement"));
+        assert!(!published.contains("<repositories>") && !published.contains("nexus.ourcorp.com"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_update_metadata() -> anyhow::Result<()> {
+        let coord = Coordinate::parse("com.ourcorp:ledger")?;
+
+        let first = update_metadata(None, &coord, "1.0.0")?;
+        let second = update_metadata(Some(&first), &coord, "1.1.0")?;
+        let again = update_metadata(Some(&second), &coord, "1.1.0")?;
+
+        assert_eq!(parse_metadata_versions(&again)?, vec!["1.1.0", "1.0.0"]);
+        assert!(again.contains("<release>1.1.0</release>"));
+
+        // an older release and a snapshot leave release alone, the snapshot is the latest
+        let older = update_metadata(Some(&again), &coord, "1.0.1")?;
+        let snapshot = update_metadata(Some(&older), &coord, "1.2.0-SNAPSHOT")?;
+        assert!(older.contains("<latest>1.1.0</latest>") && older.contains("<release>1.1.0</release>"));
+        assert!(snapshot.contains("<latest>1.2.0-SNAPSHOT</latest>") && snapshot.contains("<release>1.1.0</release>"));
+        assert!(!update_metadata(None, &coord, "0.1.0-SNAPSHOT")?.contains("<release>"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_last_updated() {
+        let time = UNIX_EPOCH + Duration::from_secs(1_709_217_000);
+        assert_eq!(last_updated(time), "20240229143000");
+    }
+}
@@ -1,8 +1,7 @@
-use std::fmt::{Debug, Formatter};
+use std::fmt::Debug;
 use std::fs;
 use std::path::{Path, PathBuf};
 use std::sync::Arc;
-use crate::manifest;
 use crate::manifest::Manifest;
 
 #[derive(Debug, Copy, Clone, PartialEq)]
@@ -12,6 +11,7 @@ pub enum BuildSystemType {
     Gallade
 }
 
+#[allow(dead_code)]
 pub trait BuildSystem: Debug {
     fn detect(&self, path: &Path) -> bool;
     fn get_build_file(&self, path: &Path) -> PathBuf;
@@ -34,7 +34,8 @@ impl BuildSystem for MavenBuildSystem {
     }
 
     fn get_dependencies(&self, path: &Path) -> anyhow::Result<Vec<String>> {
-        todo!("implement maven dependency parsing")
+        let pom = crate::import::local_effective_pom(&self.get_build_file(path))?;
+        Ok(pom.dependencies.iter().map(|dep| dep.coordinate().to_string()).collect())
     }
 
     fn system_type(&self) -> BuildSystemType {
@@ -51,11 +52,16 @@ impl BuildSystem for GradleBuildSystem {
     }
 
     fn get_build_file(&self, path: &Path) -> PathBuf {
-        path.join("build.gradle")
+        crate::gradle::build_file(path).unwrap_or_else(|| path.join("build.gradle"))
     }
 
+    // only what can be read statically, dependencies versioned by a platform are left out
     fn get_dependencies(&self, path: &Path) -> anyhow::Result<Vec<String>> {
-        todo!("implement gradle dependency parsing")
+        let project = crate::gradle::read_project(path)?;
+        Ok(project.dependencies.iter()
+            .filter(|dep| !dep.platform)
+            .filter_map(|dep| dep.version.as_ref().map(|version| format!("{}:{}", dep.key(), version)))
+            .collect())
     }
 
     fn system_type(&self) -> BuildSystemType {
@@ -79,14 +85,7 @@ impl BuildSystem for GalladeBuildSystem {
         let manifest = Manifest::load(&self.get_build_file(path))?;
         let mut deps = Vec::<String>::new();
         for (name, spec) in manifest.deps {
-            match spec {
-                manifest::DepSpec::Simple(version) => {
-                    deps.push(format!("{}:{}", name, version));
-                },
-                manifest::DepSpec::Detailed { version, .. } => {
-                    deps.push(format!("{}:{}", name, version));
-                }
-            }
+            deps.push(format!("{}:{}", spec.coordinate(&name)?, spec.version()));
         }
 
         Ok(deps)
@@ -110,7 +109,8 @@ impl Project {
 
         let build_systems: Vec<Arc<dyn BuildSystem>> = vec![
             Arc::new(GalladeBuildSystem),
-            Arc::new(MavenBuildSystem)
+            Arc::new(MavenBuildSystem),
+            Arc::new(GradleBuildSystem),
         ];
 
         loop {
@@ -135,6 +135,10 @@ impl Project {
         &self.root
     }
 
+    pub fn build_system(&self) -> BuildSystemType {
+        self.build_system.system_type()
+    }
+
     pub fn gallade_dir(&self) -> PathBuf {
         self.root.join(".gallade")
     }
@@ -162,6 +166,19 @@ mod tests {
         assert!(MavenBuildSystem::detect(&MavenBuildSystem{}, temp.path()));
     }
 
+    #[test]
+    fn test_gradle_detection() -> anyhow::Result<()> {
+        let temp = TempDir::new()?;
+        assert!(!GradleBuildSystem.detect(temp.path()));
+
+        fs::write(temp.path().join("build.gradle.kts"), "dependencies {\n    implementation(\"org.slf4j:slf4j-api:2.0.9\")\n}\n")?;
+        assert!(GradleBuildSystem.detect(temp.path()));
+        assert_eq!(GradleBuildSystem.get_build_file(temp.path()), temp.path().join("build.gradle.kts"));
+        assert_eq!(GradleBuildSystem.get_dependencies(temp.path())?, vec!["org.slf4j:slf4j-api:2.0.9"]);
+
+        Ok(())
+    }
+
     #[test]
     fn test_project_dirs() {
         let temp = TempDir::new().unwrap();
@@ -1,6 +1,7 @@
 use std::collections::HashSet;
 use crate::coordinates::Coordinate;
 use crate::lockfile::Lockfile;
+use crate::platform::Platform;
 
 #[derive(Debug)]
 pub struct DependencyPruner {
@@ -31,19 +32,67 @@ impl DependencyPruner {
         }
 
         if let Some(pkg_info) = lockfile.deps.get(&coord.to_string()) {
-            for dep_coord in &pkg_info.deps {
-                if let Ok(dep) = Coordinate::parse(dep_coord) {
+            for dep_coord in pkg_info.deps_on(&Platform::current()) {
+                if let Ok(dep) = Coordinate::parse_unversioned(&dep_coord) {
                     self.mark_tree(&dep, lockfile, for_removed);
                 }
             }
         }
     }
 
+    /// Marks everything gallade.lock still references as remaining
+    pub fn mark_locked(&mut self, lockfile: &Lockfile) {
+        for coord_str in lockfile.deps.keys() {
+            if let Ok(coord) = Coordinate::parse_unversioned(coord_str) {
+                self.mark_tree(&coord, lockfile, false);
+            }
+        }
+    }
+
+    pub fn is_retained(&self, coord: &Coordinate) -> bool {
+        self.marked_from_remaining.contains(coord)
+    }
+
+    /// Whether gallade.lock pins `version` of the module `coord`'s directory belongs to,
+    /// under any extension or classifier since they all share one version directory
+    pub fn locks_version(&self, lockfile: &Lockfile, coord: &Coordinate, version: &str) -> bool {
+        lockfile.deps.iter()
+            .filter(|(_, info)| info.version == version)
+            .filter_map(|(key, _)| Coordinate::parse_unversioned(key).ok())
+            .any(|locked| locked.namespace == coord.namespace && locked.name == coord.name && self.is_retained(&locked))
+    }
+
     pub fn get_removable(&self) -> HashSet<Coordinate> {
         self.marked_from_removed
             .iter()
-            .filter(|e| !self.marked_from_remaining.contains(&e))
+            .filter(|e| !self.marked_from_remaining.contains(e))
             .cloned()
             .collect()
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_locks_version() -> anyhow::Result<()> {
+        let lockfile: Lockfile = serde_json::from_str(r#"{"version": 1, "deps": {
+            "io.netty:netty-transport-native-epoll:jar:linux-x86_64": { "version": "4.1.100.Final", "repository": "central",
+                "integrity": "", "platform": "linux-x86_64", "deps": [] },
+            "com.ourcorp:console:war": { "version": "2.0", "repository": "central", "integrity": "", "deps": [] }
+        }}"#)?;
+        let mut pruner = DependencyPruner::new();
+        pruner.mark_locked(&lockfile);
+
+        // the cache only knows the directory, g:a/version, whatever sits in it
+        let epoll = Coordinate::parse_unversioned("io.netty:netty-transport-native-epoll")?;
+        assert!(pruner.locks_version(&lockfile, &epoll, "4.1.100.Final"));
+        assert!(!pruner.locks_version(&lockfile, &epoll, "4.1.99.Final"));
+        let console = Coordinate::parse_unversioned("com.ourcorp:console")?;
+        assert!(pruner.locks_version(&lockfile, &console, "2.0"));
+        assert!(!pruner.locks_version(&lockfile, &Coordinate::parse_unversioned("com.ourcorp:other")?, "2.0"));
+
+        Ok(())
+    }
+}
@@ -0,0 +1,274 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use reqwest::StatusCode;
+use sha2::Digest;
+
+use crate::coordinates::Coordinate;
+use crate::http::HttpClient;
+use crate::pom::update_metadata;
+
+/// Where `gallade publish` uploads to, a plain directory for file:// URLs and
+/// HTTP PUTs for everything else
+pub enum PublishTarget {
+    Directory(PathBuf),
+    Http { url: String, client: HttpClient },
+}
+
+impl PublishTarget {
+    pub fn new(url: &str, client: HttpClient) -> anyhow::Result<Self> {
+        if let Some(path) = url.strip_prefix("file://") {
+            return Ok(Self::Directory(PathBuf::from(path)));
+        }
+        if !url.starts_with("http://") && !url.starts_with("https://") {
+            anyhow::bail!("can't publish to '{}' - expected a file://, http:// or https:// url", url);
+        }
+
+        Ok(Self::Http {
+            url: url.trim_end_matches('/').to_string(),
+            client,
+        })
+    }
+
+    async fn put(&self, path: &str, body: Vec<u8>) -> anyhow::Result<()> {
+        match self {
+            Self::Directory(root) => {
+                let dest = root.join(path);
+                if let Some(parent) = dest.parent() {
+                    fs::create_dir_all(parent)?;
+                }
+                fs::write(dest, body)?;
+                Ok(())
+            }
+            Self::Http { url, client } => client.put(&format!("{}/{}", url, path), body).await,
+        }
+    }
+
+    async fn get(&self, path: &str) -> anyhow::Result<Option<String>> {
+        match self {
+            Self::Directory(root) => Ok(fs::read_to_string(root.join(path)).ok()),
+            Self::Http { url, client } => {
+                let url = format!("{}/{}", url, path);
+                let response = client.get(&url).await?;
+                match response.status() {
+                    StatusCode::NOT_FOUND => Ok(None),
+                    status if status.is_success() => Ok(Some(response.text().await?)),
+                    status => anyhow::bail!("failed to fetch {}: HTTP {}", url, status),
+                }
+            }
+        }
+    }
+}
+
+// The checksum files repositories expect next to every upload
+fn checksums(body: &[u8]) -> [(&'static str, String); 4] {
+    [
+        ("md5", hex::encode(<md5::Md5 as md5::Digest>::digest(body))),
+        ("sha1", hex::encode(<sha1::Sha1 as sha1::Digest>::digest(body))),
+        ("sha256", hex::encode(sha2::Sha256::digest(body))),
+        ("sha512", hex::encode(sha2::Sha512::digest(body))),
+    ]
+}
+
+/// Detached ASCII-armored signature made with the user's default gpg key
+pub fn sign(file: &Path) -> anyhow::Result<PathBuf> {
+    let status = Command::new("gpg")
+        .args(["--batch", "--yes", "--armor", "--detach-sign"])
+        .arg(file)
+        .status()
+        .map_err(|e| anyhow::anyhow!("failed to run gpg: {}", e))?;
+    if !status.success() {
+        anyhow::bail!("gpg failed to sign {}: {}", file.display(), status);
+    }
+
+    Ok(signature_path(file))
+}
+
+fn file_name(file: &Path) -> anyhow::Result<String> {
+    Ok(file.file_name()
+        .ok_or_else(|| anyhow::anyhow!("can't publish {}", file.display()))?
+        .to_string_lossy()
+        .into_owned())
+}
+
+fn signature_path(file: &Path) -> PathBuf {
+    let mut name = file.as_os_str().to_owned();
+    name.push(".asc");
+    PathBuf::from(name)
+}
+
+pub struct Publisher {
+    target: PublishTarget,
+}
+
+impl Publisher {
+    pub fn new(target: PublishTarget) -> Self {
+        Self { target }
+    }
+
+    async fn upload(&self, path: &str, body: Vec<u8>) -> anyhow::Result<()> {
+        let sums = checksums(&body);
+        self.target.put(path, body).await?;
+        for (extension, sum) in sums {
+            self.target.put(&format!("{}.{}", path, extension), sum.into_bytes()).await?;
+        }
+        Ok(())
+    }
+
+    /// Uploads every file into the version directory with its checksums, plus
+    /// the `signatures` made for them in this run, then adds the version to
+    /// maven-metadata.xml. Returns the repository paths that were uploaded.
+    pub async fn publish(
+        &self,
+        coord: &Coordinate,
+        version: &str,
+        files: &[PathBuf],
+        signatures: &[PathBuf],
+    ) -> anyhow::Result<Vec<String>> {
+        let dir = format!("{}/{}", coord.to_path(), version);
+        let mut uploaded = Vec::new();
+
+        for file in files {
+            let path = format!("{}/{}", dir, file_name(file)?);
+            self.upload(&path, fs::read(file)?).await?;
+            uploaded.push(path);
+        }
+        // signatures aren't checksummed, same as mvn deploy
+        for signature in signatures {
+            let path = format!("{}/{}", dir, file_name(signature)?);
+            self.target.put(&path, fs::read(signature)?).await?;
+            uploaded.push(path);
+        }
+
+        // only touch the metadata once every file made it, so a failed publish isn't advertised
+        let metadata_path = format!("{}/maven-metadata.xml", coord.to_path());
+        let existing = self.target.get(&metadata_path).await?;
+        let metadata = update_metadata(existing.as_deref(), coord, version)?;
+        self.upload(&metadata_path, metadata.into_bytes()).await?;
+        uploaded.push(metadata_path);
+
+        Ok(uploaded)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::download::parse_metadata_versions;
+    use crate::http::HttpConfig;
+    use tempfile::TempDir;
+
+    // The files to publish and a signature for the jar
+    fn artifacts(dir: &Path, version: &str) -> (Vec<PathBuf>, Vec<PathBuf>) {
+        let jar = dir.join(format!("ledger-{}.jar", version));
+        let sources = dir.join(format!("ledger-{}-sources.jar", version));
+        let pom = dir.join(format!("ledger-{}.pom", version));
+        fs::write(&jar, "jar").unwrap();
+        fs::write(&sources, "sources").unwrap();
+        fs::write(&pom, "<project/>").unwrap();
+        let signature = signature_path(&jar);
+        fs::write(&signature, "-----BEGIN PGP SIGNATURE-----").unwrap();
+        (vec![jar, sources, pom], vec![signature])
+    }
+
+    #[tokio::test]
+    async fn test_publish_to_directory() -> anyhow::Result<()> {
+        let temp = TempDir::new()?;
+        let repo = temp.path().join("repo");
+        let coord = Coordinate::parse("com.ourcorp:ledger")?;
+        let target = PublishTarget::new(&format!("file://{}", repo.display()), HttpConfig::default().build_client()?)?;
+        let publisher = Publisher::new(target);
+
+        // a signature left over on disk isn't published unless it was asked for
+        let (files, _) = artifacts(temp.path(), "1.0.0");
+        publisher.publish(&coord, "1.0.0", &files, &[]).await?;
+        let (files, signatures) = artifacts(temp.path(), "1.1.0");
+        publisher.publish(&coord, "1.1.0", &files, &signatures).await?;
+
+        assert!(!repo.join("com/ourcorp/ledger/1.0.0/ledger-1.0.0.jar.asc").exists());
+        let dir = repo.join("com/ourcorp/ledger/1.1.0");
+        assert_eq!(fs::read_to_string(dir.join("ledger-1.1.0.jar.sha1"))?, checksums(b"jar")[1].1);
+        for extension in ["md5", "sha256", "sha512"] {
+            assert!(dir.join(format!("ledger-1.1.0-sources.jar.{}", extension)).exists());
+        }
+        assert!(dir.join("ledger-1.1.0.jar.asc").exists());
+        assert!(!dir.join("ledger-1.1.0.pom.asc").exists());
+
+        let metadata = fs::read_to_string(repo.join("com/ourcorp/ledger/maven-metadata.xml"))?;
+        assert_eq!(parse_metadata_versions(&metadata)?, vec!["1.1.0", "1.0.0"]);
+        assert!(repo.join("com/ourcorp/ledger/maven-metadata.xml.sha1").exists());
+
+        Ok(())
+    }
+
+    // Answers every request with 404 for GETs and 201 for PUTs, recording "METHOD /path"
+    async fn serve() -> (String, tokio::task::JoinHandle<Vec<String>>) {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/releases", listener.local_addr().unwrap());
+
+        let handle = tokio::spawn(async move {
+            let mut requests = Vec::new();
+            while let Ok(Ok((mut socket, _))) =
+                tokio::time::timeout(std::time::Duration::from_millis(500), listener.accept()).await
+            {
+                let mut buf = Vec::new();
+                let mut chunk = [0u8; 4096];
+                // read the headers and however much body Content-Length promises
+                loop {
+                    let n = socket.read(&mut chunk).await.unwrap();
+                    buf.extend_from_slice(&chunk[..n]);
+                    let text = String::from_utf8_lossy(&buf).to_lowercase();
+                    if let Some(end) = text.find("\r\n\r\n") {
+                        let length = text.lines()
+                            .find_map(|line| line.strip_prefix("content-length:"))
+                            .map(|v| v.trim().parse::<usize>().unwrap())
+                            .unwrap_or(0);
+                        if n == 0 || buf.len() >= end + 4 + length {
+                            break;
+                        }
+                    }
+                }
+
+                let request = String::from_utf8_lossy(&buf).into_owned();
+                let line = request.lines().next().unwrap_or_default().to_string();
+                let response = if line.starts_with("PUT") {
+                    "HTTP/1.1 201 Created\r\nContent-Length: 0\r\nConnection: close\r\n\r\n"
+                } else {
+                    "HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\nConnection: close\r\n\r\n"
+                };
+                socket.write_all(response.as_bytes()).await.unwrap();
+                socket.shutdown().await.unwrap();
+
+                let mut parts = line.split_whitespace();
+                requests.push(format!("{} {}", parts.next().unwrap_or_default(), parts.next().unwrap_or_default()));
+            }
+            requests
+        });
+
+        (url, handle)
+    }
+
+    #[tokio::test]
+    async fn test_publish_over_http() -> anyhow::Result<()> {
+        let temp = TempDir::new()?;
+        let (url, server) = serve().await;
+        let coord = Coordinate::parse("com.ourcorp:ledger")?;
+
+        let client = HttpConfig { retries: Some(0), ..Default::default() }.build_client()?;
+        let publisher = Publisher::new(PublishTarget::new(&url, client)?);
+        let (files, signatures) = artifacts(temp.path(), "1.0.0");
+        publisher.publish(&coord, "1.0.0", &files, &signatures).await?;
+
+        let requests = server.await?;
+        let base = "/releases/com/ourcorp/ledger";
+        assert!(requests.contains(&format!("PUT {}/1.0.0/ledger-1.0.0.jar", base)));
+        assert!(requests.contains(&format!("PUT {}/1.0.0/ledger-1.0.0.jar.sha512", base)));
+        assert!(requests.contains(&format!("PUT {}/1.0.0/ledger-1.0.0.jar.asc", base)));
+        assert!(requests.contains(&format!("GET {}/maven-metadata.xml", base)));
+        assert_eq!(requests.last().unwrap(), &format!("PUT {}/maven-metadata.xml.sha512", base));
+
+        Ok(())
+    }
+}
@@ -1,11 +1,41 @@
 use std::path::{PathBuf, Path};
 use std::fs;
+use walkdir::WalkDir;
 
+use crate::cache::{sha256_file, ArtifactCache};
 use crate::coordinates::Coordinate;
+use crate::download::ArtifactSource;
+
+// Sidecar recording which repository a cached version was downloaded from
+const SOURCE_FILE: &str = "_gallade.source";
 
 #[derive(Clone, Debug)]
 pub struct Repository {
     root: PathBuf,
+    cache: Option<ArtifactCache>,
+}
+
+/// Splits a Maven layout path like `org/slf4j/slf4j-api/1.7.36/slf4j-api-1.7.36.jar`
+/// back into its coordinate and version
+pub fn coordinate_from_layout(relative: &Path) -> Option<(Coordinate, String)> {
+    let version_dir = relative.parent()?;
+    let artifact_dir = version_dir.parent()?;
+    let group_dir = artifact_dir.parent()?;
+
+    let namespace = group_dir.iter()
+        .map(|part| part.to_string_lossy())
+        .collect::<Vec<_>>()
+        .join(".");
+    if namespace.is_empty() {
+        return None;
+    }
+
+    let coord = Coordinate {
+        namespace,
+        name: artifact_dir.file_name()?.to_string_lossy().into_owned(),
+        ..Default::default()
+    };
+    Some((coord, version_dir.file_name()?.to_string_lossy().into_owned()))
 }
 
 #[derive(Debug, Clone, Copy)]
@@ -16,19 +46,39 @@ pub enum ArtifactKind {
 
 impl Repository {
     pub fn new(root: PathBuf) -> Self {
-        Self { root }
+        Self { root, cache: None }
     }
 
-    pub fn get_artifact_path(&self, coord: &Coordinate, version: &str, kind: ArtifactKind) -> PathBuf {
-        let extension = match kind {
-            ArtifactKind::Binary => "jar",
-            ArtifactKind::Metadata => "pom",
+    /// Shares artifacts with other projects through the user-level cache
+    pub fn with_cache(mut self, cache: ArtifactCache) -> Self {
+        self.cache = Some(cache);
+        self
+    }
+
+    fn relative_artifact_path(coord: &Coordinate, version: &str, kind: ArtifactKind) -> PathBuf {
+        let file = match kind {
+            ArtifactKind::Binary => coord.file_name(version),
+            // classified artifacts share the POM of the main one
+            ArtifactKind::Metadata => coord.pom_file_name(version),
         };
 
-        self.root
-            .join(coord.to_path())
+        Path::new(&coord.to_path())
             .join(version)
-            .join(format!("{}-{}.{}", coord.name, version, extension))
+            .join(file)
+    }
+
+    // One sidecar per file, since a version directory can hold several classifiers
+    fn source_path(&self, coord: &Coordinate, version: &str, kind: ArtifactKind) -> PathBuf {
+        let dir = self.root.join(coord.to_path()).join(version);
+        match kind {
+            ArtifactKind::Binary if coord.classifier.is_none() && coord.is_jar() => dir.join(SOURCE_FILE),
+            ArtifactKind::Binary => dir.join(format!("{}.{}", SOURCE_FILE, coord.file_name(version))),
+            ArtifactKind::Metadata => dir.join(format!("{}.{}", SOURCE_FILE, coord.pom_file_name(version))),
+        }
+    }
+
+    pub fn get_artifact_path(&self, coord: &Coordinate, version: &str, kind: ArtifactKind) -> PathBuf {
+        self.root.join(Self::relative_artifact_path(coord, version, kind))
     }
 
     pub async fn store_artifact(
@@ -37,6 +87,7 @@ impl Repository {
         version: &str,
         kind: ArtifactKind,
         content: impl AsRef<[u8]>,
+        source: &ArtifactSource,
     ) -> anyhow::Result<()> {
         let path = self.get_artifact_path(coord, version, kind);
 
@@ -44,10 +95,69 @@ impl Repository {
             fs::create_dir_all(parent)?;
         }
 
+        // never write through an existing file, it may be a hardlink into the shared cache
+        if path.exists() {
+            fs::remove_file(&path)?;
+        }
+
         fs::write(path, content)?;
+        self.store_source(coord, version, kind, source)?;
+        self.cache_artifact(coord, version, kind, Some(source))
+    }
+
+    /// Copies a file that is already in the project repository into the shared cache
+    pub fn cache_artifact(
+        &self,
+        coord: &Coordinate,
+        version: &str,
+        kind: ArtifactKind,
+        source: Option<&ArtifactSource>,
+    ) -> anyhow::Result<()> {
+        if let Some(cache) = &self.cache {
+            let relative = Self::relative_artifact_path(coord, version, kind);
+            cache.insert(&relative, &self.root.join(&relative), source)?;
+        }
         Ok(())
     }
 
+    /// Makes the file available in the project repository, linking it from the
+    /// shared cache when needed, but only a copy whose recorded download source
+    /// passes `allowed`. Returns that source.
+    pub fn restore(
+        &self,
+        coord: &Coordinate,
+        version: &str,
+        kind: ArtifactKind,
+        allowed: impl Fn(&ArtifactSource) -> bool,
+    ) -> anyhow::Result<Option<ArtifactSource>> {
+        if self.has_artifact(coord, version, kind) {
+            if let Some(source) = self.load_source(coord, version, kind).filter(|s| allowed(s)) {
+                return Ok(Some(source));
+            }
+        }
+
+        let Some(cache) = &self.cache else {
+            return Ok(None);
+        };
+
+        let relative = Self::relative_artifact_path(coord, version, kind);
+        match cache.lookup(&relative) {
+            Some(entry) if entry.source.as_ref().is_some_and(&allowed) => {
+                cache.link(&relative, &entry, &self.root.join(&relative))?;
+                let source = entry.source.unwrap();
+                self.store_source(coord, version, kind, &source)?;
+                Ok(Some(source))
+            }
+            _ => Ok(None),
+        }
+    }
+
+    /// `sha256:<hex>` of the jar as stored in the project repository
+    pub fn integrity(&self, coord: &Coordinate, version: &str) -> anyhow::Result<String> {
+        let path = self.get_artifact_path(coord, version, ArtifactKind::Binary);
+        Ok(format!("sha256:{}", sha256_file(&path)?))
+    }
+
     pub fn has_artifact(&self, coord: &Coordinate, version: &str, kind: ArtifactKind) -> bool {
         self.get_artifact_path(coord, version, kind).exists()
     }
@@ -62,6 +172,16 @@ impl Repository {
         Ok(fs::read(path)?)
     }
 
+    pub fn store_source(&self, coord: &Coordinate, version: &str, kind: ArtifactKind, source: &ArtifactSource) -> anyhow::Result<()> {
+        fs::write(self.source_path(coord, version, kind), serde_json::to_string(source)?)?;
+        Ok(())
+    }
+
+    pub fn load_source(&self, coord: &Coordinate, version: &str, kind: ArtifactKind) -> Option<ArtifactSource> {
+        let content = fs::read_to_string(self.source_path(coord, version, kind)).ok()?;
+        serde_json::from_str(&content).ok()
+    }
+
     // Helper function to check if a directory is empty
     fn is_dir_empty(path: &Path) -> anyhow::Result<bool> {
         Ok(fs::read_dir(path)?.next().is_none())
@@ -104,6 +224,40 @@ impl Repository {
         Ok(())
     }
 
+    /// Every coordinate and version that has a jar or pom in this repository
+    pub fn list_artifacts(&self) -> anyhow::Result<Vec<(Coordinate, String)>> {
+        let mut artifacts = Vec::new();
+
+        for entry in WalkDir::new(&self.root).into_iter().filter_map(|e| e.ok()) {
+            let path = entry.path();
+            let is_artifact = matches!(path.extension().and_then(|e| e.to_str()), Some("jar" | "pom"));
+            if !entry.file_type().is_file() || !is_artifact {
+                continue;
+            }
+
+            if let Some(found) = path.strip_prefix(&self.root).ok().and_then(coordinate_from_layout) {
+                if !artifacts.contains(&found) {
+                    artifacts.push(found);
+                }
+            }
+        }
+
+        artifacts.sort_by_key(|(coord, version)| format!("{}:{}", coord, version));
+        Ok(artifacts)
+    }
+
+    /// Bytes on disk for everything stored under one version, not counting source sidecars
+    pub fn artifact_size(&self, coord: &Coordinate, version: &str) -> u64 {
+        WalkDir::new(self.root.join(coord.to_path()).join(version))
+            .into_iter()
+            .filter_map(|e| e.ok())
+            .filter(|e| !e.file_name().to_string_lossy().starts_with(SOURCE_FILE))
+            .filter_map(|e| e.metadata().ok())
+            .filter(|m| m.is_file())
+            .map(|m| m.len())
+            .sum()
+    }
+
     pub fn list_versions(&self, coord: &Coordinate) -> anyhow::Result<Vec<String>> {
         let coord_dir = self.root.join(coord.to_path());
 
@@ -112,12 +266,10 @@ impl Repository {
         }
 
         let mut versions = vec![];
-        for entry in fs::read_dir(coord_dir)? {
-            if let Ok(entry) = entry {
-                if entry.path().is_dir() {
-                    if let Some(version) = entry.file_name().to_str() {
-                        versions.push(version.to_string());
-                    }
+        for entry in fs::read_dir(coord_dir)?.flatten() {
+            if entry.path().is_dir() {
+                if let Some(version) = entry.file_name().to_str() {
+                    versions.push(version.to_string());
                 }
             }
         }
@@ -130,7 +282,6 @@ impl Repository {
 mod tests {
     use super::*;
     use tempfile::TempDir;
-    use std::fs::File;
 
     #[test]
     fn test_artifact_paths() {
@@ -151,6 +302,16 @@ mod tests {
             pom_path.strip_prefix(temp.path()).unwrap(),
             Path::new("com/google/guava/guava/31.1-jre/guava-31.1-jre.pom")
         );
+
+        // classified artifacts live next to the main one and share its POM
+        let protoc = Coordinate::parse_unversioned("com.google.protobuf:protoc:exe:linux-x86_64").unwrap();
+        let exe_path = repo.get_artifact_path(&protoc, "3.25.1", ArtifactKind::Binary);
+        assert_eq!(
+            exe_path.strip_prefix(temp.path()).unwrap(),
+            Path::new("com/google/protobuf/protoc/3.25.1/protoc-3.25.1-linux-x86_64.exe")
+        );
+        let pom_path = repo.get_artifact_path(&protoc, "3.25.1", ArtifactKind::Metadata);
+        assert!(pom_path.ends_with("protoc-3.25.1.pom"));
     }
 
     #[test]
@@ -162,11 +323,13 @@ mod tests {
         let version = "1.7.36";
         let content = b"test content";
 
+        let source = ArtifactSource { repository: "central".to_string(), url: String::new() };
         tokio_test::block_on(repo.store_artifact(
             &coord,
             version,
             ArtifactKind::Binary,
-            content
+            content,
+            &source,
         ))?;
 
         assert!(repo.has_artifact(&coord, version, ArtifactKind::Binary));
@@ -178,6 +341,64 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_shared_cache_between_projects() -> anyhow::Result<()> {
+        let temp = TempDir::new()?;
+        let cache = ArtifactCache::new(temp.path().join("cache"));
+        let first = Repository::new(temp.path().join("a")).with_cache(cache.clone());
+        let second = Repository::new(temp.path().join("b")).with_cache(cache);
+
+        let coord = Coordinate::parse("org.slf4j:slf4j-api").unwrap();
+        let source = ArtifactSource {
+            repository: "central".to_string(),
+            url: "https://repo.maven.apache.org/maven2/org/slf4j/slf4j-api/1.7.36/slf4j-api-1.7.36.jar".to_string(),
+        };
+
+        let jar = first.get_artifact_path(&coord, "1.7.36", ArtifactKind::Binary);
+        fs::create_dir_all(jar.parent().unwrap())?;
+        fs::write(&jar, "jar")?;
+        first.store_source(&coord, "1.7.36", ArtifactKind::Binary, &source)?;
+        first.cache_artifact(&coord, "1.7.36", ArtifactKind::Binary, Some(&source))?;
+        tokio_test::block_on(first.store_artifact(&coord, "1.7.36", ArtifactKind::Metadata, "<project/>", &source))?;
+
+        // a disallowed source is not linked in, the POM included
+        let internal = |s: &ArtifactSource| s.repository == "internal";
+        assert!(second.restore(&coord, "1.7.36", ArtifactKind::Binary, internal)?.is_none());
+        assert!(!second.has_artifact(&coord, "1.7.36", ArtifactKind::Binary));
+        assert!(second.restore(&coord, "1.7.36", ArtifactKind::Metadata, internal)?.is_none());
+
+        assert_eq!(second.restore(&coord, "1.7.36", ArtifactKind::Binary, |_| true)?, Some(source.clone()));
+        assert_eq!(second.load_artifact(&coord, "1.7.36", ArtifactKind::Binary)?, b"jar");
+        assert_eq!(second.load_source(&coord, "1.7.36", ArtifactKind::Binary), Some(source.clone()));
+        assert_eq!(second.restore(&coord, "1.7.36", ArtifactKind::Metadata, |_| true)?, Some(source));
+        // already linked, but that doesn't make it allowed
+        assert!(second.restore(&coord, "1.7.36", ArtifactKind::Metadata, internal)?.is_none());
+        assert_eq!(second.integrity(&coord, "1.7.36")?, first.integrity(&coord, "1.7.36")?);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_list_artifacts() -> anyhow::Result<()> {
+        let temp = TempDir::new()?;
+        let repo = Repository::new(temp.path().to_path_buf());
+
+        let guava = Coordinate::parse("com.google.guava:guava").unwrap();
+        let slf4j = Coordinate::parse("org.slf4j:slf4j-api").unwrap();
+        let source = ArtifactSource { repository: "central".to_string(), url: String::new() };
+        tokio_test::block_on(repo.store_artifact(&guava, "31.1-jre", ArtifactKind::Binary, "12345", &source))?;
+        tokio_test::block_on(repo.store_artifact(&guava, "31.1-jre", ArtifactKind::Metadata, "123", &source))?;
+        tokio_test::block_on(repo.store_artifact(&slf4j, "1.7.36", ArtifactKind::Metadata, "1", &source))?;
+
+        assert_eq!(repo.list_artifacts()?, vec![
+            (guava.clone(), "31.1-jre".to_string()),
+            (slf4j, "1.7.36".to_string()),
+        ]);
+        assert_eq!(repo.artifact_size(&guava, "31.1-jre"), 8);
+
+        Ok(())
+    }
+
     #[test]
     fn test_directory_cleanup() -> anyhow::Result<()> {
         let temp = TempDir::new()?;
@@ -1,11 +1,17 @@
 use std::collections::{HashMap, HashSet, VecDeque};
+use std::fmt::Write;
+use std::fs::{self, File};
 use std::path::Path;
-use log::__private_api::loc;
-use serde::Deserialize;
+
+use anyhow::Context;
 
 use crate::coordinates::Coordinate;
-use crate::download::RepositoryManager;
+use crate::download::{ArtifactSource, RepositoryManager};
+use crate::effective::{EffectivePom, Exclusion, ModelBuilder, ModelError, PomRepository, ProfileActivation};
 use crate::lockfile::Lockfile;
+use crate::manifest::Manifest;
+use crate::module::{ModuleMetadata, ModuleParser, GRADLE_METADATA_MARKER};
+use crate::platform::Platform;
 use crate::prune::DependencyPruner;
 use crate::repository::{Repository, ArtifactKind};
 use crate::version::{MavenVersion, VersionReq};
@@ -15,7 +21,10 @@ pub struct DependencyRequest {
     pub coordinate: Coordinate,
     pub version_req: VersionReq,
     pub scope: Option<String>,
-    pub depth: usize
+    pub exclusions: Vec<Exclusion>,
+    pub depth: usize,
+    /// Asks for this platform's native classifier through `${os.detected.classifier}`
+    pub native: bool,
 }
 
 
@@ -24,6 +33,18 @@ pub struct DependencyGraph {
     pub resolved: HashMap<Coordinate, MavenVersion>,
     requirements: HashMap<Coordinate, Vec<(VersionReq, usize)>>,
     pub edges: HashMap<Coordinate, HashSet<Coordinate>>,
+    pub sources: HashMap<Coordinate, ArtifactSource>,
+    /// Resolved modules with no file of their own, e.g. one whose variant is
+    /// available at another module
+    pub metadata_only: HashSet<Coordinate>,
+    /// Modules whose POM says they moved, to where
+    pub relocations: HashMap<Coordinate, Coordinate>,
+    /// What a metadata-only module's lock entry is hashed from, its POM or `.module`
+    pub metadata_files: HashMap<Coordinate, Coordinate>,
+    /// Modules at the version `[overrides]` forced
+    pub overridden: HashSet<Coordinate>,
+    /// Modules asked for with this platform's native classifier
+    pub native: HashSet<Coordinate>,
 }
 
 #[derive(Debug, Default)]
@@ -32,18 +53,20 @@ pub struct ReverseDependencyGraph {
 }
 
 impl ReverseDependencyGraph {
-    pub fn from_lockfile(lockfile: &Lockfile) -> Self {
+    pub fn from_lockfile(lockfile: &Lockfile) -> anyhow::Result<Self> {
         let mut graph = Self::default();
 
         for (coord, info) in &lockfile.deps {
-            let is_depended_by = Coordinate::parse(coord).unwrap();
-            for dep in &info.deps {
-                let dependency = Coordinate::parse(dep).unwrap();
+            let is_depended_by = Coordinate::parse_unversioned(coord)
+                .with_context(|| format!("bad entry {} in gallade.lock", coord))?;
+            for dep in info.deps_on(&Platform::current()) {
+                let dependency = Coordinate::parse_unversioned(&dep)
+                    .with_context(|| format!("bad dependency {} of {} in gallade.lock", dep, coord))?;
                 graph.add_edge(dependency, is_depended_by.clone());
             }
         }
 
-        graph
+        Ok(graph)
     }
 
     fn add_edge(&mut self, target: Coordinate, dependent: Coordinate) {
@@ -53,6 +76,7 @@ impl ReverseDependencyGraph {
             .insert(dependent);
     }
 
+    #[allow(dead_code)]
     pub fn has_dependents(&self, coord: &Coordinate) -> bool {
         self.dependents
             .get(coord)
@@ -87,79 +111,60 @@ impl DependencyGraph {
             .insert(to.clone());
     }
 
-    pub fn check_version_compatibility(&self, coord: &Coordinate, version: &MavenVersion) -> bool {
-        if let Some(reqs) = self.requirements.get(coord) {
-            let mut sorted_reqs = reqs.clone();
-            sorted_reqs.sort_by_key(|(_, depth)| *depth);
-
-            let (nearest_req, _) = &sorted_reqs[0];
+    pub fn add_resolution(&mut self, coord: &Coordinate, version: MavenVersion) {
+        self.resolved.insert(coord.clone(), version);
+    }
 
-            nearest_req.matches(version)
-        } else {
-            true
-        }
+    pub fn add_source(&mut self, coord: &Coordinate, source: ArtifactSource) {
+        self.sources.insert(coord.clone(), source);
     }
 
-    pub fn add_resolution(&mut self, coord: &Coordinate, version: MavenVersion) {
-        self.resolved.insert(coord.clone(), version);
+    /// Every resolved artifact, in a stable order
+    pub fn artifacts(&self) -> Vec<(Coordinate, String)> {
+        let mut artifacts: Vec<_> = self.resolved.iter()
+            .filter(|(coord, _)| !self.metadata_only.contains(*coord))
+            .map(|(coord, version)| (coord.clone(), version.to_string()))
+            .collect();
+        artifacts.sort_by_key(|(coord, _)| coord.to_string());
+        artifacts
     }
 }
 
-pub trait MetadataParser {
-    fn parse_dependencies(&self, content: &str) -> anyhow::Result<Vec<DependencyRequest>>;
+pub trait MetadataParser: Send + Sync {
+    type Metadata;
+
+    /// Dependencies `metadata` brings onto the classpath of whoever depends on it
+    fn parse_dependencies(&self, metadata: &Self::Metadata) -> anyhow::Result<Vec<DependencyRequest>>;
 }
 
 pub struct PomParser;
 
 impl MetadataParser for PomParser {
-    fn parse_dependencies(&self, content: &str) -> anyhow::Result<Vec<DependencyRequest>> {
-        #[derive(Debug, Deserialize)]
-        struct Project {
-            #[serde(default)]
-            dependencies: Dependencies,
-        }
+    type Metadata = EffectivePom;
 
-        #[derive(Debug, Default, Deserialize)]
-        struct Dependencies {
-            #[serde(default)]
-            dependency: Vec<Dependency>,
-        }
-
-        #[derive(Debug, Deserialize)]
-        struct Dependency {
-            #[serde(rename = "groupId")]
-            group_id: String,
-            #[serde(rename = "artifactId")]
-            artifact_id: String,
-            version: Option<String>,
-            #[serde(default)]
-            scope: Option<String>,
-        }
-
-        let project: Project = quick_xml::de::from_str(content)?;
+    fn parse_dependencies(&self, pom: &EffectivePom) -> anyhow::Result<Vec<DependencyRequest>> {
         let mut requests = Vec::new();
 
-        for dep in project.dependencies.dependency {
-            if dep.scope.as_deref() == Some("test") {
+        for dep in &pom.dependencies {
+            // none of these are transitive
+            if dep.optional || matches!(dep.scope.as_deref(), Some("test" | "provided" | "system")) {
                 continue;
             }
 
-            let coord = Coordinate {
-                namespace: dep.group_id,
-                name: dep.artifact_id,
-                version: None,
-            };
+            let coord = Coordinate { version: None, ..dep.coordinate() };
 
-            let version_req = match dep.version {
-                Some(v) => VersionReq::parse(&v)?,
+            let version_req = match &dep.version {
+                Some(v) => VersionReq::parse(v)?,
                 None => VersionReq::Latest,
             };
 
             requests.push(DependencyRequest {
                 coordinate: coord,
                 version_req,
-                scope: dep.scope,
-                depth: 0
+                scope: dep.scope.clone(),
+                exclusions: dep.exclusions.clone(),
+                depth: 0,
+                native: dep.native,
             });
         }
 
@@ -167,10 +172,43 @@ impl MetadataParser for PomParser {
     }
 }
 
+// What a module version puts on the classpath of whoever depends on it
+#[derive(Debug, Clone, PartialEq)]
+enum Artifact {
+    Jar,
+    /// An Android library, whose classes.jar is what goes on the classpath
+    Aar,
+    /// The file of the Gradle variant picked, when it isn't the module's jar
+    Variant(Coordinate),
+    /// Only metadata, e.g. a BOM, an aggregator POM or a relocation stub
+    Nothing,
+}
+
+// What resolution needs to know about one module version
+#[derive(Clone)]
+struct Component {
+    dependencies: Vec<DependencyRequest>,
+    artifact: Artifact,
+    /// The metadata file and where it came from, recorded for modules without an artifact
+    metadata_source: Option<(Coordinate, ArtifactSource)>,
+    relocated: Option<Coordinate>,
+    /// Declared in the POM, for whatever is below this module
+    repositories: Vec<PomRepository>,
+}
+
 pub struct DependencyResolver {
     repo: Repository,
     manager: RepositoryManager,
-    parser: Box<dyn MetadataParser>,
+    parser: Box<dyn MetadataParser<Metadata = EffectivePom>>,
+    modules: ModuleParser,
+    platform: Platform,
+    activation: ProfileActivation,
+    overrides: HashMap<Coordinate, MavenVersion>,
+    constraints: HashMap<Coordinate, VersionReq>,
+    exclusions: Vec<Exclusion>,
+    direct_exclusions: HashMap<Coordinate, Vec<Exclusion>>,
+    /// Direct dependencies gallade.toml gives `${os.detected.classifier}`
+    native_direct: HashSet<Coordinate>,
 }
 
 impl DependencyResolver {
@@ -179,69 +217,453 @@ impl DependencyResolver {
             repo,
             manager,
             parser: Box::new(PomParser),
+            modules: ModuleParser::new(None),
+            platform: Platform::current(),
+            activation: ProfileActivation::default(),
+            overrides: HashMap::new(),
+            constraints: HashMap::new(),
+            exclusions: Vec::new(),
+            direct_exclusions: HashMap::new(),
+            native_direct: HashSet::new(),
         }
     }
 
-    pub async fn resolve(&self, root_coord: &Coordinate, version: &str) -> anyhow::Result<DependencyGraph> {
-        let mut graph = DependencyGraph::new();
-        let mut queue = VecDeque::new();
-        let mut seen = HashSet::new();
+    /// Picks the Gradle module variants built for `java_version` or older, and
+    /// the POM profiles activated by it
+    pub fn with_java_version(mut self, java_version: Option<&str>) -> Self {
+        self.modules = ModuleParser::new(java_version);
+        self.activation.java_version = java_version.map(String::from);
+        self
+    }
 
-        let root_version = version.parse::<MavenVersion>()?;
-        queue.push_back((root_coord.clone(), root_version.clone(), 0));
+    /// Profiles turned on or off with -P, which only apply to the project's own POMs
+    pub fn with_profiles(mut self, profiles: Vec<String>) -> Self {
+        self.activation.profiles = profiles;
+        self
+    }
 
-        while let Some((coord, version, depth)) = queue.pop_front() {
-            let key = format!("{}:{:?}", coord, version);
-            if seen.contains(&key) {
-                continue;
+    /// Properties set with -D, for every POM
+    pub fn with_properties(mut self, properties: HashMap<String, String>) -> Self {
+        self.activation.properties = properties;
+        self
+    }
+
+    /// Versions modules are forced to wherever they turn up, whatever asks for them
+    pub fn with_overrides(mut self, overrides: HashMap<Coordinate, MavenVersion>) -> Self {
+        self.overrides = overrides;
+        self
+    }
+
+    /// Requirements on modules that only apply when something else pulls them in
+    pub fn with_constraints(mut self, constraints: HashMap<Coordinate, VersionReq>) -> Self {
+        self.constraints = constraints;
+        self
+    }
+
+    /// Modules left out below every direct dependency, and below just the one that lists them
+    pub fn with_exclusions(mut self, exclusions: Vec<Exclusion>, direct_exclusions: HashMap<Coordinate, Vec<Exclusion>>) -> Self {
+        self.exclusions = exclusions;
+        self.direct_exclusions = direct_exclusions;
+        self
+    }
+
+    /// Everything gallade.toml has to say about resolution: the java version,
+    /// overrides, constraints and exclusions
+    pub fn with_manifest(self, manifest: &Manifest) -> anyhow::Result<Self> {
+        let direct_exclusions = manifest.deps.iter()
+            .map(|(name, spec)| Ok((spec.coordinate(name)?, spec.exclusions())))
+            .collect::<anyhow::Result<_>>()?;
+        let native_direct = manifest.deps.iter()
+            .filter(|(name, spec)| spec.is_native(name))
+            .map(|(name, spec)| spec.coordinate(name))
+            .collect::<anyhow::Result<_>>()?;
+        Ok(Self {
+            native_direct,
+            ..self
+                .with_java_version(manifest.project.java_version.as_deref())
+                .with_overrides(manifest.parsed_overrides()?)
+                .with_constraints(manifest.parsed_constraints()?)
+                .with_exclusions(manifest.parsed_exclusions(), direct_exclusions)
+        })
+    }
+
+    // what's left out of a direct dependency's subtree
+    fn root_exclusions(&self, coord: &Coordinate) -> Vec<Exclusion> {
+        let direct = self.direct_exclusions.get(&Coordinate { version: None, ..coord.clone() });
+        self.exclusions.iter().chain(direct.into_iter().flatten()).cloned().collect()
+    }
+
+    /// A model builder for the project rooted at `root`, rather than a dependency
+    pub fn project_model(&self, root: &Path) -> ModelBuilder {
+        ModelBuilder::new()
+            .with_platform(self.platform.clone())
+            .with_activation(ProfileActivation { basedir: Some(root.to_path_buf()), ..self.activation.clone() })
+    }
+
+    /// Resolves native classifiers for `platform` instead of this machine
+    #[cfg(test)]
+    pub fn with_platform(mut self, platform: Platform) -> Self {
+        self.platform = platform;
+        self
+    }
+
+    // Picks one version of every module reachable from `direct` and fetches them
+    async fn solve(&self, direct: &[(Coordinate, String)], preferred: &HashMap<Coordinate, MavenVersion>) -> anyhow::Result<DependencyGraph> {
+        let mut solver = Solver::new(self, direct, preferred.clone())?;
+        solver.run().await?;
+
+        let mut graph = DependencyGraph::new();
+        graph.native = solver.demands()
+            .filter(|d| d.request.native)
+            .map(|d| d.request.coordinate.clone())
+            .collect();
+        for decision in &solver.decisions {
+            let coord = &decision.coordinate;
+            let version = decision.version.to_string();
+            graph.add_resolution(coord, decision.version.clone());
+            if self.overrides.contains_key(coord) {
+                graph.overridden.insert(coord.clone());
+            }
+            for demand in &decision.demands {
+                graph.add_edge(coord, &demand.request.coordinate);
+                graph.add_requirement(&demand.request.coordinate, demand.request.version_req.clone(), demand.request.depth);
             }
-            seen.insert(key);
 
-            if !self.repo.has_artifact(&coord, &version.to_string(), ArtifactKind::Binary) {
-                let jar = self.manager.download_jar(&coord, &version.to_string()).await?;
-                self.repo.store_artifact(&coord, &version.to_string(), ArtifactKind::Binary, jar).await?;
+            let component = &decision.component;
+            match &component.artifact {
+                Artifact::Jar => graph.add_source(coord, self.fetch(&decision.manager, coord, &version).await?),
+                Artifact::Aar => graph.add_source(coord, self.fetch_aar(&decision.manager, coord, &version).await?),
+                Artifact::Variant(file) => graph.add_source(coord, self.fetch_variant(&decision.manager, coord, file, &version).await?),
+                Artifact::Nothing => {
+                    graph.metadata_only.insert(coord.clone());
+                    if let Some((file, source)) = &component.metadata_source {
+                        graph.add_source(coord, source.clone());
+                        graph.metadata_files.insert(coord.clone(), file.clone());
+                    }
+                }
+            }
+            if let Some(target) = &component.relocated {
+                graph.relocations.insert(coord.clone(), target.clone());
             }
+        }
 
-            let metadata = if self.repo.has_artifact(&coord, &version.to_string(), ArtifactKind::Metadata) {
-                String::from_utf8(self.repo.load_artifact(&coord, &version.to_string(), ArtifactKind::Metadata)?)?
-            } else {
-                let metadata = self.manager.download_metadata(&coord, &version.to_string()).await?;
-                self.repo.store_artifact(&coord, &version.to_string(), ArtifactKind::Metadata, metadata.as_bytes()).await?;
-                metadata
-            };
+        Ok(graph)
+    }
+
+    /// The version resolving the project again would pick for each module, keeping
+    /// only the `preferred` ones, without fetching anything but metadata
+    pub async fn plan(&self, direct: &[(Coordinate, String)], preferred: &HashMap<Coordinate, MavenVersion>) -> anyhow::Result<HashMap<Coordinate, MavenVersion>> {
+        let mut solver = Solver::new(self, direct, preferred.clone())?;
+        solver.run().await?;
+        Ok(solver.decisions.into_iter().map(|d| (d.coordinate, d.version)).collect())
+    }
+
+    /// What `coord` at `version` asks for, as its POM or Gradle module says
+    pub async fn dependencies_of(&self, coord: &Coordinate, version: &str) -> anyhow::Result<Vec<DependencyRequest>> {
+        Ok(self.component(&self.manager, coord, version).await?.dependencies)
+    }
+
+    // Puts the file in the project repository and returns where it came from. A
+    // cached copy is only reused when we know it came from a repository the
+    // routing rules still allow for its group.
+    async fn fetch(&self, manager: &RepositoryManager, coord: &Coordinate, version: &str) -> anyhow::Result<ArtifactSource> {
+        // a copy of another file put where the jar goes, e.g. a variant's, isn't the jar
+        let file = coord.file_name(version);
+        let cached_source = self.repo.restore(coord, version, ArtifactKind::Binary, |source| {
+            source.url.ends_with(&file) && manager.routing().is_allowed(&coord.namespace, &source.repository)
+        })?;
+        if let Some(source) = cached_source {
+            return Ok(source);
+        }
+
+        let path = self.repo.get_artifact_path(coord, version, ArtifactKind::Binary);
+        let source = manager.download_jar_to(coord, version, &path).await?;
+        self.repo.store_source(coord, version, ArtifactKind::Binary, &source)?;
+        self.repo.cache_artifact(coord, version, ArtifactKind::Binary, Some(&source))?;
+        Ok(source)
+    }
+
+    // Fetches a Gradle variant's own file and puts it where the jar would have gone
+    async fn fetch_variant(&self, manager: &RepositoryManager, coord: &Coordinate, file: &Coordinate, version: &str) -> anyhow::Result<ArtifactSource> {
+        let source = self.fetch(manager, file, version).await?;
+        if self.repo.restore(coord, version, ArtifactKind::Binary, |placed| placed == &source)?.is_none() {
+            self.place_jar(coord, version, &mut File::open(self.repo.get_artifact_path(file, version, ArtifactKind::Binary))?, &source)?;
+        }
+        Ok(source)
+    }
+
+    // Writes `content` where the jar of `coord` goes, through a temporary file
+    // so an interrupted copy never passes for a finished one
+    fn place_jar(&self, coord: &Coordinate, version: &str, content: &mut impl std::io::Read, source: &ArtifactSource) -> anyhow::Result<()> {
+        let jar = self.repo.get_artifact_path(coord, version, ArtifactKind::Binary);
+        let dir = jar.parent().ok_or_else(|| anyhow::anyhow!("{} has no parent directory", jar.display()))?;
+        fs::create_dir_all(dir)?;
+        let mut temp = tempfile::NamedTempFile::new_in(dir)?;
+        std::io::copy(content, &mut temp)?;
+        temp.persist(&jar)?;
+
+        self.repo.store_source(coord, version, ArtifactKind::Binary, source)?;
+        self.repo.cache_artifact(coord, version, ArtifactKind::Binary, Some(source))?;
+        Ok(())
+    }
+
+    // Fetches the .aar and puts its classes.jar where the jar would have gone
+    async fn fetch_aar(&self, manager: &RepositoryManager, coord: &Coordinate, version: &str) -> anyhow::Result<ArtifactSource> {
+        let aar = Coordinate { extension: Some("aar".to_string()), ..coord.clone() };
+        let source = self.fetch(manager, &aar, version).await?;
+
+        // only a classes.jar recorded as extracted from this very .aar is reused
+        if self.repo.restore(coord, version, ArtifactKind::Binary, |extracted| extracted == &source)?.is_none() {
+            let mut archive = zip::ZipArchive::new(File::open(self.repo.get_artifact_path(&aar, version, ArtifactKind::Binary))?)?;
+            let mut classes = archive.by_name("classes.jar")
+                .with_context(|| format!("{}:{} has no classes.jar", aar, version))?;
+            self.place_jar(coord, version, &mut classes, &source)?;
+        }
+        Ok(source)
+    }
 
-            let mut deps = self.parser.parse_dependencies(&metadata)?;
-            for dep in &mut deps {
-                dep.depth = depth + 1;
+    // Dependencies from the `.module` file when Gradle published one, since its
+    // variants say more than the POM can, otherwise from the effective POM
+    async fn component(&self, manager: &RepositoryManager, coord: &Coordinate, version: &str) -> anyhow::Result<Component> {
+        let pom = self.pom_from(manager, coord, version).await?;
+        // classified artifacts were already picked by whoever depends on them
+        if coord.classifier.is_none() && coord.is_jar() && pom.contains(GRADLE_METADATA_MARKER) {
+            let module_coord = Coordinate { extension: Some("module".to_string()), ..coord.clone() };
+            match self.fetch(manager, &module_coord, version).await {
+                Ok(source) => {
+                    let path = self.repo.get_artifact_path(&module_coord, version, ArtifactKind::Binary);
+                    let module = ModuleMetadata::parse(&fs::read_to_string(path)?)
+                        .with_context(|| format!("invalid Gradle module metadata for {}:{}", coord, version))?;
+                    let artifact = match self.modules.select(&module)?.artifact(coord, version)? {
+                        Some(file) if &file == coord => Artifact::Jar,
+                        Some(file) => Artifact::Variant(file),
+                        None => Artifact::Nothing,
+                    };
+                    return Ok(Component {
+                        dependencies: self.modules.parse_dependencies(&module)?,
+                        artifact,
+                        metadata_source: Some((module_coord, source)),
+                        relocated: None,
+                        // Gradle never writes <repositories> into what it publishes
+                        repositories: Vec::new(),
+                    });
+                }
+                Err(err) => tracing::debug!("falling back to the POM of {}:{}: {:#}", coord, version, err),
             }
+        }
+
+        let pom = self.model_from(manager, &pom, self.dependency_model()).await
+            .map_err(|err| err.context(format!("invalid POM for {}:{}", coord, version)))?;
+        // the POM is all a module without an artifact has to show for where it came from
+        let pom_coord = Coordinate { extension: Some("pom".to_string()), classifier: None, ..coord.clone() };
+
+        if let Some(relocation) = pom.relocation.as_ref().filter(|r| r.coordinate != pom.coordinate) {
+            let message = relocation.message.as_ref().map(|m| format!(": {}", m)).unwrap_or_default();
+            eprintln!("warning: {}:{} has moved to {}{}", coord, version, relocation.coordinate, message);
+
+            // whatever was asked of the old name is asked of the new one
+            let target = Coordinate {
+                version: None,
+                extension: coord.extension.clone(),
+                classifier: coord.classifier.clone(),
+                ..relocation.coordinate.clone()
+            };
+            return Ok(Component {
+                dependencies: vec![DependencyRequest {
+                    coordinate: target.clone(),
+                    version_req: VersionReq::parse(relocation.coordinate.version.as_deref().unwrap_or(version))?,
+                    scope: None,
+                    exclusions: Vec::new(),
+                    depth: 0,
+                    native: false,
+                }],
+                artifact: Artifact::Nothing,
+                metadata_source: Some((pom_coord.clone(), self.fetch(manager, &pom_coord, version).await?)),
+                relocated: Some(target),
+                repositories: pom.repositories,
+            });
+        }
 
-            for dep in deps {
-                graph.add_requirement(&dep.coordinate, dep.version_req.clone(), dep.depth);
-                graph.add_edge(&coord, &dep.coordinate);
+        // packaging only says what the main artifact is, anything classified was asked for by name
+        let artifact = match pom.packaging.as_str() {
+            _ if coord.extension() == "pom" => Artifact::Nothing,
+            _ if coord.classifier.is_some() || !coord.is_jar() => Artifact::Jar,
+            "pom" => Artifact::Nothing,
+            "aar" => Artifact::Aar,
+            _ => Artifact::Jar,
+        };
+        Ok(Component {
+            dependencies: self.parser.parse_dependencies(&pom)?,
+            metadata_source: match artifact {
+                Artifact::Nothing => Some((pom_coord.clone(), self.fetch(manager, &pom_coord, version).await?)),
+                _ => None,
+            },
+            artifact,
+            relocated: None,
+            repositories: pom.repositories,
+        })
+    }
+
+    /// Resolves all of a project's direct dependencies into one graph. The lockfile
+    /// is used as is when it already pins each of them and every jar it lists is in
+    /// the project repository, otherwise it's updated with a fresh resolution.
+    pub async fn resolve_project(&self, direct: &[(Coordinate, String)], lockfile: &mut Lockfile) -> anyhow::Result<DependencyGraph> {
+        let fetched = |graph: &DependencyGraph| graph.artifacts().iter()
+            .all(|(coord, version)| self.repo.get_artifact_path(coord, version, ArtifactKind::Binary).exists());
+        if let Some(graph) = self.locked_graph(direct, lockfile)?.filter(fetched) {
+            return Ok(graph);
+        }
 
-                let available_versions = self.manager.search_versions(&dep.coordinate).await?;
-                let mut compatible_version = None;
+        let graph = self.solve(direct, &lockfile.versions()).await?;
+        lockfile.merge_graph(&graph, &self.repo, self.manager.routing(), &self.platform)?;
+        Ok(graph)
+    }
+
+    /// Resolves the project again, keeping only the `preferred` versions, and
+    /// replaces what the lockfile has for this platform with the result
+    pub async fn update_project(
+        &self,
+        direct: &[(Coordinate, String)],
+        lockfile: &mut Lockfile,
+        preferred: &HashMap<Coordinate, MavenVersion>,
+    ) -> anyhow::Result<DependencyGraph> {
+        let graph = self.solve(direct, preferred).await?;
+
+        // other platforms' native artifacts weren't looked at, so they stay
+        let current = self.platform.classifier();
+        lockfile.deps.retain(|coord, info| {
+            let resolved = Coordinate::parse_unversioned(coord).is_ok_and(|c| graph.resolved.get(&c).is_some_and(|v| v.to_string() == info.version));
+            resolved || info.platform.as_ref().is_some_and(|p| *p != current)
+        });
+        lockfile.merge_graph(&graph, &self.repo, self.manager.routing(), &self.platform)?;
+        Ok(graph)
+    }
 
-                for v in available_versions {
-                    let maven_version: MavenVersion = v.parse()?;
-                    if graph.check_version_compatibility(&dep.coordinate, &maven_version) {
-                        compatible_version = Some(maven_version);
-                        break;
+    /// The graph gallade.lock records for `direct`, without looking at any repository.
+    /// None when the lockfile doesn't pin all of them or gallade.toml's rules changed since.
+    pub fn locked_graph(&self, direct: &[(Coordinate, String)], lockfile: &Lockfile) -> anyhow::Result<Option<DependencyGraph>> {
+        let mut graph = DependencyGraph::new();
+        let mut queue = VecDeque::new();
+
+        for (coord, version) in direct {
+            let req = VersionReq::declared(version)?;
+            let locked = lockfile.deps.get(&coord.to_string()).and_then(|info| info.version.parse::<MavenVersion>().ok());
+            if !locked.is_some_and(|locked| req.matches(&locked)) {
+                return Ok(None);
+            }
+            queue.push_back((coord.clone(), self.root_exclusions(coord)));
+        }
+
+        // a module reached under different exclusions was solved going by one of them, which
+        // the lockfile can't tell, so it's only stale if every one of them drops a locked dependency
+        let mut visited = HashSet::new();
+        let mut untouched: HashMap<Coordinate, bool> = HashMap::new();
+        while let Some((coord, exclusions)) = queue.pop_front() {
+            let mut excluded: Vec<String> = exclusions.iter().map(|ex| ex.to_string()).collect();
+            excluded.sort();
+            excluded.dedup();
+            if !visited.insert((coord.clone(), excluded)) {
+                continue;
+            }
+            let Some(info) = lockfile.deps.get(&coord.to_string()) else {
+                return Ok(None);
+            };
+
+            if !graph.resolved.contains_key(&coord) {
+                // the manifest's rules may have changed since the lockfile was written
+                let version: MavenVersion = info.version.parse()?;
+                match self.overrides.get(&coord) {
+                    Some(forced) if forced != &version => return Ok(None),
+                    Some(_) => {
+                        graph.overridden.insert(coord.clone());
                     }
+                    // forced by an override that's gone since
+                    None if info.overridden => return Ok(None),
+                    None => {}
+                }
+                if self.constraints.get(&coord).is_some_and(|req| !req.matches(&version)) {
+                    return Ok(None);
+                }
+
+                if info.metadata_only {
+                    graph.metadata_only.insert(coord.clone());
                 }
+                if info.platform.is_some() {
+                    graph.native.insert(coord.clone());
+                }
+                graph.add_resolution(&coord, version);
+            }
 
-                if let Some(v) = compatible_version {
-                    graph.add_resolution(&dep.coordinate.clone(), v.clone());
-                    queue.push_back((dep.coordinate.clone(), v, dep.depth));
-                } else {
-                    anyhow::bail!("no compatible version found for {} with version: {:?}", dep.coordinate, dep.version_req);
+            let mut clean = true;
+            for dep in info.deps_on(&self.platform) {
+                let dep = Coordinate::parse_unversioned(&dep)?;
+                // excluded since the lockfile was written, at least along this path
+                if exclusions.iter().any(|ex| ex.matches(&dep)) {
+                    clean = false;
+                    continue;
                 }
+                graph.add_edge(&coord, &dep);
+                queue.push_back((dep, exclusions.clone()));
             }
+            *untouched.entry(coord).or_default() |= clean;
+        }
+
+        if untouched.values().any(|clean| !clean) {
+            return Ok(None);
         }
+        Ok(Some(graph))
+    }
 
-        graph.add_resolution(root_coord, root_version);
+    pub async fn load_pom(&self, coord: &Coordinate, version: &str) -> anyhow::Result<String> {
+        self.pom_from(&self.manager, coord, version).await
+    }
 
-        Ok(graph)
+    async fn pom_from(&self, manager: &RepositoryManager, coord: &Coordinate, version: &str) -> anyhow::Result<String> {
+        // same as jars, a cached POM is only trusted from a repository routing still allows
+        let cached = self.repo.restore(coord, version, ArtifactKind::Metadata, |source| {
+            manager.routing().is_allowed(&coord.namespace, &source.repository)
+        })?;
+        if cached.is_some() {
+            return Ok(String::from_utf8(self.repo.load_artifact(coord, version, ArtifactKind::Metadata)?)?);
+        }
+
+        let (metadata, source) = manager.download_metadata(coord, version).await?;
+        self.repo.store_artifact(coord, version, ArtifactKind::Metadata, metadata.as_bytes(), &source).await?;
+        Ok(metadata)
+    }
+
+    // Dependencies' POMs see -D and the java version, but not -P or the project directory
+    fn dependency_model(&self) -> ModelBuilder {
+        let activation = ProfileActivation { profiles: Vec::new(), basedir: None, ..self.activation.clone() };
+        ModelBuilder::new().with_platform(self.platform.clone()).with_activation(activation)
+    }
+
+    /// The artifact's POM with parents and imported BOMs fetched and applied
+    pub async fn effective_pom(&self, coord: &Coordinate, version: &str) -> anyhow::Result<EffectivePom> {
+        let pom = self.load_pom(coord, version).await?;
+        self.complete_model(&pom, self.dependency_model()).await
+            .map_err(|err| err.context(format!("invalid POM for {}:{}", coord, version)))
+    }
+
+    /// Builds the effective model of `pom`, fetching whatever parents and BOMs
+    /// `builder` doesn't already have
+    pub async fn complete_model(&self, pom: &str, builder: ModelBuilder) -> anyhow::Result<EffectivePom> {
+        self.model_from(&self.manager, pom, builder).await
+    }
+
+    async fn model_from(&self, manager: &RepositoryManager, pom: &str, mut builder: ModelBuilder) -> anyhow::Result<EffectivePom> {
+        loop {
+            match builder.build(pom) {
+                Ok(effective) => return Ok(effective),
+                Err(ModelError::Missing(needed)) => {
+                    let needed_version = needed.version.clone()
+                        .ok_or_else(|| anyhow::anyhow!("{} is referenced without a version", needed))?;
+                    let xml = self.pom_from(manager, &needed, &needed_version).await?;
+                    builder.add(&needed, xml);
+                }
+                Err(ModelError::Invalid(err)) => return Err(err),
+            }
+        }
     }
 
     pub fn remove(&self, coord: Coordinate, lockfile: &mut Lockfile) -> anyhow::Result<()> {
@@ -250,7 +672,7 @@ impl DependencyResolver {
         pruner.mark_tree(&coord, lockfile, true);
 
         for (coord_str, _) in lockfile.deps.iter() {
-            if let Ok(c) = Coordinate::parse(coord_str) {
+            if let Ok(c) = Coordinate::parse_unversioned(coord_str) {
                 if c != coord {
                     pruner.mark_tree(&coord, lockfile,  false);
                 }
@@ -269,15 +691,349 @@ impl DependencyResolver {
     }
 }
 
+// Gives up instead of trying every combination of a hopeless graph
+const MAX_SOLVER_STEPS: usize = 10_000;
+
+// A version requirement on a module, and who has it
+#[derive(Debug, Clone)]
+struct Demand {
+    request: DependencyRequest,
+    /// The decision that asked for it, None for the project itself
+    from: Option<usize>,
+}
+
+// A version picked for a module, and the others it could still be
+struct Decision {
+    coordinate: Coordinate,
+    version: MavenVersion,
+    /// Whoever is nearest among those asking for it
+    parent: Option<usize>,
+    depth: usize,
+    exclusions: Vec<Exclusion>,
+    /// Where its versions are looked up, from the POMs above it
+    lookup: RepositoryManager,
+    /// The same plus its own POM's repositories, for itself and what's below it
+    manager: RepositoryManager,
+    component: Component,
+    demands: Vec<Demand>,
+    /// The ranges it was picked to satisfy
+    requirements: Vec<VersionReq>,
+    /// Versions left to try, listed the first time they're needed
+    alternatives: Option<Vec<MavenVersion>>,
+}
+
+// No version of `coordinate` works with what's been decided so far
+struct Conflict {
+    coordinate: Coordinate,
+    /// Every version that was available, when none of them matched
+    available: Option<Vec<MavenVersion>>,
+}
+
+// Picks versions one module at a time, nearest first like Maven. A plain version
+// is a preference and the nearest one wins, but ranges have to hold, so when one
+// can't the most recent decision that led to it is changed to its next best
+// version and everything decided after it is redone.
+struct Solver<'a> {
+    resolver: &'a DependencyResolver,
+    roots: Vec<Demand>,
+    decisions: Vec<Decision>,
+    decided: HashMap<Coordinate, usize>,
+    /// Versions from the lockfile, kept over anything else that fits
+    locked: HashMap<Coordinate, MavenVersion>,
+    components: HashMap<(Coordinate, String), Component>,
+    /// Versions given up on, for the error when nothing works
+    tried: Vec<String>,
+}
+
+impl<'a> Solver<'a> {
+    fn new(resolver: &'a DependencyResolver, direct: &[(Coordinate, String)], locked: HashMap<Coordinate, MavenVersion>) -> anyhow::Result<Self> {
+        // the project's own dependencies are exactly what it says they are
+        let roots = direct.iter()
+            .map(|(coord, version)| Ok(Demand {
+                request: DependencyRequest {
+                    coordinate: Coordinate { version: None, ..coord.clone() },
+                    version_req: VersionReq::declared(version)?,
+                    scope: None,
+                    exclusions: resolver.root_exclusions(coord),
+                    depth: 0,
+                    native: resolver.native_direct.contains(&Coordinate { version: None, ..coord.clone() }),
+                },
+                from: None,
+            }))
+            .collect::<anyhow::Result<_>>()?;
+
+        Ok(Self {
+            resolver,
+            roots,
+            decisions: Vec::new(),
+            decided: HashMap::new(),
+            locked,
+            components: HashMap::new(),
+            tried: Vec::new(),
+        })
+    }
+
+    fn demands(&self) -> impl Iterator<Item = &Demand> {
+        self.roots.iter().chain(self.decisions.iter().flat_map(|d| d.demands.iter()))
+    }
+
+    fn demands_on(&self, coord: &Coordinate) -> Vec<Demand> {
+        let mut demands: Vec<Demand> = self.demands().filter(|d| &d.request.coordinate == coord).cloned().collect();
+        demands.sort_by_key(|d| d.request.depth);
+        demands
+    }
+
+    async fn run(&mut self) -> anyhow::Result<()> {
+        let mut conflict = None;
+        for _ in 0..MAX_SOLVER_STEPS {
+            if let Some(found) = conflict.take() {
+                conflict = self.backtrack(found).await?;
+                continue;
+            }
+
+            // nearest undecided module first
+            let Some(coord) = self.demands()
+                .filter(|d| !self.decided.contains_key(&d.request.coordinate))
+                .min_by_key(|d| d.request.depth)
+                .map(|d| d.request.coordinate.clone())
+            else {
+                return Ok(());
+            };
+
+            let demands = self.demands_on(&coord);
+            let nearest = &demands[0];
+            let mut requirements: Vec<VersionReq> = demands.iter()
+                .map(|d| d.request.version_req.clone())
+                .filter(VersionReq::is_hard)
+                .collect();
+            requirements.extend(self.resolver.constraints.get(&coord).cloned());
+            let lookup = match nearest.from {
+                Some(parent) => self.decisions[parent].manager.clone(),
+                None => self.resolver.manager.clone(),
+            };
+
+            // a locked version stays put and otherwise the nearest one asked for
+            // wins, neither needs a lookup as long as it's in every range
+            let preferred = self.locked.get(&coord).into_iter()
+                .chain(demands.iter().filter_map(|d| d.request.version_req.pinned()))
+                .find(|v| requirements.iter().all(|req| req.matches(v)))
+                .cloned();
+            let (version, alternatives) = match preferred {
+                // an override isn't up for negotiation, so one that can't hold ends it here
+                _ if self.resolver.overrides.contains_key(&coord) => {
+                    let forced = &self.resolver.overrides[&coord];
+                    if self.resolver.constraints.get(&coord).is_some_and(|req| !req.matches(forced)) {
+                        let conflict = Conflict { coordinate: coord.clone(), available: None };
+                        anyhow::bail!(self.explain(&conflict, &self.demands_on(&coord)));
+                    }
+                    // a search can miss versions, fetching the POM is what tells it exists
+                    self.resolver.pom_from(&lookup, &coord, &forced.to_string()).await
+                        .with_context(|| format!("[overrides] in gallade.toml forces {} {}", coord, forced))?;
+                    (forced.clone(), Some(Vec::new()))
+                }
+                Some(version) => (version, None),
+                None => {
+                    let mut versions = self.candidates(&lookup, &coord, &requirements, None).await?;
+                    if versions.is_empty() {
+                        let available = self.candidates(&lookup, &coord, &[], None).await?;
+                        conflict = Some(Conflict { coordinate: coord, available: Some(available) });
+                        continue;
+                    }
+                    (versions.remove(0), Some(versions))
+                }
+            };
+
+            self.decisions.push(Decision {
+                coordinate: coord.clone(),
+                version,
+                parent: nearest.from,
+                depth: nearest.request.depth,
+                exclusions: nearest.request.exclusions.clone(),
+                manager: lookup.clone(),
+                lookup,
+                component: Component {
+                    dependencies: Vec::new(),
+                    artifact: Artifact::Nothing,
+                    metadata_source: None,
+                    relocated: None,
+                    repositories: Vec::new(),
+                },
+                demands: Vec::new(),
+                requirements,
+                alternatives,
+            });
+            self.decided.insert(coord, self.decisions.len() - 1);
+            conflict = self.settle().await?;
+        }
+
+        anyhow::bail!("gave up resolving after {} attempts, too many versions conflict with each other", MAX_SOLVER_STEPS)
+    }
+
+    // Loads what the latest decision depends on, and checks it against what's already decided
+    async fn settle(&mut self) -> anyhow::Result<Option<Conflict>> {
+        let index = self.decisions.len() - 1;
+        let decision = &self.decisions[index];
+        let key = (decision.coordinate.clone(), decision.version.to_string());
+        let component = match self.components.get(&key) {
+            Some(component) => component.clone(),
+            None => {
+                let component = self.resolver.component(&decision.lookup, &key.0, &key.1).await?;
+                self.components.insert(key, component.clone());
+                component
+            }
+        };
+
+        let decision = &mut self.decisions[index];
+        let mut deps = component.dependencies.clone();
+        // exclusions apply to the whole subtree below the dependency that declared them
+        deps.retain(|dep| !decision.exclusions.iter().any(|ex| ex.matches(&dep.coordinate)));
+        decision.demands = deps.into_iter()
+            .map(|mut dep| {
+                dep.depth = decision.depth + 1;
+                dep.exclusions.extend(decision.exclusions.iter().cloned());
+                Demand { request: dep, from: Some(index) }
+            })
+            .collect();
+        decision.manager = decision.lookup.with_pom_repositories(&component.repositories);
+        decision.component = component;
+
+        let decision = &self.decisions[index];
+        for demand in &decision.demands {
+            let req = &demand.request.version_req;
+            if let Some(&other) = self.decided.get(&demand.request.coordinate) {
+                let forced = self.resolver.overrides.contains_key(&demand.request.coordinate);
+                if req.is_hard() && !forced && !req.matches(&self.decisions[other].version) {
+                    return Ok(Some(Conflict { coordinate: demand.request.coordinate.clone(), available: None }));
+                }
+            }
+        }
+        Ok(None)
+    }
+
+    // Moves the most recent decision behind `conflict` that has any left to its next version
+    async fn backtrack(&mut self, conflict: Conflict) -> anyhow::Result<Option<Conflict>> {
+        let demands = self.demands_on(&conflict.coordinate);
+        let mut involved = HashSet::new();
+        let culprits = demands.iter().map(|d| d.from).chain([self.decided.get(&conflict.coordinate).copied()]);
+        for mut index in culprits {
+            while let Some(i) = index {
+                involved.insert(i);
+                index = self.decisions[i].parent;
+            }
+        }
+        let mut involved: Vec<usize> = involved.into_iter().collect();
+        involved.sort_unstable_by(|a, b| b.cmp(a));
+
+        for index in involved {
+            if self.decisions[index].alternatives.is_none() {
+                let decision = &self.decisions[index];
+                let versions = self.candidates(&decision.lookup, &decision.coordinate, &decision.requirements, Some(&decision.version)).await?;
+                self.decisions[index].alternatives = Some(versions);
+            }
+            let decision = &mut self.decisions[index];
+            let Some(alternatives) = decision.alternatives.as_mut().filter(|a| !a.is_empty()) else {
+                continue;
+            };
+
+            // a module that clashed with a later range tries the versions inside it first
+            if decision.coordinate == conflict.coordinate {
+                let ranges: Vec<&VersionReq> = demands.iter().map(|d| &d.request.version_req).filter(|r| r.is_hard()).collect();
+                alternatives.sort_by_key(|v| !ranges.iter().all(|r| r.matches(v)));
+            }
+            let next = alternatives.remove(0);
+            let given_up = format!("{} {}", decision.coordinate, decision.version);
+            if !self.tried.contains(&given_up) {
+                self.tried.push(given_up);
+            }
+            decision.version = next;
+
+            self.decisions.truncate(index + 1);
+            self.decided.retain(|_, i| *i <= index);
+            return self.settle().await;
+        }
+
+        Err(anyhow::anyhow!(self.explain(&conflict, &demands)))
+    }
+
+    // Versions of `coord` inside every range, best first. Around a version that
+    // didn't work out, older ones come first since newer ones usually only ask for more.
+    async fn candidates(&self, manager: &RepositoryManager, coord: &Coordinate, requirements: &[VersionReq], around: Option<&MavenVersion>) -> anyhow::Result<Vec<MavenVersion>> {
+        let mut versions: Vec<MavenVersion> = match requirements.iter().find_map(VersionReq::pinned) {
+            Some(pinned) => vec![pinned.clone()],
+            None => manager.search_versions(coord).await?.iter().filter_map(|v| v.parse().ok()).collect(),
+        };
+        versions.retain(|v| requirements.iter().all(|req| req.matches(v)) && Some(v) != around);
+        versions.sort_by(|a, b| b.cmp(a));
+        versions.dedup();
+
+        if let Some(around) = around {
+            let (mut newer, older): (Vec<_>, Vec<_>) = versions.into_iter().partition(|v| v > around);
+            newer.reverse();
+            versions = older.into_iter().chain(newer).collect();
+        }
+        Ok(versions)
+    }
+
+    // The chain of decisions that led to `index`
+    fn path(&self, index: Option<usize>) -> String {
+        let mut chain = Vec::new();
+        let mut index = index;
+        while let Some(i) = index {
+            chain.push(format!("{} {}", self.decisions[i].coordinate, self.decisions[i].version));
+            index = self.decisions[i].parent;
+        }
+        if chain.is_empty() {
+            return "the project".to_string();
+        }
+        chain.reverse();
+        chain.join(" -> ")
+    }
+
+    fn explain(&self, conflict: &Conflict, demands: &[Demand]) -> String {
+        let coord = &conflict.coordinate;
+        let mut message = format!("no version of {} satisfies all of:", coord);
+        for demand in demands {
+            let req = &demand.request.version_req;
+            let verb = if req.is_hard() { "requires" } else { "asks for" };
+            write!(message, "\n  {} {} {} {}", self.path(demand.from), verb, coord, req).unwrap();
+        }
+        if let Some(forced) = self.resolver.overrides.get(coord) {
+            write!(message, "\n  [overrides] in gallade.toml forces {} {}", coord, forced).unwrap();
+        }
+        if let Some(constraint) = self.resolver.constraints.get(coord) {
+            write!(message, "\n  [constraints] in gallade.toml requires {} {}", coord, constraint).unwrap();
+        }
+        if let Some(available) = &conflict.available {
+            match available.len() {
+                0 => write!(message, "\n  and no versions of {} were found in any repository", coord).unwrap(),
+                n => {
+                    let shown: Vec<String> = available.iter().take(5).map(|v| v.to_string()).collect();
+                    let more = if n > 5 { format!(" and {} more", n - 5) } else { String::new() };
+                    write!(message, "\n  and the versions available are {}{}", shown.join(", "), more).unwrap();
+                }
+            }
+        }
+        if !self.tried.is_empty() {
+            write!(message, "\nalso tried {}", self.tried.join(", ")).unwrap();
+        }
+        message.push_str("\ntherefore no solution");
+        message
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::testing::{module, offline_resolver, publish};
 
     #[test]
     fn test_pom_parser() -> anyhow::Result<()> {
         let parser = PomParser;
         let content = r#"
             <project>
+                <groupId>com.ourcorp</groupId>
+                <artifactId>ledger</artifactId>
+                <version>1.0.0</version>
                 <dependencies>
                     <dependency>
                         <groupId>org.slf4j</groupId>
@@ -294,10 +1050,403 @@ mod tests {
             </project>
         "#;
 
-        let deps = parser.parse_dependencies(content)?;
+        let deps = parser.parse_dependencies(&ModelBuilder::new().build(content)?)?;
         assert_eq!(deps.len(), 1); // junit should be skipped
         assert_eq!(deps[0].coordinate.namespace, "org.slf4j");
 
         Ok(())
     }
-}
\ No newline at end of file
+
+    #[tokio::test]
+    async fn test_resolve_project_from_lockfile() -> anyhow::Result<()> {
+        let temp = tempfile::TempDir::new()?;
+        let repo = Repository::new(temp.path().to_path_buf());
+        let manager = RepositoryManager::new(&Default::default(), &Default::default())?;
+        let resolver = DependencyResolver::new(repo.clone(), manager);
+
+        let mut lockfile: Lockfile = serde_json::from_value(serde_json::json!({
+            "version": 1,
+            "deps": {
+                "com.ourcorp:ledger": { "version": "1.0.0", "repository": "central", "integrity": "", "deps": ["org.slf4j:slf4j-api"] },
+                "org.slf4j:slf4j-api": { "version": "2.0.9", "repository": "central", "integrity": "", "deps": [] },
+            }
+        }))?;
+        for (coord, version) in [("com.ourcorp:ledger", "1.0.0"), ("org.slf4j:slf4j-api", "2.0.9")] {
+            let jar = repo.get_artifact_path(&Coordinate::parse(coord)?, version, ArtifactKind::Binary);
+            std::fs::create_dir_all(jar.parent().unwrap())?;
+            std::fs::write(jar, "jar")?;
+        }
+
+        // everything is locked and on disk, so nothing needs the network
+        let direct = vec![(Coordinate::parse("com.ourcorp:ledger")?, "1.0.0".to_string())];
+        let graph = resolver.resolve_project(&direct, &mut lockfile).await?;
+        let artifacts: Vec<_> = graph.artifacts().into_iter().map(|(c, v)| format!("{}:{}", c, v)).collect();
+        assert_eq!(artifacts, vec!["com.ourcorp:ledger:1.0.0", "org.slf4j:slf4j-api:2.0.9"]);
+
+        // a different declared version isn't covered by the lockfile
+        let changed = vec![(Coordinate::parse("com.ourcorp:ledger")?, "1.1.0".to_string())];
+        assert!(resolver.locked_graph(&changed, &lockfile)?.is_none());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_resolve_through_gradle_module() -> anyhow::Result<()> {
+        let m2 = tempfile::TempDir::new()?;
+        let pom = |artifact: &str| format!(
+            "<project><!-- {} --><groupId>org.jetbrains.kotlinx</groupId><artifactId>{}</artifactId><version>1.7.3</version></project>",
+            crate::module::GRADLE_METADATA_MARKER, artifact
+        );
+
+        publish(m2.path(), "org.jetbrains.kotlinx:kotlinx-coroutines-core", "1.7.3", &[
+            ("kotlinx-coroutines-core-1.7.3.pom", pom("kotlinx-coroutines-core").as_bytes()),
+            ("kotlinx-coroutines-core-1.7.3.module", r#"{"formatVersion": "1.1", "variants": [{
+                "name": "jvmRuntimeElements-published",
+                "attributes": {"org.gradle.usage": "java-runtime", "org.jetbrains.kotlin.platform.type": "jvm"},
+                "available-at": {"url": "../../kotlinx-coroutines-core-jvm/1.7.3/kotlinx-coroutines-core-jvm-1.7.3.module",
+                    "group": "org.jetbrains.kotlinx", "module": "kotlinx-coroutines-core-jvm", "version": "1.7.3"}
+            }]}"#.as_bytes()),
+        ])?;
+        // no .module here, so its POM is used
+        publish(m2.path(), "org.jetbrains.kotlinx:kotlinx-coroutines-core-jvm", "1.7.3", &[
+            ("kotlinx-coroutines-core-jvm-1.7.3.pom", pom("kotlinx-coroutines-core-jvm").as_bytes()),
+            ("kotlinx-coroutines-core-jvm-1.7.3.jar", b"jar"),
+        ])?;
+
+        let temp = tempfile::TempDir::new()?;
+        let repo = Repository::new(temp.path().to_path_buf());
+        let resolver = offline_resolver(&repo, m2.path())?.with_java_version(Some("17"));
+
+        let core = Coordinate::parse("org.jetbrains.kotlinx:kotlinx-coroutines-core")?;
+        let mut lockfile = Lockfile::new();
+        let graph = resolver.resolve_project(&[(core.clone(), "1.7.3".to_string())], &mut lockfile).await?;
+
+        // only the module the variant is available at has a jar to put on the classpath
+        let artifacts: Vec<_> = graph.artifacts().into_iter().map(|(c, v)| format!("{}:{}", c, v)).collect();
+        assert_eq!(artifacts, vec!["org.jetbrains.kotlinx:kotlinx-coroutines-core-jvm:1.7.3"]);
+
+        let locked = &lockfile.deps["org.jetbrains.kotlinx:kotlinx-coroutines-core"];
+        assert!(locked.metadata_only);
+        assert_eq!(locked.deps, vec!["org.jetbrains.kotlinx:kotlinx-coroutines-core-jvm"]);
+
+        // and the lockfile alone is enough the next time round
+        let relocked = resolver.locked_graph(&[(core, "1.7.3".to_string())], &lockfile)?.expect("everything is locked");
+        assert_eq!(relocked.artifacts().len(), 1);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_gradle_variant_file() -> anyhow::Result<()> {
+        let m2 = tempfile::TempDir::new()?;
+        let pom = format!(
+            "<project><!-- {} --><groupId>com.ourcorp</groupId><artifactId>ledger</artifactId><version>2.0.0</version></project>",
+            crate::module::GRADLE_METADATA_MARKER
+        );
+        publish(m2.path(), "com.ourcorp:ledger", "2.0.0", &[
+            ("ledger-2.0.0.pom", pom.as_bytes()),
+            ("ledger-2.0.0.module", r#"{"formatVersion": "1.1", "variants": [
+                {"name": "runtimeElements",
+                 "attributes": {"org.gradle.category": "library", "org.gradle.usage": "java-runtime", "org.gradle.jvm.version": 17},
+                 "files": [{"name": "ledger-2.0.0.jar", "url": "ledger-2.0.0.jar"}]},
+                {"name": "java11RuntimeElements",
+                 "attributes": {"org.gradle.category": "library", "org.gradle.usage": "java-runtime", "org.gradle.jvm.version": 11},
+                 "files": [{"name": "ledger-2.0.0-java11.jar", "url": "ledger-2.0.0-java11.jar"}]}
+            ]}"#.as_bytes()),
+            ("ledger-2.0.0.jar", b"java17"),
+            ("ledger-2.0.0-java11.jar", b"java11"),
+        ])?;
+
+        let temp = tempfile::TempDir::new()?;
+        let repo = Repository::new(temp.path().to_path_buf());
+        let ledger = Coordinate::parse("com.ourcorp:ledger")?;
+        let direct = [(ledger.clone(), "2.0.0".to_string())];
+        let jar = repo.get_artifact_path(&ledger, "2.0.0", ArtifactKind::Binary);
+
+        // on java 11 the classpath gets the variant's own jar
+        let mut lockfile = Lockfile::new();
+        let graph = offline_resolver(&repo, m2.path())?.with_java_version(Some("11")).resolve_project(&direct, &mut lockfile).await?;
+        assert_eq!(fs::read(&jar)?, b"java11");
+        assert!(graph.sources[&ledger].url.ends_with("ledger-2.0.0-java11.jar"));
+
+        // and that copy isn't mistaken for the module's jar on java 17
+        let mut lockfile = Lockfile::new();
+        offline_resolver(&repo, m2.path())?.with_java_version(Some("17")).resolve_project(&direct, &mut lockfile).await?;
+        assert_eq!(fs::read(&jar)?, b"java17");
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_relocation_and_packaging() -> anyhow::Result<()> {
+        let m2 = tempfile::TempDir::new()?;
+        let pom = |group: &str, artifact: &str, rest: &str| format!(
+            "<project><groupId>{}</groupId><artifactId>{}</artifactId><version>8.0.33</version>{}</project>", group, artifact, rest
+        );
+
+        publish(m2.path(), "mysql:mysql-connector-java", "8.0.33", &[
+            ("mysql-connector-java-8.0.33.pom", pom("mysql", "mysql-connector-java", "<packaging>pom</packaging>\
+                <distributionManagement><relocation><groupId>com.mysql</groupId><artifactId>mysql-connector-j</artifactId>\
+                </relocation></distributionManagement>").as_bytes()),
+        ])?;
+        publish(m2.path(), "com.mysql:mysql-connector-j", "8.0.33", &[
+            ("mysql-connector-j-8.0.33.pom", pom("com.mysql", "mysql-connector-j", "<dependencies><dependency>\
+                <groupId>com.ourcorp</groupId><artifactId>widgets</artifactId><version>8.0.33</version>\
+                </dependency></dependencies>").as_bytes()),
+            ("mysql-connector-j-8.0.33.jar", b"jar"),
+        ])?;
+
+        // an Android library with its classes in a jar inside the .aar
+        let mut aar = zip::ZipWriter::new(std::io::Cursor::new(Vec::new()));
+        aar.start_file("AndroidManifest.xml", zip::write::SimpleFileOptions::default())?;
+        aar.start_file("classes.jar", zip::write::SimpleFileOptions::default())?;
+        std::io::Write::write_all(&mut aar, b"classes")?;
+        publish(m2.path(), "com.ourcorp:widgets", "8.0.33", &[
+            ("widgets-8.0.33.pom", pom("com.ourcorp", "widgets", "<packaging>aar</packaging>").as_bytes()),
+            ("widgets-8.0.33.aar", &aar.finish()?.into_inner()),
+        ])?;
+
+        let temp = tempfile::TempDir::new()?;
+        let repo = Repository::new(temp.path().to_path_buf());
+        let resolver = offline_resolver(&repo, m2.path())?;
+        // what an interrupted extraction would have left behind
+        let widgets = repo.get_artifact_path(&Coordinate::parse("com.ourcorp:widgets")?, "8.0.33", ArtifactKind::Binary);
+        fs::create_dir_all(widgets.parent().unwrap())?;
+        fs::write(&widgets, b"clas")?;
+
+        let old = Coordinate::parse("mysql:mysql-connector-java")?;
+        let mut lockfile = Lockfile::new();
+        let graph = resolver.resolve_project(&[(old.clone(), "8.0.33".to_string())], &mut lockfile).await?;
+
+        // the stub has no jar, nothing tried to fetch one
+        let artifacts: Vec<_> = graph.artifacts().into_iter().map(|(c, v)| format!("{}:{}", c, v)).collect();
+        assert_eq!(artifacts, vec!["com.mysql:mysql-connector-j:8.0.33", "com.ourcorp:widgets:8.0.33"]);
+
+        // both names are in the lock
+        let moved = &lockfile.deps["mysql:mysql-connector-java"];
+        assert!(moved.metadata_only);
+        assert_eq!(moved.relocated.as_deref(), Some("com.mysql:mysql-connector-j"));
+        assert!(lockfile.deps.contains_key("com.mysql:mysql-connector-j"));
+        // pinned by the POM it was resolved from
+        let pom_hash = repo.integrity(&Coordinate { extension: Some("pom".to_string()), ..old.clone() }, "8.0.33")?;
+        assert_eq!(serde_json::to_value(moved)?["integrity"], pom_hash);
+
+        assert_eq!(std::fs::read(&widgets)?, b"classes");
+        let widgets_hash = repo.integrity(&Coordinate::parse("com.ourcorp:widgets")?, "8.0.33")?;
+        assert_eq!(serde_json::to_value(&lockfile.deps["com.ourcorp:widgets"])?["integrity"], widgets_hash);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_solver_backtracks_and_explains() -> anyhow::Result<()> {
+        let m2 = tempfile::TempDir::new()?;
+        module(m2.path(), "app", "1.0", &[("a", "2.1"), ("c", "1.0")])?;
+        module(m2.path(), "a", "2.1", &[("b", "[3,4)")])?;
+        module(m2.path(), "a", "1.0", &[("b", "[1,2)")])?;
+        module(m2.path(), "c", "1.0", &[("b", "[1,2)")])?;
+        module(m2.path(), "b", "1.5", &[])?;
+        module(m2.path(), "b", "3.0", &[])?;
+
+        let temp = tempfile::TempDir::new()?;
+        let repo = Repository::new(temp.path().to_path_buf());
+        let resolver = offline_resolver(&repo, m2.path())?;
+
+        // a 2.1 only asks for a preference, so going back to a 1.0 fixes it
+        let graph = resolver.solve(&[(Coordinate::parse("com.ourcorp:app")?, "1.0".to_string())], &HashMap::new()).await?;
+        let version = |name: &str| graph.resolved[&Coordinate::parse(&format!("com.ourcorp:{}", name)).unwrap()].to_string();
+        assert_eq!(version("a"), "1.0");
+        assert_eq!(version("b"), "1.5");
+
+        // but not when the project itself wants a 2.1
+        let direct = [
+            (Coordinate::parse("com.ourcorp:a")?, "2.1".to_string()),
+            (Coordinate::parse("com.ourcorp:c")?, "1.0".to_string()),
+        ];
+        let err = resolver.resolve_project(&direct, &mut Lockfile::new()).await.unwrap_err().to_string();
+        assert!(err.starts_with("no version of com.ourcorp:b satisfies all of:"), "{}", err);
+        assert!(err.contains("com.ourcorp:a 2.1 requires com.ourcorp:b [3,4)"), "{}", err);
+        assert!(err.contains("com.ourcorp:c 1.0 requires com.ourcorp:b [1,2)"), "{}", err);
+        assert!(err.contains("the versions available are 3.0, 1.5"), "{}", err);
+        assert!(err.ends_with("therefore no solution"), "{}", err);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_locked_versions_are_kept() -> anyhow::Result<()> {
+        let m2 = tempfile::TempDir::new()?;
+        module(m2.path(), "app", "1.0", &[("b", "3.0")])?;
+        module(m2.path(), "strict", "1.0", &[("b", "[3,4)")])?;
+        for version in ["1.5", "3.0"] {
+            module(m2.path(), "b", version, &[])?;
+        }
+
+        let temp = tempfile::TempDir::new()?;
+        let repo = Repository::new(temp.path().to_path_buf());
+        let resolver = offline_resolver(&repo, m2.path())?;
+        let lockfile: Lockfile = serde_json::from_str(r#"{"version": 1, "deps": {
+            "com.ourcorp:b": { "version": "1.5", "repository": "central", "integrity": "", "deps": [] }
+        }}"#)?;
+        let b = Coordinate::parse("com.ourcorp:b")?;
+
+        // app would rather have 3.0, but 1.5 is locked and nothing rules it out
+        let graph = resolver.solve(&[(Coordinate::parse("com.ourcorp:app")?, "1.0".to_string())], &lockfile.versions()).await?;
+        assert_eq!(graph.resolved[&b].to_string(), "1.5");
+
+        // strict needs a 3.x, so it has to move
+        let graph = resolver.solve(&[(Coordinate::parse("com.ourcorp:strict")?, "1.0".to_string())], &lockfile.versions()).await?;
+        assert_eq!(graph.resolved[&b].to_string(), "3.0");
+
+        let mut updated = lockfile.clone();
+        updated.merge_graph(&graph, &repo, resolver.manager.routing(), &resolver.platform)?;
+        let changes = updated.changes_since(&lockfile);
+        assert_eq!(changes, vec![
+            ("com.ourcorp:b".to_string(), Some("1.5".to_string()), Some("3.0".to_string())),
+            ("com.ourcorp:strict".to_string(), None, Some("1.0".to_string())),
+        ]);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_overrides_and_constraints() -> anyhow::Result<()> {
+        let m2 = tempfile::TempDir::new()?;
+        module(m2.path(), "app", "1.0", &[("b", "3.0"), ("c", "[1.5,2.0)")])?;
+        for version in ["1.5", "3.0"] {
+            module(m2.path(), "b", version, &[])?;
+        }
+        for version in ["1.0", "1.5"] {
+            module(m2.path(), "c", version, &[])?;
+        }
+
+        let temp = tempfile::TempDir::new()?;
+        let repo = Repository::new(temp.path().to_path_buf());
+        let (b, c) = (Coordinate::parse("com.ourcorp:b")?, Coordinate::parse("com.ourcorp:c")?);
+        let direct = [(Coordinate::parse("com.ourcorp:app")?, "1.0".to_string())];
+        let mut lockfile = Lockfile::new();
+
+        // c is forced below app's range, b is kept under 2 even though app asks for 3.0,
+        // and d isn't pulled in just because there's a constraint on it
+        let resolver = offline_resolver(&repo, m2.path())?
+            .with_overrides(HashMap::from([(c.clone(), "1.0".parse()?)]))
+            .with_constraints(HashMap::from([
+                (b.clone(), VersionReq::parse("[1,2)")?),
+                (Coordinate::parse("com.ourcorp:d")?, VersionReq::parse("[1,2)")?),
+            ]));
+        let graph = resolver.resolve_project(&direct, &mut lockfile).await?;
+        assert_eq!(graph.resolved[&c].to_string(), "1.0");
+        assert_eq!(graph.resolved[&b].to_string(), "1.5");
+        assert_eq!(graph.resolved.len(), 3);
+        assert!(graph.overridden.contains(&c));
+
+        // dropping the override makes the lockfile stale
+        let resolver = offline_resolver(&repo, m2.path())?;
+        let graph = resolver.resolve_project(&direct, &mut lockfile).await?;
+        assert_eq!(graph.resolved[&c].to_string(), "1.5");
+        assert!(graph.overridden.is_empty());
+
+        // an override has to exist, and can't step outside a constraint on the same module
+        let resolver = offline_resolver(&repo, m2.path())?
+            .with_overrides(HashMap::from([(c.clone(), "2.0".parse()?)]));
+        let err = format!("{:#}", resolver.solve(&direct, &HashMap::new()).await.unwrap_err());
+        assert!(err.starts_with("[overrides] in gallade.toml forces com.ourcorp:c 2.0"), "{}", err);
+        assert!(err.contains("could not download com.ourcorp:c:2.0 pom"), "{}", err);
+
+        let resolver = offline_resolver(&repo, m2.path())?
+            .with_overrides(HashMap::from([(b.clone(), "3.0".parse()?)]))
+            .with_constraints(HashMap::from([(b.clone(), VersionReq::parse("[1,2)")?)]));
+        let err = resolver.solve(&direct, &HashMap::new()).await.unwrap_err().to_string();
+        assert!(err.starts_with("no version of com.ourcorp:b satisfies all of:"), "{}", err);
+        assert!(err.contains("[overrides] in gallade.toml forces com.ourcorp:b 3.0"), "{}", err);
+        assert!(err.contains("[constraints] in gallade.toml requires com.ourcorp:b [1,2)"), "{}", err);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_manifest_exclusions() -> anyhow::Result<()> {
+        let m2 = tempfile::TempDir::new()?;
+        module(m2.path(), "app", "1.0", &[("b", "1.0"), ("logging", "1.0")])?;
+        module(m2.path(), "b", "1.0", &[("c", "1.0")])?;
+        for artifact in ["c", "logging"] {
+            module(m2.path(), artifact, "1.0", &[])?;
+        }
+
+        let temp = tempfile::TempDir::new()?;
+        let repo = Repository::new(temp.path().to_path_buf());
+        let app = Coordinate::parse("com.ourcorp:app")?;
+        let direct = [(app.clone(), "1.0".to_string())];
+        let mut lockfile = Lockfile::new();
+        let graph = offline_resolver(&repo, m2.path())?.resolve_project(&direct, &mut lockfile).await?;
+        assert_eq!(graph.resolved.len(), 4);
+
+        // app's own exclusion and a global one, both reaching all the way down;
+        // the lockfile still has them, so it's stale
+        let resolver = offline_resolver(&repo, m2.path())?.with_exclusions(
+            vec![Exclusion::parse("com.ourcorp:logging")],
+            HashMap::from([(app.clone(), vec![Exclusion::parse("com.ourcorp:c")])]),
+        );
+        let graph = resolver.resolve_project(&direct, &mut lockfile).await?;
+        let mut resolved: Vec<String> = graph.resolved.keys().map(|c| c.to_string()).collect();
+        resolved.sort();
+        assert_eq!(resolved, ["com.ourcorp:app", "com.ourcorp:b"]);
+        assert!(lockfile.deps["com.ourcorp:b"].deps.is_empty());
+        assert!(resolver.locked_graph(&direct, &lockfile)?.is_some());
+
+        // x is as near from r1, which excludes y, as from r2, which doesn't; the solver
+        // went by r2 and the lockfile walk reaches it from r1 first, but that's no reason to re-solve
+        module(m2.path(), "r1", "1.0", &[("x", "1.0")])?;
+        module(m2.path(), "r2", "1.0", &[("x", "1.0")])?;
+        module(m2.path(), "x", "1.0", &[("y", "1.0")])?;
+        module(m2.path(), "y", "1.0", &[])?;
+        let (r1, r2) = (Coordinate::parse("com.ourcorp:r1")?, Coordinate::parse("com.ourcorp:r2")?);
+        let resolver = offline_resolver(&repo, m2.path())?
+            .with_exclusions(Vec::new(), HashMap::from([(r1.clone(), vec![Exclusion::parse("com.ourcorp:y")])]));
+        let mut lockfile = Lockfile::new();
+        let graph = resolver.resolve_project(&[(r2.clone(), "1.0".to_string()), (r1.clone(), "1.0".to_string())], &mut lockfile).await?;
+        assert!(graph.resolved.contains_key(&Coordinate::parse("com.ourcorp:y")?));
+        let direct = [(r1, "1.0".to_string()), (r2, "1.0".to_string())];
+        let graph = resolver.locked_graph(&direct, &lockfile)?.unwrap();
+        assert_eq!(graph.resolved.len(), 4);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_locked_graph_per_platform() -> anyhow::Result<()> {
+        let temp = tempfile::TempDir::new()?;
+        let repo = Repository::new(temp.path().to_path_buf());
+        let manager = RepositoryManager::new(&Default::default(), &Default::default())?;
+
+        // one lockfile, written to by developers on two platforms
+        let lockfile: Lockfile = serde_json::from_value(serde_json::json!({
+            "version": 1,
+            "deps": {
+                "io.netty:netty-all": { "version": "4.1.100", "repository": "central", "integrity": "",
+                    "deps": ["io.netty:netty-transport-native-epoll:jar:${os.detected.classifier}"] },
+                "io.netty:netty-transport-native-epoll:jar:linux-x86_64": { "version": "4.1.100", "repository": "central",
+                    "integrity": "", "platform": "linux-x86_64", "deps": [] },
+                "io.netty:netty-transport-native-epoll:jar:linux-aarch_64": { "version": "4.1.100", "repository": "central",
+                    "integrity": "", "platform": "linux-aarch_64", "deps": [] },
+            }
+        }))?;
+        for key in lockfile.deps.keys() {
+            let jar = repo.get_artifact_path(&Coordinate::parse_unversioned(key)?, "4.1.100", ArtifactKind::Binary);
+            std::fs::create_dir_all(jar.parent().unwrap())?;
+            std::fs::write(jar, "jar")?;
+        }
+
+        let direct = vec![(Coordinate::parse("io.netty:netty-all")?, "4.1.100".to_string())];
+        for arch in ["x86_64", "aarch_64"] {
+            let resolver = DependencyResolver::new(repo.clone(), manager.clone())
+                .with_platform(Platform::new("linux", arch));
+            let graph = resolver.locked_graph(&direct, &lockfile)?.expect("everything is locked");
+
+            let native = graph.artifacts().into_iter().find_map(|(coord, _)| coord.classifier);
+            assert_eq!(native, Some(format!("linux-{}", arch)));
+        }
+
+        Ok(())
+    }
+}
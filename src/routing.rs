@@ -0,0 +1,111 @@
+use std::collections::HashMap;
+
+use crate::coordinates::Coordinate;
+
+/// Pins group patterns to the repositories allowed to serve them, so an
+/// internal namespace can't be satisfied by whoever publishes it on Central.
+///
+/// `com.ourcorp.*` matches `com.ourcorp` and everything below it, a plain
+/// group only matches itself and `*` matches every group. When several
+/// patterns match, the most specific one wins.
+#[derive(Clone, Debug, Default)]
+pub struct RoutingRules {
+    rules: Vec<(String, Vec<String>)>,
+}
+
+fn specificity(pattern: &str) -> (usize, bool) {
+    match pattern.strip_suffix('*') {
+        Some(prefix) => (prefix.trim_end_matches('.').len(), false),
+        None => (pattern.len(), true),
+    }
+}
+
+fn matches(pattern: &str, group: &str) -> bool {
+    if pattern == "*" {
+        return true;
+    }
+
+    match pattern.strip_suffix(".*") {
+        Some(prefix) => group == prefix || group.starts_with(&format!("{}.", prefix)),
+        None => group == pattern,
+    }
+}
+
+impl RoutingRules {
+    pub fn new(routes: &HashMap<String, Vec<String>>) -> Self {
+        let mut rules: Vec<(String, Vec<String>)> = routes.iter()
+            .map(|(pattern, repos)| (pattern.clone(), repos.clone()))
+            .collect();
+
+        // exact groups beat wildcards of the same length, longer prefixes beat shorter ones
+        rules.sort_by(|(a, _), (b, _)| specificity(b).cmp(&specificity(a)).then_with(|| a.cmp(b)));
+
+        Self { rules }
+    }
+
+    pub fn repositories(&self) -> impl Iterator<Item = &String> {
+        self.rules.iter().flat_map(|(_, repos)| repos)
+    }
+
+    /// Repositories the group is pinned to, or None when no rule covers it
+    pub fn allowed_for(&self, group: &str) -> Option<(&str, &[String])> {
+        self.rules.iter()
+            .find(|(pattern, _)| matches(pattern, group))
+            .map(|(pattern, repos)| (pattern.as_str(), repos.as_slice()))
+    }
+
+    pub fn is_allowed(&self, group: &str, repository: &str) -> bool {
+        match self.allowed_for(group) {
+            Some((_, repos)) => repos.iter().any(|r| r == repository),
+            None => true,
+        }
+    }
+
+    pub fn check(&self, coord: &Coordinate, repository: &str) -> anyhow::Result<()> {
+        match self.allowed_for(&coord.namespace) {
+            Some((pattern, repos)) if !repos.iter().any(|r| r == repository) => anyhow::bail!(
+                "{} came from repository '{}', but {} may only be served by: {}",
+                coord,
+                repository,
+                pattern,
+                repos.join(", ")
+            ),
+            _ => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rules() -> RoutingRules {
+        RoutingRules::new(&HashMap::from([
+            ("com.ourcorp.*".to_string(), vec!["internal".to_string()]),
+            ("com.ourcorp.oss".to_string(), vec!["internal".to_string(), "central".to_string()]),
+            ("*".to_string(), vec!["central".to_string()]),
+        ]))
+    }
+
+    #[test]
+    fn test_most_specific_rule_wins() {
+        let rules = rules();
+
+        assert_eq!(rules.allowed_for("com.ourcorp").unwrap().0, "com.ourcorp.*");
+        assert_eq!(rules.allowed_for("com.ourcorp.billing").unwrap().0, "com.ourcorp.*");
+        assert_eq!(rules.allowed_for("com.ourcorp.oss").unwrap().0, "com.ourcorp.oss");
+        assert_eq!(rules.allowed_for("com.ourcorporate").unwrap().0, "*");
+    }
+
+    #[test]
+    fn test_check_rejects_unlisted_repository() {
+        let rules = rules();
+        let coord = Coordinate::parse("com.ourcorp.billing:ledger").unwrap();
+
+        assert!(rules.check(&coord, "internal").is_ok());
+        let err = rules.check(&coord, "central").unwrap_err().to_string();
+        assert!(err.contains("com.ourcorp.* may only be served by: internal"));
+
+        assert!(RoutingRules::default().is_allowed("com.ourcorp", "central"));
+    }
+}
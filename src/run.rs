@@ -4,9 +4,6 @@ use walkdir::WalkDir;
 use crate::build::{BuildOptions, Builder};
 use crate::classpath;
 use crate::classpath::ClasspathBuilder;
-use crate::coordinates::Coordinate;
-use crate::manifest::Manifest;
-use crate::repository::Repository;
 
 pub struct RunOptions {
      pub(crate) debug: bool,
@@ -14,21 +11,20 @@ pub struct RunOptions {
  }
 
  pub struct Runner {
-     manifest: Manifest,
-     repository: Repository
+     builder: Builder,
  }
 
 impl Runner {
-    pub fn new(manifest: Manifest, repository: Repository) -> Self {
+    /// Runs what `builder` compiles, recompiling first when sources changed
+    pub fn new(builder: Builder) -> Self {
         Self {
-            manifest,
-            repository
+            builder
         }
     }
 
     fn needs_compilation(&self) -> anyhow::Result<bool> {
-        let target_dir = std::env::current_dir()?.join("target").join("classes");
-        let src_dir = std::env::current_dir()?.join("src").join("main").join("java");
+        let target_dir = &self.builder.layout().classes;
+        let src_dir = &self.builder.layout().sources;
 
         if !target_dir.exists() {
             return Ok(true);
@@ -47,7 +43,7 @@ impl Runner {
         }
 
         let mut oldest_class = SystemTime::now();
-        for entry in WalkDir::new(&target_dir) {
+        for entry in WalkDir::new(target_dir) {
             let entry = entry?;
             if entry.path().extension().and_then(|s| s.to_str()) == Some("class") {
                 if let Ok(modified) = entry.metadata()?.modified() {
@@ -64,27 +60,25 @@ impl Runner {
     pub fn run(&self, options: RunOptions) -> anyhow::Result<()> {
         // Check if we need to recompile and do so if necessary
         if self.needs_compilation()? {
-            let builder = Builder::new(self.manifest.clone(), self.repository.clone());
-            builder.build(BuildOptions {
+            self.builder.build(BuildOptions {
                 debug: options.debug,
             })?;
         }
 
         // Set up the runtime classpath
-        let mut cp_builder = ClasspathBuilder::new(self.repository.clone());
+        let mut cp_builder = ClasspathBuilder::new(self.builder.repository().clone());
 
-        // Add target/classes as the first entry
-        let target_dir = std::env::current_dir()?.join("target").join("classes");
+        // Add target/classes as the first entry, resources are read straight from their directories
+        let layout = self.builder.layout();
+        let target_dir = layout.classes.clone();
         cp_builder.with_dir(target_dir.clone());
+        for dir in layout.resources.iter().filter(|dir| dir.exists()) {
+            cp_builder.with_dir(dir.clone());
+        }
 
         // Add all dependencies
-        for (coord_str, dep_spec) in &self.manifest.deps {
-            let coord = Coordinate::parse(coord_str)?;
-            let version = match dep_spec {
-                crate::manifest::DepSpec::Simple(v) => v,
-                crate::manifest::DepSpec::Detailed { version, .. } => version,
-            };
-            cp_builder.with_dep(&coord, version);
+        for (coord, version) in self.builder.dependencies()? {
+            cp_builder.with_dep(&coord, &version);
         }
 
         // Build final classpath
@@ -93,7 +87,7 @@ impl Runner {
         // Resolve and verify main class
         let main_class = classpath::resolve_main_class(
             &target_dir,
-            self.manifest.project.main_class.clone()
+            self.builder.manifest().project.main_class.clone()
         )?;
 
         // Prepare java command
@@ -0,0 +1,52 @@
+use std::path::Path;
+
+use crate::coordinates::Coordinate;
+use crate::download::RepositoryManager;
+use crate::repository::Repository;
+use crate::resolver::DependencyResolver;
+
+/// Puts files in a Maven local repository as if they had been installed there
+pub fn publish(m2: &Path, coord: &str, version: &str, files: &[(&str, &[u8])]) -> anyhow::Result<()> {
+    let dir = m2.join(Coordinate::parse(coord)?.to_path()).join(version);
+    std::fs::create_dir_all(&dir)?;
+    let mut marker = String::new();
+    for (name, content) in files {
+        std::fs::write(dir.join(name), content)?;
+        marker.push_str(&format!("{}>=\n", name));
+    }
+    std::fs::write(dir.join("_remote.repositories"), marker)?;
+    Ok(())
+}
+
+/// Publishes com.ourcorp:`artifact` with a jar and a pom depending on each
+/// com.ourcorp (artifactId, version) in `deps`
+pub fn module(m2: &Path, artifact: &str, version: &str, deps: &[(&str, &str)]) -> anyhow::Result<()> {
+    let deps: String = deps.iter().map(|(a, v)| format!(
+        "<dependency><groupId>com.ourcorp</groupId><artifactId>{}</artifactId><version>{}</version></dependency>", a, v
+    )).collect();
+    let pom = format!(
+        "<project><groupId>com.ourcorp</groupId><artifactId>{}</artifactId><version>{}</version>\
+        <dependencies>{}</dependencies></project>", artifact, version, deps
+    );
+    publish(m2, &format!("com.ourcorp:{}", artifact), version, &[
+        (&format!("{}-{}.pom", artifact, version), pom.as_bytes()),
+        (&format!("{}-{}.jar", artifact, version), b"jar"),
+    ])
+}
+
+/// A repository manager that only sees the Maven local repository at `m2`
+pub fn offline_manager(m2: &Path) -> anyhow::Result<RepositoryManager> {
+    let mut repositories = crate::manifest::Repositories::new();
+    repositories.insert("central".to_string(), crate::manifest::RepositoryConfig {
+        url: "http://127.0.0.1:9".to_string(),
+        username: None,
+        password: None,
+    });
+    let http = crate::http::HttpConfig { retries: Some(0), ..Default::default() };
+    Ok(RepositoryManager::new(&http, &repositories)?.with_local_maven(m2.to_path_buf()))
+}
+
+/// A resolver that only sees the Maven local repository at `m2`
+pub fn offline_resolver(repo: &Repository, m2: &Path) -> anyhow::Result<DependencyResolver> {
+    Ok(DependencyResolver::new(repo.clone(), offline_manager(m2)?))
+}
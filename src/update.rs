@@ -0,0 +1,218 @@
+use std::collections::{BTreeMap, HashMap};
+
+use crate::coordinates::Coordinate;
+use crate::download::RepositoryManager;
+use crate::lockfile::Lockfile;
+use crate::manifest::Manifest;
+use crate::platform::Platform;
+use crate::version::{MavenVersion, VersionReq};
+
+/// Works out what `gallade update` may move before anything is resolved: which
+/// locked versions are kept and which manifest requirements get bumped
+pub struct Updater<'a> {
+    manager: &'a RepositoryManager,
+    /// Modules to update, everything when empty
+    targets: Vec<Coordinate>,
+}
+
+impl<'a> Updater<'a> {
+    pub fn new(manager: &'a RepositoryManager, targets: Vec<Coordinate>, manifest: &Manifest, lockfile: &Lockfile) -> anyhow::Result<Self> {
+        for target in &targets {
+            let declared = manifest.deps.iter().any(|(name, spec)| spec.coordinate(name).is_ok_and(|c| &c == target));
+            if !declared && !lockfile.deps.contains_key(&target.to_string()) {
+                anyhow::bail!("{} isn't a dependency of this project", target);
+            }
+        }
+        Ok(Self { manager, targets })
+    }
+
+    fn is_target(&self, coord: &Coordinate) -> bool {
+        self.targets.is_empty() || self.targets.contains(coord)
+    }
+
+    /// Locked versions that should stay put, which is all but the targets' ones
+    pub fn preferred(&self, lockfile: &Lockfile) -> HashMap<Coordinate, MavenVersion> {
+        let mut versions = lockfile.versions();
+        versions.retain(|coord, _| !self.is_target(coord));
+        versions
+    }
+
+    /// Moves the manifest requirement of every targeted direct dependency to its
+    /// newest release, unless that already satisfies it. A range is replaced by the
+    /// bare version, so the changes are returned as (dependency, old, new) to be shown.
+    pub async fn bump_latest(&self, manifest: &mut Manifest) -> anyhow::Result<Vec<(String, String, String)>> {
+        let mut changes = Vec::new();
+        for (name, spec) in manifest.deps.iter_mut() {
+            let coord = spec.coordinate(name)?;
+            if !self.is_target(&coord) {
+                continue;
+            }
+            let Some(newest) = newest(self.manager, &coord).await? else {
+                continue;
+            };
+
+            let req = VersionReq::declared(spec.version())?;
+            // never a downgrade, e.g. from a milestone newer than the last release
+            if req.matches(&newest) || req.pinned().is_some_and(|v| v >= &newest) {
+                continue;
+            }
+            changes.push((name.clone(), spec.version().to_string(), newest.to_string()));
+            spec.set_version(newest.to_string());
+        }
+        Ok(changes)
+    }
+
+    /// Asks for exactly `version` of the single target, rewriting its manifest
+    /// requirement when it's a direct dependency that doesn't allow it. Returns
+    /// whether the manifest changed.
+    pub fn pin(&self, manifest: &mut Manifest, preferred: &mut HashMap<Coordinate, MavenVersion>, version: &str) -> anyhow::Result<bool> {
+        let [target] = self.targets.as_slice() else {
+            anyhow::bail!("--precise needs exactly one artifact to update");
+        };
+        let precise: MavenVersion = version.parse()?;
+        preferred.insert(target.clone(), precise.clone());
+
+        for (name, spec) in manifest.deps.iter_mut() {
+            if &spec.coordinate(name)? == target && !VersionReq::declared(spec.version())?.matches(&precise) {
+                spec.set_version(version.to_string());
+                return Ok(true);
+            }
+        }
+        Ok(false)
+    }
+
+    /// Fails unless the pinned target really ended up at `version` in `resolved`
+    pub fn check_pinned(&self, resolved: &HashMap<Coordinate, MavenVersion>, version: &str) -> anyhow::Result<()> {
+        let precise: MavenVersion = version.parse()?;
+        for target in &self.targets {
+            match resolved.get(target) {
+                Some(resolved) if resolved == &precise => {}
+                Some(resolved) => anyhow::bail!(
+                    "{} can't be {}, another dependency requires a version it doesn't match so it stays at {}",
+                    target, version, resolved
+                ),
+                None => anyhow::bail!("{} is no longer a dependency of this project", target),
+            }
+        }
+        Ok(())
+    }
+}
+
+/// The newest release of `coord`, or the newest pre-release when that's all there is
+pub async fn newest(manager: &RepositoryManager, coord: &Coordinate) -> anyhow::Result<Option<MavenVersion>> {
+    let versions: Vec<MavenVersion> = manager.search_versions(coord).await?
+        .iter()
+        .filter_map(|v| v.parse().ok())
+        .collect();
+    let release = versions.iter().filter(|v| !v.is_prerelease()).max();
+    Ok(release.or_else(|| versions.iter().max()).cloned())
+}
+
+/// What resolving to `planned` would change in the lockfile, listed the way
+/// `Lockfile::changes_since` does. Other platforms' native artifacts aren't
+/// looked at by a resolution, so they're left out.
+pub fn planned_changes(lockfile: &Lockfile, planned: &HashMap<Coordinate, MavenVersion>) -> Vec<(String, Option<String>, Option<String>)> {
+    let current = Platform::current().classifier();
+    let mut changes: BTreeMap<String, (Option<String>, Option<String>)> = lockfile.deps.iter()
+        .filter(|(_, info)| info.platform.as_ref().is_none_or(|p| *p == current))
+        .map(|(coord, info)| (coord.clone(), (Some(info.version.clone()), None)))
+        .collect();
+    for (coord, version) in planned {
+        changes.entry(coord.to_string()).or_default().1 = Some(version.to_string());
+    }
+    changes.into_iter()
+        .filter(|(_, (old, new))| old != new)
+        .map(|(coord, (old, new))| (coord, old, new))
+        .collect()
+}
+
+/// Prints lockfile changes as an aligned old -> new table
+pub fn print_changes(changes: &[(String, Option<String>, Option<String>)]) {
+    if changes.is_empty() {
+        println!("everything is up to date");
+        return;
+    }
+
+    let old = |old: &Option<String>| old.clone().unwrap_or_else(|| "(new)".to_string());
+    let name_width = changes.iter().map(|(coord, _, _)| coord.len()).max().unwrap_or(0);
+    let old_width = changes.iter().map(|(_, v, _)| old(v).len()).max().unwrap_or(0);
+    for (coord, before, after) in changes {
+        println!(
+            "  {:name_width$}  {:old_width$} -> {}",
+            coord,
+            old(before),
+            after.as_deref().unwrap_or("(removed)"),
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{offline_manager, publish};
+
+    #[tokio::test]
+    async fn test_bump_and_pin() -> anyhow::Result<()> {
+        let m2 = tempfile::TempDir::new()?;
+        for version in ["1.0", "2.0", "3.0-M1"] {
+            publish(m2.path(), "com.ourcorp:b", version, &[(&format!("b-{}.pom", version), b"<project/>")])?;
+        }
+        let manager = offline_manager(m2.path())?;
+
+        let mut manifest: Manifest = toml::from_str(r#"
+            [project]
+            name = "app"
+            version = "1.0"
+
+            [deps]
+            "com.ourcorp:b" = "[1.0,2.0)"
+        "#)?;
+        let lockfile = Lockfile::new();
+        let b = Coordinate::parse("com.ourcorp:b")?;
+
+        assert!(Updater::new(&manager, vec![Coordinate::parse("com.ourcorp:c")?], &manifest, &lockfile).is_err());
+
+        // the milestone isn't a release, so 2.0 is the latest
+        let updater = Updater::new(&manager, Vec::new(), &manifest, &lockfile)?;
+        // the range is gone, which is reported rather than done quietly
+        let changes = updater.bump_latest(&mut manifest).await?;
+        assert_eq!(changes, vec![("com.ourcorp:b".to_string(), "[1.0,2.0)".to_string(), "2.0".to_string())]);
+        assert_eq!(manifest.deps["com.ourcorp:b"].version(), "2.0");
+        assert!(updater.bump_latest(&mut manifest).await?.is_empty());
+
+        let updater = Updater::new(&manager, vec![b.clone()], &manifest, &lockfile)?;
+        let mut preferred = HashMap::new();
+        assert!(updater.pin(&mut manifest, &mut preferred, "1.0")?);
+        assert_eq!(manifest.deps["com.ourcorp:b"].version(), "1.0");
+        assert_eq!(preferred[&b].to_string(), "1.0");
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_planned_changes() -> anyhow::Result<()> {
+        let elsewhere = if Platform::current().name == "osx" { "windows-x86_64" } else { "osx-aarch_64" };
+        let lockfile: Lockfile = serde_json::from_str(&format!(r#"{{"version": 1, "deps": {{
+            "com.ourcorp:b": {{ "version": "1.0", "repository": "central", "integrity": "", "deps": [] }},
+            "com.ourcorp:c": {{ "version": "4.0", "repository": "central", "integrity": "", "deps": [] }},
+            "com.ourcorp:e": {{ "version": "1.0", "repository": "central", "integrity": "", "deps": [] }},
+            "com.ourcorp:native:jar:{0}": {{ "version": "1.0", "repository": "central", "integrity": "",
+                "platform": "{0}", "deps": [] }}
+        }}}}"#, elsewhere))?;
+        let planned = HashMap::from([
+            (Coordinate::parse("com.ourcorp:b")?, "1.5".parse()?),
+            (Coordinate::parse("com.ourcorp:d")?, "2.0".parse()?),
+            (Coordinate::parse("com.ourcorp:e")?, "1.0".parse()?),
+        ]);
+
+        // another platform's native artifact isn't something this resolution removes
+        let changes = planned_changes(&lockfile, &planned);
+        assert_eq!(changes, vec![
+            ("com.ourcorp:b".to_string(), Some("1.0".to_string()), Some("1.5".to_string())),
+            ("com.ourcorp:c".to_string(), Some("4.0".to_string()), None),
+            ("com.ourcorp:d".to_string(), None, Some("2.0".to_string())),
+        ]);
+
+        Ok(())
+    }
+}
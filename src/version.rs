@@ -2,12 +2,48 @@ use std::cmp::Ordering;
 use std::fmt;
 use std::str::FromStr;
 
-#[derive(Debug, Clone, Eq, Hash)]
+/// A Maven version, ordered roughly the way Maven's ComparableVersion orders them.
+/// It's split into numbers and qualifiers at every `.`, `-` and switch between
+/// digits and letters, so `4.1.100.Final`, `31.1-jre` and `1.0-rc1` all work, and
+/// it's displayed exactly as written.
+#[derive(Debug, Clone)]
 pub struct MavenVersion {
-    major: u32,
-    minor: u32,
-    patch: u32,
-    qualifier: Option<String>,
+    raw: String,
+    items: Vec<Item>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+enum Item {
+    Number(u64),
+    Qualifier(String),
+}
+
+// Where a qualifier sorts, an empty one being a release. Anything unknown counts
+// as a pre-release of the same version, e.g. `31.1-jre` comes before `31.1`.
+fn qualifier_rank(qualifier: &str) -> (u8, &str) {
+    match qualifier {
+        "alpha" | "a" => (0, ""),
+        "beta" | "b" => (1, ""),
+        "milestone" | "m" => (2, ""),
+        "rc" | "cr" => (3, ""),
+        "snapshot" => (4, ""),
+        "" => (6, ""),
+        "sp" => (7, ""),
+        other => (5, other),
+    }
+}
+
+impl Item {
+    fn cmp_to(&self, other: Option<&Item>) -> Ordering {
+        match (self, other) {
+            (Item::Number(a), Some(Item::Number(b))) => a.cmp(b),
+            (Item::Number(a), None) => a.cmp(&0),
+            (Item::Number(_), Some(Item::Qualifier(_))) => Ordering::Greater,
+            (Item::Qualifier(_), Some(Item::Number(_))) => Ordering::Less,
+            (Item::Qualifier(a), Some(Item::Qualifier(b))) => qualifier_rank(a).cmp(&qualifier_rank(b)),
+            (Item::Qualifier(a), None) => qualifier_rank(a).cmp(&qualifier_rank("")),
+        }
+    }
 }
 
 #[derive(Debug)]
@@ -38,45 +74,76 @@ impl FromStr for MavenVersion {
     type Err = VersionParseError;
 
     fn from_str(version: &str) -> Result<Self, Self::Err> {
-        // Split version and qualifier
-        let (version_part, qualifier) = match version.split_once('-') {
-            Some((v, q)) => (v, Some(q.to_string())),
-            None => (version, None),
-        };
-
-        // Parse numeric components
-        let nums: Vec<&str> = version_part.split('.').collect();
-
-        match nums.len() {
-            3 => Ok(Self {
-                major: nums[0].parse().map_err(VersionParseError::InvalidNumber)?,
-                minor: nums[1].parse().map_err(VersionParseError::InvalidNumber)?,
-                patch: nums[2].parse().map_err(VersionParseError::InvalidNumber)?,
-                qualifier,
-            }),
-            2 => Ok(Self {
-                major: nums[0].parse().map_err(VersionParseError::InvalidNumber)?,
-                minor: nums[1].parse().map_err(VersionParseError::InvalidNumber)?,
-                patch: 0,
-                qualifier,
-            }),
-            1 => Ok(Self {
-                major: nums[0].parse().map_err(VersionParseError::InvalidNumber)?,
-                minor: 0,
-                patch: 0,
-                qualifier,
-            }),
-            _ => Err(VersionParseError::InvalidFormat),
+        let version = version.trim();
+        // a version has to start with a number, anything else is a name or a typo
+        if !version.starts_with(|c: char| c.is_ascii_digit()) {
+            return Err(VersionParseError::InvalidFormat);
+        }
+
+        let mut items = Vec::new();
+        for part in version.split(['.', '-', '_']) {
+            let mut rest = part;
+            while !rest.is_empty() {
+                let digits = rest.starts_with(|c: char| c.is_ascii_digit());
+                let len = rest.find(|c: char| c.is_ascii_digit() != digits).unwrap_or(rest.len());
+                let (token, tail) = rest.split_at(len);
+                items.push(if digits {
+                    Item::Number(token.parse().map_err(VersionParseError::InvalidNumber)?)
+                } else {
+                    match token.to_lowercase().as_str() {
+                        "ga" | "final" | "release" => Item::Qualifier(String::new()),
+                        other => Item::Qualifier(other.to_string()),
+                    }
+                });
+                rest = tail;
+            }
+        }
+
+        // 1, 1.0, 1.0.0 and 1.0.Final are all the same version, and so are 1-rc and 1.0-rc
+        let is_padding = |item: &Item| matches!(item, Item::Number(0)) || *item == Item::Qualifier(String::new());
+        while items.len() > 1 && items.last().is_some_and(is_padding) {
+            items.pop();
+        }
+        let mut normalized: Vec<Item> = Vec::with_capacity(items.len());
+        for item in items {
+            if matches!(item, Item::Qualifier(_)) {
+                while normalized.len() > 1 && normalized.last() == Some(&Item::Number(0)) {
+                    normalized.pop();
+                }
+            }
+            normalized.push(item);
+        }
+
+        Ok(Self { raw: version.to_string(), items: normalized })
+    }
+}
+
+impl MavenVersion {
+    /// The first number, e.g. 32 for 32.1.3-jre
+    pub fn major(&self) -> u64 {
+        match self.items.first() {
+            Some(Item::Number(major)) => *major,
+            _ => 0,
         }
     }
+
+    /// Alphas, betas, milestones, release candidates and snapshots
+    pub fn is_prerelease(&self) -> bool {
+        self.items.iter().any(|item| matches!(item, Item::Qualifier(q) if qualifier_rank(q).0 < 5))
+    }
 }
 
 impl PartialEq for MavenVersion {
     fn eq(&self, other: &Self) -> bool {
-        self.major == other.major
-            && self.minor == other.minor
-            && self.patch == other.patch
-            && self.qualifier == other.qualifier
+        self.items == other.items
+    }
+}
+
+impl Eq for MavenVersion {}
+
+impl std::hash::Hash for MavenVersion {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        self.items.hash(state);
     }
 }
 
@@ -88,34 +155,24 @@ impl PartialOrd for MavenVersion {
 
 impl fmt::Display for MavenVersion {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        match &self.qualifier {
-            Some(q) => write!(f, "{}.{}.{}-{}", self.major, self.minor, self.patch, q),
-            None => write!(f, "{}.{}.{}", self.major, self.minor, self.patch)
-        }
+        write!(f, "{}", self.raw)
     }
 }
 
 impl Ord for MavenVersion {
     fn cmp(&self, other: &Self) -> Ordering {
-        match self.major.cmp(&other.major) {
-            Ordering::Equal => {},
-            ord => return ord,
-        }
-        match self.minor.cmp(&other.minor) {
-            Ordering::Equal => {},
-            ord => return ord,
-        }
-        match self.patch.cmp(&other.patch) {
-            Ordering::Equal => {},
-            ord => return ord,
-        }
-
-        match (&self.qualifier, &other.qualifier) {
-            (None, None) => Ordering::Equal,
-            (Some(_), None) => Ordering::Less,
-            (None, Some(_)) => Ordering::Greater,
-            (Some(a), Some(b)) => a.cmp(b),
+        let len = self.items.len().max(other.items.len());
+        for i in 0..len {
+            let ord = match (self.items.get(i), other.items.get(i)) {
+                (Some(a), b) => a.cmp_to(b),
+                (None, Some(b)) => b.cmp_to(None).reverse(),
+                (None, None) => Ordering::Equal,
+            };
+            if ord != Ordering::Equal {
+                return ord;
+            }
         }
+        Ordering::Equal
     }
 }
 
@@ -155,6 +212,17 @@ impl VersionReq {
             let content = &input[1..input.len()-1];
             let parts: Vec<&str> = content.split(',').collect();
 
+            // [1.0] means exactly 1.0, nothing else will do
+            if parts.len() == 1 && min_inclusive && max_inclusive {
+                let version: MavenVersion = parts[0].trim().parse()?;
+                return Ok(Self::Range {
+                    min: Some(version.clone()),
+                    min_inclusive: true,
+                    max: Some(version),
+                    max_inclusive: true,
+                });
+            }
+
             if parts.len() != 2 {
                 anyhow::bail!("invalid range format: expected two versions separated by comma");
             }
@@ -206,6 +274,52 @@ impl VersionReq {
             Self::Latest | Self::Release => true,
         }
     }
+
+    /// A version as declared for one of the project's own dependencies, where a
+    /// plain version means exactly that one
+    pub fn declared(version: &str) -> anyhow::Result<Self> {
+        if version.trim().starts_with(['[', '(']) {
+            Self::parse(version)
+        } else {
+            Self::parse(&format!("[{}]", version.trim()))
+        }
+    }
+
+    /// Whether this has to be satisfied. A plain version is only a preference,
+    /// the nearest one wins like in Maven, but a range is a requirement.
+    pub fn is_hard(&self) -> bool {
+        matches!(self, Self::Range { .. })
+    }
+
+    /// The single version this asks for, if it asks for one
+    pub fn pinned(&self) -> Option<&MavenVersion> {
+        match self {
+            Self::Exact(version) => Some(version),
+            Self::Range { min: Some(min), max: Some(max), min_inclusive: true, max_inclusive: true } if min == max => Some(min),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for VersionReq {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Exact(version) => write!(f, "{}", version),
+            Self::Range { min: Some(min), max: Some(max), min_inclusive: true, max_inclusive: true } if min == max => {
+                write!(f, "[{}]", min)
+            }
+            Self::Range { min, min_inclusive, max, max_inclusive } => write!(
+                f,
+                "{}{},{}{}",
+                if *min_inclusive { '[' } else { '(' },
+                min.as_ref().map(|v| v.to_string()).unwrap_or_default(),
+                max.as_ref().map(|v| v.to_string()).unwrap_or_default(),
+                if *max_inclusive { ']' } else { ')' },
+            ),
+            Self::Latest => write!(f, "LATEST"),
+            Self::Release => write!(f, "RELEASE"),
+        }
+    }
 }
 
 impl FromStr for VersionReq {
@@ -216,6 +330,7 @@ impl FromStr for VersionReq {
     }
 }
 
+#[cfg(test)]
 mod tests {
     use super::*;
 
@@ -277,4 +392,28 @@ mod tests {
         assert!(v1 > v3);
         assert!(v2 > v3);
     }
+
+    #[test]
+    fn test_maven_ordering() {
+        let v = |s: &str| s.parse::<MavenVersion>().unwrap();
+
+        assert_eq!(v("4.1.100.Final").to_string(), "4.1.100.Final");
+        assert_eq!(v("31.1-jre").to_string(), "31.1-jre");
+        assert_eq!(v("1.0"), v("1.0.0.Final"));
+        assert!(v("4.1.100.Final") > v("4.1.99.Final"));
+        assert!(v("1.0-alpha1") < v("1.0-beta"));
+        assert!(v("1.0-rc1") < v("1.0-rc2"));
+        assert!(v("1.0-rc2") < v("1.0"));
+        assert!(v("1.0") < v("1.0-sp1"));
+        assert!(v("1.0.1") > v("1.0-sp1"));
+        assert_eq!(v("32.1.3-jre").major(), 32);
+        assert!(v("2.0-M1").is_prerelease() && v("1.0-SNAPSHOT").is_prerelease());
+        assert!(!v("31.1-jre").is_prerelease() && !v("1.0.Final").is_prerelease());
+
+        let pin = VersionReq::parse("[1.5]").unwrap();
+        assert_eq!(pin.pinned(), Some(&v("1.5")));
+        assert!(pin.is_hard() && !VersionReq::parse("1.5").unwrap().is_hard());
+        assert_eq!(pin.to_string(), "[1.5]");
+        assert_eq!(VersionReq::parse("[1.0,2.0)").unwrap().to_string(), "[1.0,2.0)");
+    }
 }
\ No newline at end of file
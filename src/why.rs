@@ -0,0 +1,274 @@
+use std::collections::HashMap;
+
+use crate::coordinates::Coordinate;
+use crate::lockfile::Lockfile;
+use crate::manifest::Manifest;
+use crate::resolver::{DependencyResolver, ReverseDependencyGraph};
+use crate::version::{MavenVersion, VersionReq};
+
+// Enough to see what's going on without printing half the graph
+const MAX_PATHS: usize = 20;
+const MAX_STEPS: usize = 10_000;
+
+// One hop of a path, what `from` asks for. `from` is None for gallade.toml.
+struct Request {
+    from: Option<Coordinate>,
+    req: VersionReq,
+    /// The version or range as it was written
+    written: String,
+    scope: String,
+}
+
+/// Explains how a module ended up in gallade.lock: every path to it from the
+/// manifest, and why its version won
+pub struct Explainer<'a> {
+    resolver: &'a DependencyResolver,
+    lockfile: &'a Lockfile,
+    dependents: ReverseDependencyGraph,
+    /// The manifest's dependencies, with their version and scope
+    direct: HashMap<Coordinate, (String, Option<String>)>,
+    constraints: HashMap<Coordinate, VersionReq>,
+}
+
+impl<'a> Explainer<'a> {
+    pub fn new(resolver: &'a DependencyResolver, manifest: &Manifest, lockfile: &'a Lockfile) -> anyhow::Result<Self> {
+        let direct = manifest.deps.iter()
+            .map(|(name, spec)| Ok((spec.coordinate(name)?, (spec.version().to_string(), spec.scope().map(String::from)))))
+            .collect::<anyhow::Result<_>>()?;
+        Ok(Self {
+            resolver,
+            lockfile,
+            dependents: ReverseDependencyGraph::from_lockfile(lockfile)?,
+            direct,
+            constraints: manifest.parsed_constraints()?,
+        })
+    }
+
+    fn locked(&self, coord: &Coordinate) -> Option<&str> {
+        self.lockfile.deps.get(&coord.to_string()).map(|info| info.version.as_str())
+    }
+
+    // forced by `[overrides]` when the lockfile was written
+    fn overridden(&self, coord: &Coordinate) -> bool {
+        self.lockfile.deps.get(&coord.to_string()).is_some_and(|info| info.overridden)
+    }
+
+    fn label(&self, coord: Option<&Coordinate>) -> String {
+        match coord {
+            Some(coord) => format!("{} {}", coord, self.locked(coord).unwrap_or("?")),
+            None => "gallade.toml".to_string(),
+        }
+    }
+
+    /// Every path from a direct dependency down to `target`, shortest first, and how many there were
+    fn paths(&self, target: &Coordinate) -> (Vec<Vec<Coordinate>>, usize) {
+        let mut found = Vec::new();
+        let mut steps = 0;
+        self.walk(&mut vec![target.clone()], &mut found, &mut steps);
+
+        let total = found.len();
+        found.sort_by_key(|path| path.len());
+        found.truncate(MAX_PATHS);
+        (found, total)
+    }
+
+    // Climbs from the end of `path` towards the manifest, `path` going upwards
+    fn walk(&self, path: &mut Vec<Coordinate>, found: &mut Vec<Vec<Coordinate>>, steps: &mut usize) {
+        *steps += 1;
+        if *steps > MAX_STEPS {
+            return;
+        }
+        let node = path.last().unwrap().clone();
+        if self.direct.contains_key(&node) {
+            found.push(path.iter().rev().cloned().collect());
+        }
+
+        let mut dependents: Vec<_> = self.dependents.get_dependents(&node).into_iter().collect();
+        dependents.sort_by_key(|c| c.to_string());
+        for dependent in dependents {
+            if path.contains(&dependent) {
+                continue;
+            }
+            path.push(dependent);
+            self.walk(path, found, steps);
+            path.pop();
+        }
+    }
+
+    // What `from` asks for of `to`, None when its metadata doesn't mention it
+    async fn request(&self, from: Option<&Coordinate>, to: &Coordinate) -> anyhow::Result<Option<Request>> {
+        let Some(from) = from else {
+            let Some((version, scope)) = self.direct.get(to) else {
+                return Ok(None);
+            };
+            return Ok(Some(Request {
+                from: None,
+                req: VersionReq::declared(version)?,
+                written: version.clone(),
+                scope: scope.clone().unwrap_or_else(|| "compile".to_string()),
+            }));
+        };
+
+        let version = self.locked(from).ok_or_else(|| anyhow::anyhow!("{} isn't in gallade.lock", from))?;
+        let request = self.resolver.dependencies_of(from, version).await?
+            .into_iter()
+            .find(|dep| &dep.coordinate == to)
+            .map(|dep| Request {
+                from: Some(from.clone()),
+                written: dep.version_req.to_string(),
+                req: dep.version_req,
+                scope: dep.scope.unwrap_or_else(|| "compile".to_string()),
+            });
+        Ok(request)
+    }
+
+    pub async fn explain(&self, target: &Coordinate) -> anyhow::Result<()> {
+        let Some(version) = self.locked(target) else {
+            anyhow::bail!("{} isn't in gallade.lock", target);
+        };
+        let (paths, total) = self.paths(target);
+        if paths.is_empty() {
+            anyhow::bail!("nothing in gallade.toml leads to {} anymore - gallade update will drop it", target);
+        }
+
+        // whoever asks for the target directly, nearest first
+        let mut requests: Vec<(usize, Request)> = Vec::new();
+        for path in &paths {
+            let from = path.len().checked_sub(2).map(|i| &path[i]);
+            if requests.iter().any(|(_, r)| r.from.as_ref() == from) {
+                continue;
+            }
+            if let Some(request) = self.request(from, target).await? {
+                requests.push((path.len(), request));
+            }
+        }
+
+        println!("{} {}", target, version);
+        println!("  {}", self.reason(target, &version.parse()?, &requests));
+
+        for path in &paths {
+            println!();
+            for (depth, to) in path.iter().enumerate() {
+                let from = depth.checked_sub(1).map(|i| &path[i]);
+                let indent = "  ".repeat(depth);
+                let forced = if self.overridden(to) { " (overridden)" } else { "" };
+                let got = format!("{}{}", self.locked(to).unwrap_or("?"), forced);
+                match self.request(from, to).await? {
+                    Some(request) => {
+                        let verb = if request.req.is_hard() && from.is_some() { "requires" } else { "asks for" };
+                        println!(
+                            "{}{} {} {} {} ({}), got {}",
+                            indent, self.label(from), verb, to, request.written, request.scope, got
+                        );
+                    }
+                    None => println!("{}{} brings in {}, got {}", indent, self.label(from), to, got),
+                }
+            }
+        }
+        if total > paths.len() {
+            println!("\n... and {} more paths", total - paths.len());
+        }
+
+        Ok(())
+    }
+
+    // Why `locked` won, going by the same preferences the solver has
+    fn reason(&self, target: &Coordinate, locked: &MavenVersion, requests: &[(usize, Request)]) -> String {
+        if self.overridden(target) {
+            return format!("forced to {} by [overrides] in gallade.toml", locked);
+        }
+        let reason = self.preference(locked, requests);
+        match self.constraints.get(target) {
+            Some(constraint) => format!("{}, within {} from [constraints] in gallade.toml", reason, constraint),
+            None => reason,
+        }
+    }
+
+    fn preference(&self, locked: &MavenVersion, requests: &[(usize, Request)]) -> String {
+        if requests.iter().any(|(_, r)| r.from.is_none()) {
+            return "declared in gallade.toml".to_string();
+        }
+
+        let ranges: Vec<&Request> = requests.iter().map(|(_, r)| r).filter(|r| r.req.is_hard()).collect();
+        let fits = |v: &MavenVersion| ranges.iter().all(|r| r.req.matches(v));
+        let nearest = requests.iter().find(|(_, r)| r.req.pinned().is_some_and(fits));
+
+        match nearest {
+            Some((depth, request)) if request.req.pinned() == Some(locked) => {
+                let others: Vec<String> = requests.iter()
+                    .filter(|(_, r)| r.req.pinned().is_some_and(|v| v != locked))
+                    .map(|(_, r)| format!("{} from {}", r.written, self.label(r.from.as_ref())))
+                    .collect();
+                let over = if others.is_empty() { String::new() } else { format!(", over {}", others.join(", ")) };
+                format!("the nearest request wins: {} asks for it at depth {}{}", self.label(request.from.as_ref()), depth, over)
+            }
+            _ if !ranges.is_empty() && fits(locked) => {
+                let ranges: Vec<String> = ranges.iter()
+                    .map(|r| format!("{} from {}", r.written, self.label(r.from.as_ref())))
+                    .collect();
+                format!("fits every range asked for: {}", ranges.join(", "))
+            }
+            _ => "kept at the version already in gallade.lock".to_string(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::repository::Repository;
+    use crate::testing::{module, offline_resolver};
+
+    #[tokio::test]
+    async fn test_paths_and_reason() -> anyhow::Result<()> {
+        let m2 = tempfile::TempDir::new()?;
+        module(m2.path(), "app", "1.0", &[("a", "1.0"), ("c", "1.5")])?;
+        module(m2.path(), "a", "1.0", &[("c", "1.0")])?;
+        module(m2.path(), "c", "1.5", &[])?;
+
+        let temp = tempfile::TempDir::new()?;
+        let resolver = offline_resolver(&Repository::new(temp.path().to_path_buf()), m2.path())?;
+
+        let manifest: Manifest = toml::from_str(r#"
+            [project]
+            name = "demo"
+            version = "1.0"
+
+            [deps]
+            "com.ourcorp:app" = "1.0"
+        "#)?;
+        let app = Coordinate::parse("com.ourcorp:app")?;
+        let mut lockfile = Lockfile::new();
+        resolver.resolve_project(&[(app.clone(), "1.0".to_string())], &mut lockfile).await?;
+
+        let explainer = Explainer::new(&resolver, &manifest, &lockfile)?;
+        let c = Coordinate::parse("com.ourcorp:c")?;
+        let (paths, total) = explainer.paths(&c);
+        assert_eq!(total, 2);
+        assert_eq!(paths[0], vec![app.clone(), c.clone()]);
+        assert_eq!(paths[1].len(), 3);
+
+        let mut requests = Vec::new();
+        for path in &paths {
+            let request = explainer.request(Some(&path[path.len() - 2]), &c).await?.unwrap();
+            requests.push((path.len(), request));
+        }
+        assert_eq!(
+            explainer.reason(&c, &"1.5".parse()?, &requests),
+            "the nearest request wins: com.ourcorp:app 1.0 asks for it at depth 2, over 1.0 from com.ourcorp:a 1.0",
+        );
+        assert_eq!(explainer.reason(&c, &"1.0".parse()?, &requests[..0]), "kept at the version already in gallade.lock");
+
+        // the lockfile says who was forced, not whatever gallade.toml says now
+        lockfile.deps.get_mut("com.ourcorp:c").unwrap().overridden = true;
+        let explainer = Explainer::new(&resolver, &manifest, &lockfile)?;
+        assert_eq!(explainer.reason(&c, &"1.0".parse()?, &requests), "forced to 1.0 by [overrides] in gallade.toml");
+
+        // a hand-edited lockfile is an error, not a panic
+        lockfile.deps.get_mut("com.ourcorp:c").unwrap().deps.push("nonsense".to_string());
+        let err = Explainer::new(&resolver, &manifest, &lockfile).err().unwrap().to_string();
+        assert_eq!(err, "bad dependency nonsense of com.ourcorp:c in gallade.lock");
+
+        Ok(())
+    }
+}
@@ -0,0 +1,127 @@
+use quick_xml::events::Event;
+use quick_xml::Reader;
+
+/// Minimal element tree for documents like POMs where the interesting parts
+/// (plugin configuration, properties, profiles) don't have a fixed schema
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Element {
+    pub name: String,
+    pub attributes: Vec<(String, String)>,
+    pub text: String,
+    pub children: Vec<Element>,
+}
+
+impl Element {
+    pub fn parse(xml: &str) -> anyhow::Result<Element> {
+        let mut reader = Reader::from_str(xml);
+        let mut stack: Vec<Element> = Vec::new();
+
+        loop {
+            match reader.read_event()? {
+                Event::Start(start) => {
+                    let mut element = Element {
+                        name: String::from_utf8_lossy(start.local_name().as_ref()).into_owned(),
+                        ..Default::default()
+                    };
+                    for attr in start.attributes().flatten() {
+                        element.attributes.push((
+                            String::from_utf8_lossy(attr.key.local_name().as_ref()).into_owned(),
+                            attr.unescape_value()?.into_owned(),
+                        ));
+                    }
+                    stack.push(element);
+                }
+                Event::Empty(empty) => {
+                    let element = Element {
+                        name: String::from_utf8_lossy(empty.local_name().as_ref()).into_owned(),
+                        ..Default::default()
+                    };
+                    match stack.last_mut() {
+                        Some(parent) => parent.children.push(element),
+                        None => return Ok(element),
+                    }
+                }
+                Event::Text(text) => {
+                    if let Some(current) = stack.last_mut() {
+                        current.text.push_str(&text.unescape()?);
+                    }
+                }
+                Event::CData(data) => {
+                    if let Some(current) = stack.last_mut() {
+                        current.text.push_str(&String::from_utf8_lossy(&data));
+                    }
+                }
+                Event::End(_) => {
+                    let mut element = stack.pop()
+                        .ok_or_else(|| anyhow::anyhow!("unbalanced closing tag"))?;
+                    element.text = element.text.trim().to_string();
+                    match stack.last_mut() {
+                        Some(parent) => parent.children.push(element),
+                        None => return Ok(element),
+                    }
+                }
+                Event::Eof => anyhow::bail!("unexpected end of document"),
+                _ => {}
+            }
+        }
+    }
+
+    pub fn child(&self, name: &str) -> Option<&Element> {
+        self.children.iter().find(|c| c.name == name)
+    }
+
+    pub fn children<'a>(&'a self, name: &'a str) -> impl Iterator<Item = &'a Element> + 'a {
+        self.children.iter().filter(move |c| c.name == name)
+    }
+
+    /// Follows a slash-separated path of child names, e.g. `build/sourceDirectory`
+    pub fn find(&self, path: &str) -> Option<&Element> {
+        path.split('/').try_fold(self, |element, name| element.child(name))
+    }
+
+    /// Trimmed text at `path`, None when missing or empty
+    pub fn text_at(&self, path: &str) -> Option<String> {
+        self.find(path)
+            .map(|e| e.text.clone())
+            .filter(|text| !text.is_empty())
+    }
+
+    /// Every element with `name` below this one, at any depth
+    pub fn descendants<'a>(&'a self, name: &'a str) -> Vec<&'a Element> {
+        let mut found = Vec::new();
+        for child in &self.children {
+            if child.name == name {
+                found.push(child);
+            }
+            found.extend(child.descendants(name));
+        }
+        found
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_tree() -> anyhow::Result<()> {
+        let root = Element::parse(r#"<?xml version="1.0"?>
+            <project xmlns="http://maven.apache.org/POM/4.0.0">
+                <!-- comment -->
+                <properties><guava.version>31.1-jre</guava.version><empty/></properties>
+                <build><plugins><plugin><configuration>
+                    <mainClass><![CDATA[com.ourcorp.App]]></mainClass>
+                </configuration></plugin></plugins></build>
+                <name>a &amp; b</name>
+            </project>
+        "#)?;
+
+        assert_eq!(root.name, "project");
+        assert_eq!(root.text_at("properties/guava.version").as_deref(), Some("31.1-jre"));
+        assert_eq!(root.text_at("properties/empty"), None);
+        assert_eq!(root.descendants("mainClass")[0].text, "com.ourcorp.App");
+        assert_eq!(root.text_at("name").as_deref(), Some("a & b"));
+
+        Ok(())
+    }
+}